@@ -0,0 +1,116 @@
+use epub::doc::EpubDoc;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use zip::ZipArchive;
+use crate::book::FontObfuscation;
+
+/// 已打开的书的共享句柄，阅读器和资源协议都从同一份数据按需读取
+#[derive(Clone, Debug)]
+pub struct BookArchive {
+    source: Source,
+    fonts: Arc<FontObfuscation>,
+}
+
+#[derive(Clone, Debug)]
+enum Source {
+    Epub(Arc<Mutex<EpubDoc<BufReader<File>>>>),
+    // 由 TXT 等其他格式转换来的书，章节和图片都在内存中，按完整路径存放
+    Memory(Arc<HashMap<String, (Vec<u8>, String)>>),
+    // CBZ 漫画，图片按需从压缩包读取，生成的页面在内存中
+    Comic {
+        archive: Arc<Mutex<ZipArchive<BufReader<File>>>>,
+        pages: Arc<HashMap<String, (Vec<u8>, String)>>,
+    },
+}
+
+impl BookArchive {
+    pub(crate) fn new(doc: EpubDoc<BufReader<File>>, fonts: FontObfuscation) -> Self {
+        Self {
+            source: Source::Epub(Arc::new(Mutex::new(doc))),
+            fonts: Arc::new(fonts),
+        }
+    }
+
+    /// 转换后的书：资源的完整路径 -> (内容, MIME 类型)
+    pub(crate) fn from_resources(resources: HashMap<String, (Vec<u8>, String)>) -> Self {
+        Self {
+            source: Source::Memory(Arc::new(resources)),
+            fonts: Arc::new(FontObfuscation::default()),
+        }
+    }
+
+    /// 漫画压缩包，`pages` 为生成的页面：完整路径 -> (内容, MIME 类型)
+    pub(crate) fn from_comic(archive: ZipArchive<BufReader<File>>, pages: HashMap<String, (Vec<u8>, String)>) -> Self {
+        Self {
+            source: Source::Comic { archive: Arc::new(Mutex::new(archive)), pages: Arc::new(pages) },
+            fonts: Arc::new(FontObfuscation::default()),
+        }
+    }
+
+    fn doc(doc: &Mutex<EpubDoc<BufReader<File>>>) -> MutexGuard<'_, EpubDoc<BufReader<File>>> {
+        // 读取资源时出现 panic 不会破坏文档本身，继续使用即可
+        doc.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 按压缩包内的完整路径读取资源，返回内容和 MIME 类型。被混淆的字体读取时自动还原。
+    pub fn read(&self, path: &str) -> Option<(Vec<u8>, String)> {
+        let doc = match &self.source {
+            Source::Epub(doc) => doc,
+            Source::Memory(resources) => return resources.get(path).cloned(),
+            Source::Comic { archive, pages } => {
+                if let Some(page) = pages.get(path) {
+                    return Some(page.clone());
+                }
+                let mut archive = archive.lock().unwrap_or_else(|e| e.into_inner());
+                let mut entry = archive.by_name(path).ok()?;
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).ok()?;
+                return Some((data, guess_mime(Path::new(path)).to_string()));
+            }
+        };
+        let mut doc = Self::doc(doc);
+        let mut data = doc.get_resource_by_path(path)?;
+        self.fonts.deobfuscate(path, &mut data);
+
+        let guessed = guess_mime(Path::new(path));
+        let mime = match doc.get_resource_mime_by_path(path) {
+            // 旧书常把字体登记为 application/x-font-ttf 等，按扩展名给出标准类型
+            Some(_) if guessed.starts_with("font/") => guessed.to_string(),
+            Some(mime) => mime,
+            None => guessed.to_string(),
+        };
+        Some((data, mime))
+    }
+}
+
+/// 清单中没有登记的资源按扩展名推断 MIME 类型
+pub fn guess_mime(path: &Path) -> &'static str {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| match ext.to_lowercase().as_str() {
+            "jpg" | "jpeg" => "image/jpeg",
+            "png" => "image/png",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            "svg" => "image/svg+xml",
+            "bmp" => "image/bmp",
+            "css" => "text/css",
+            "xhtml" | "xht" => "application/xhtml+xml",
+            "html" | "htm" => "text/html",
+            "ttf" => "font/ttf",
+            "otf" => "font/otf",
+            "woff" => "font/woff",
+            "woff2" => "font/woff2",
+            "mp3" => "audio/mpeg",
+            "m4a" => "audio/mp4",
+            "ogg" | "oga" => "audio/ogg",
+            "mp4" | "m4v" => "video/mp4",
+            "webm" => "video/webm",
+            "smil" => "application/smil+xml",
+            _ => "application/octet-stream",
+        })
+        .unwrap_or("application/octet-stream")
+}
@@ -0,0 +1,145 @@
+use std::collections::{HashMap, VecDeque};
+use crate::book::Chapter;
+
+/// 默认最多缓存 32MB 处理后的章节
+pub const DEFAULT_CHAPTER_CACHE_BYTES: usize = 32 * 1024 * 1024;
+
+/// 按书脊序号缓存处理后的章节，超过内存上限时淘汰最久未读的章节
+#[derive(Debug)]
+pub struct ChapterCache {
+    entries: HashMap<usize, Chapter>,
+    order: VecDeque<usize>,  // 队首是最久未读的
+    size: usize,
+    limit: usize,
+}
+
+impl ChapterCache {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            size: 0,
+            limit,
+        }
+    }
+
+    pub fn get(&mut self, spine_index: usize) -> Option<Chapter> {
+        let chapter = self.entries.get(&spine_index)?.clone();
+        self.touch(spine_index);
+        Some(chapter)
+    }
+
+    pub fn insert(&mut self, spine_index: usize, chapter: Chapter) {
+        // 单个章节超过上限时不缓存，避免把其他章节全部挤掉
+        let size = chapter_size(&chapter);
+        if size > self.limit {
+            return;
+        }
+        if let Some(old) = self.entries.insert(spine_index, chapter) {
+            self.size -= chapter_size(&old);
+        }
+        self.size += size;
+        self.touch(spine_index);
+        self.evict();
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.evict();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.size = 0;
+    }
+
+    /// 当前缓存占用的字节数
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn touch(&mut self, spine_index: usize) {
+        self.order.retain(|&idx| idx != spine_index);
+        self.order.push_back(spine_index);
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.limit {
+            let Some(oldest) = self.order.pop_front() else { break };
+            if let Some(chapter) = self.entries.remove(&oldest) {
+                self.size -= chapter_size(&chapter);
+            }
+        }
+    }
+}
+
+fn chapter_size(chapter: &Chapter) -> usize {
+    chapter.content.len() + chapter.styles.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::*;
+
+    // 大小为 size 字节的章节
+    fn chapter(size: usize) -> Chapter {
+        Chapter {
+            id: String::new(),
+            content: "a".repeat(size),
+            styles: String::new(),
+            path: PathBuf::new(),
+            play_order: 0,
+            processed: true,
+        }
+    }
+
+    fn cached(cache: &mut ChapterCache) -> Vec<usize> {
+        (0..10).filter(|&i| cache.get(i).is_some()).collect()
+    }
+
+    #[test]
+    fn evicts_least_recently_read() {
+        let mut cache = ChapterCache::new(100);
+        cache.insert(0, chapter(40));
+        cache.insert(1, chapter(40));
+        assert!(cache.get(0).is_some());
+        // 超过上限时淘汰最久未读的 1，而不是最早插入的 0
+        cache.insert(2, chapter(40));
+        assert_eq!(cache.size(), 80);
+        assert_eq!(cached(&mut cache), [0, 2]);
+        // 替换同一章节时按新的大小计算
+        cache.insert(2, chapter(10));
+        assert_eq!(cache.size(), 50);
+        cache.clear();
+        assert_eq!(cache.size(), 0);
+        assert!(cached(&mut cache).is_empty());
+    }
+
+    #[test]
+    fn skips_oversized_chapters() {
+        let mut cache = ChapterCache::new(100);
+        cache.insert(0, chapter(60));
+        cache.insert(1, Chapter { styles: "b".repeat(50), ..chapter(51) });
+        assert_eq!(cache.size(), 60);
+        assert_eq!(cached(&mut cache), [0]);
+        cache.insert(2, chapter(100));
+        assert_eq!(cached(&mut cache), [2]);
+    }
+
+    #[test]
+    fn lowering_the_limit_evicts() {
+        let mut cache = ChapterCache::new(DEFAULT_CHAPTER_CACHE_BYTES);
+        for i in 0..4 {
+            cache.insert(i, chapter(30));
+        }
+        cache.get(0);
+        cache.set_limit(60);
+        assert_eq!(cache.size(), 60);
+        assert_eq!(cached(&mut cache), [0, 3]);
+        cache.set_limit(0);
+        assert_eq!(cache.size(), 0);
+        assert!(cached(&mut cache).is_empty());
+    }
+}
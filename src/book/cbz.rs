@@ -0,0 +1,374 @@
+// CBZ 漫画：压缩包中的图片按文件名的自然顺序排列，每张图片生成一个固定版式页面，
+// 子文件夹组成目录。压缩包中有 ComicInfo.xml 时读取书名、作者和翻页方向。
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek};
+use std::path::PathBuf;
+use percent_encoding::utf8_percent_encode;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use zip::ZipArchive;
+use crate::book::convert::{escape_text, number_toc, Converted};
+use crate::book::html_processor::PATH_SEGMENT;
+use crate::book::metadata::PackageMetadata;
+use crate::book::{decode_text, guess_mime, BookContent, Contributor, ItemRendition, Layout, NavPoint, PageDirection, Rendition, Series};
+
+// 生成的页面的存放位置，避免和压缩包中的文件重名
+const PAGE_DIR: &str = "_pages";
+
+// 读取图片尺寸时最多解压的字节数，尺寸都在文件头中
+const HEADER_BYTES: u64 = 64 * 1024;
+
+// 图片铺满页面
+const COMIC_CSS: &str = "body { margin: 0; } img { display: block; width: 100%; height: 100%; object-fit: contain; }";
+
+/// 把漫画压缩包转换为书的结构，返回的资源只有生成的页面，图片仍从压缩包读取。
+/// `title` 为 ComicInfo.xml 没有书名时使用的书名，通常取文件名。压缩包中没有图片时返回 `None`。
+pub(crate) fn import_cbz<R: Read + Seek>(archive: &mut ZipArchive<R>, title: &str) -> Option<Converted> {
+    let mut images: Vec<String> = archive.file_names()
+        .filter(|name| !name.ends_with('/') && !name.starts_with("__MACOSX/"))
+        .filter(|name| !name.rsplit('/').next().unwrap_or_default().starts_with('.'))
+        .filter(|name| guess_mime(name.as_ref()).starts_with("image/"))
+        .map(str::to_string)
+        .collect();
+    if images.is_empty() {
+        return None;
+    }
+    images.sort_by(|a, b| natural_cmp(a, b));
+
+    let info = archive.by_name("ComicInfo.xml").ok()
+        .and_then(|mut entry| {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).ok()?;
+            Some(comic_info(&decode_text(&data)))
+        })
+        .unwrap_or_default();
+
+    // 所有图片都在同一个顶层文件夹中时，这一层不算目录
+    let common = common_dir(&images);
+    let mut spine = Vec::new();
+    let mut manifest = HashMap::new();
+    let mut resources = HashMap::new();
+    let mut toc: Vec<NavPoint> = Vec::new();
+    for (i, image) in images.iter().enumerate() {
+        let id = format!("page{}", i + 1);
+        let path = format!("{}/{:04}.xhtml", PAGE_DIR, i + 1);
+        let page = page_html(i + 1, image, image_size(archive, image));
+        manifest.insert(id.clone(), (PathBuf::from(&path), "application/xhtml+xml".to_string()));
+        manifest.insert(format!("image{}", i + 1), (PathBuf::from(image), guess_mime(image.as_ref()).to_string()));
+        resources.insert(path.clone(), (page.into_bytes(), "application/xhtml+xml".to_string()));
+        spine.push(id);
+
+        let folders: Vec<&str> = image[common.len()..].split('/').collect();
+        add_folders(&mut toc, &folders[..folders.len() - 1], &path);
+    }
+
+    let title = info.get("Title").cloned()
+        .or_else(|| {
+            let series = info.get("Series")?;
+            Some(match info.get("Number") {
+                Some(number) => format!("{} {}", series, number),
+                None => series.clone(),
+            })
+        })
+        .unwrap_or_else(|| title.to_string());
+    // 没有子文件夹时目录只有一项，指向第一页
+    if toc.is_empty() {
+        toc.push(NavPoint {
+            label: title.clone(),
+            content: PathBuf::from(format!("{}/{:04}.xhtml", PAGE_DIR, 1)),
+            children: Vec::new(),
+            play_order: 0,
+        });
+    }
+    number_toc(&mut toc);
+
+    let (metadata, package) = metadata(&info, title);
+    let items = spine.len();
+    let mut content = BookContent::from_parts(spine, manifest, toc, metadata);
+    content.package_metadata = package;
+    content.cover_id = Some("image1".to_string());
+    content.extra_css.push(COMIC_CSS.to_string());
+    content.rendition = Rendition {
+        layout: Layout::PrePaginated,
+        direction: if info.get("Manga").is_some_and(|m| m == "YesAndRightToLeft") {
+            PageDirection::Rtl
+        } else {
+            PageDirection::Default
+        },
+        items: vec![ItemRendition { layout: Layout::PrePaginated, page_spread: None }; items],
+        comic: true,
+        ..Rendition::default()
+    };
+    Some(Converted { content, resources })
+}
+
+// 一张图片的页面，视口取图片尺寸。图片地址从压缩包根目录算起
+fn page_html(number: usize, image: &str, size: Option<(u32, u32)>) -> String {
+    let viewport = size
+        .map(|(width, height)| format!("<meta name=\"viewport\" content=\"width={}, height={}\"/>", width, height))
+        .unwrap_or_default();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\"><head><title>第 {} 页</title>{}</head>\n\
+         <body><img src=\"/{}\" alt=\"\"/></body></html>\n",
+        number,
+        viewport,
+        escape_text(&utf8_percent_encode(image, PATH_SEGMENT).to_string()),
+    )
+}
+
+// 只解压文件头来读取图片尺寸
+fn image_size<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<(u32, u32)> {
+    let entry = archive.by_name(name).ok()?;
+    let mut header = Vec::new();
+    entry.take(HEADER_BYTES).read_to_end(&mut header).ok()?;
+    image::ImageReader::new(Cursor::new(header))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+// 所有图片共同所在的文件夹（含末尾的 '/'），图片分散在多个顶层文件夹时为空
+fn common_dir(images: &[String]) -> String {
+    let first = images[0].rsplit_once('/').map(|(dir, _)| dir).unwrap_or_default();
+    let mut common = first;
+    for image in &images[1..] {
+        while !common.is_empty() && !image.starts_with(&format!("{}/", common)) {
+            common = common.rsplit_once('/').map(|(dir, _)| dir).unwrap_or_default();
+        }
+    }
+    if common.is_empty() { String::new() } else { format!("{}/", common) }
+}
+
+// 每个文件夹一个目录项，指向其中的第一页；图片已排序，同一文件夹的页面是连续的
+fn add_folders(toc: &mut Vec<NavPoint>, folders: &[&str], page: &str) {
+    let Some((folder, rest)) = folders.split_first() else { return };
+    match toc.last() {
+        Some(nav) if nav.label == *folder => {}
+        _ => toc.push(NavPoint {
+            label: folder.to_string(),
+            content: PathBuf::from(page),
+            children: Vec::new(),
+            play_order: 0,
+        }),
+    }
+    if let Some(last) = toc.last_mut() {
+        add_folders(&mut last.children, rest, page);
+    }
+}
+
+// 按自然顺序比较文件名：数字部分按数值比较，page2 排在 page10 之前
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+                        digits.push(c);
+                        chars.next();
+                    }
+                    digits
+                };
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let (x_trimmed, y_trimmed) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_trimmed.len().cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+// ComicInfo.xml 根元素下各字段的文本
+fn comic_info(xml: &str) -> HashMap<String, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut fields = HashMap::new();
+    let mut depth = 0;
+    let mut current: Option<String> = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                depth += 1;
+                current = (depth == 2).then(|| String::from_utf8_lossy(e.local_name().as_ref()).into_owned());
+            }
+            Ok(Event::Text(t)) => {
+                let (Some(name), Ok(text)) = (current.as_ref(), t.unescape()) else { continue };
+                if !text.trim().is_empty() {
+                    fields.insert(name.clone(), text.trim().to_string());
+                }
+            }
+            Ok(Event::End(_)) => {
+                depth -= 1;
+                current = None;
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    fields
+}
+
+fn metadata(info: &HashMap<String, String>, title: String) -> (HashMap<String, Vec<String>>, PackageMetadata) {
+    let list = |name: &str| -> Vec<String> {
+        info.get(name)
+            .map(|value| value.split(',').map(str::trim).filter(|v| !v.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    let writers = list("Writer");
+
+    let mut metadata = HashMap::new();
+    metadata.insert("title".to_string(), vec![title]);
+    if !writers.is_empty() {
+        metadata.insert("creator".to_string(), writers.clone());
+    }
+    if let Some(summary) = info.get("Summary") {
+        metadata.insert("description".to_string(), vec![summary.clone()]);
+    }
+
+    let contributors = ["Penciller", "Inker", "Colorist", "Letterer", "Translator"].iter()
+        .flat_map(|role| list(role).into_iter().map(move |name| (name, *role)))
+        .map(|(name, role)| Contributor {
+            name,
+            role: Some(if role == "Translator" { "trl" } else { "ill" }.to_string()),
+            file_as: None,
+        })
+        .collect();
+    let package = PackageMetadata {
+        creators: writers.into_iter()
+            .map(|name| Contributor { name, role: Some("aut".to_string()), file_as: None })
+            .collect(),
+        contributors,
+        language: info.get("LanguageISO").cloned(),
+        publisher: info.get("Publisher").cloned(),
+        date: info.get("Year").cloned(),
+        subjects: list("Genre"),
+        identifiers: Vec::new(),
+        series: info.get("Series").map(|name| Series {
+            name: name.clone(),
+            index: info.get("Number").and_then(|n| n.parse().ok()),
+        }),
+    };
+    (metadata, package)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        image::RgbImage::new(width, height).write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    fn comic(files: &[(&str, Vec<u8>)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        ZipArchive::new(zip.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn natural_order() {
+        assert_eq!(natural_cmp("page2.jpg", "page10.jpg"), Ordering::Less);
+        assert_eq!(natural_cmp("page10.jpg", "page9.jpg"), Ordering::Greater);
+        assert_eq!(natural_cmp("Page1.jpg", "page1.jpg"), Ordering::Equal);
+        // 数值相同时前导零少的在前
+        assert_eq!(natural_cmp("p1.jpg", "p01.jpg"), Ordering::Less);
+        assert_eq!(natural_cmp("vol1/p9.jpg", "vol2/p1.jpg"), Ordering::Less);
+        let mut names = vec!["c10/1.png", "c2/10.png", "c2/2.png", "c1.png"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["c1.png", "c2/2.png", "c2/10.png", "c10/1.png"]);
+    }
+
+    #[test]
+    fn common_folder() {
+        let images = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(common_dir(&images(&["Book/Ch1/1.jpg", "Book/Ch2/1.jpg"])), "Book/");
+        assert_eq!(common_dir(&images(&["Book/Ch1/1.jpg", "Book/Ch1/2.jpg"])), "Book/Ch1/");
+        assert_eq!(common_dir(&images(&["A/1.jpg", "B/1.jpg"])), "");
+        assert_eq!(common_dir(&images(&["1.jpg"])), "");
+        // 只有前缀相同的文件夹不算共同文件夹
+        assert_eq!(common_dir(&images(&["Book/1.jpg", "Book2/1.jpg"])), "");
+    }
+
+    #[test]
+    fn nested_folders_become_toc() {
+        let mut archive = comic(&[
+            ("Book/Vol 1/Ch 10/1.png", png(1, 1)),
+            ("Book/Vol 1/Ch 2/1.png", png(1, 1)),
+            ("Book/Vol 1/Ch 2/2.png", png(2, 3)),
+            ("Book/Vol 2/1.png", png(1, 1)),
+            ("Book/.hidden.png", png(1, 1)),
+            ("__MACOSX/Book/._1.png", Vec::new()),
+            ("Book/notes.txt", Vec::new()),
+        ]);
+        let converted = import_cbz(&mut archive, "file").unwrap();
+        let content = &converted.content;
+        assert_eq!(content.spine.len(), 4);
+        assert_eq!(content.resources["image1"].0, PathBuf::from("Book/Vol 1/Ch 2/1.png"));
+        let toc: Vec<(&str, Vec<&str>)> = content.toc.iter()
+            .map(|nav| (nav.label.as_str(), nav.children.iter().map(|c| c.label.as_str()).collect()))
+            .collect();
+        assert_eq!(toc, [("Vol 1", vec!["Ch 2", "Ch 10"]), ("Vol 2", vec![])]);
+        assert_eq!(content.toc[0].children[1].content, PathBuf::from("_pages/0003.xhtml"));
+        assert_eq!(content.toc[1].content, PathBuf::from("_pages/0004.xhtml"));
+
+        let page = String::from_utf8(converted.resources["_pages/0002.xhtml"].0.clone()).unwrap();
+        assert!(page.contains(r#"content="width=2, height=3""#));
+        assert!(page.contains(r#"src="/Book/Vol%201/Ch%202/2.png""#));
+        assert_eq!(content.metadata["title"], ["file"]);
+        assert_eq!(content.rendition.direction, PageDirection::Default);
+    }
+
+    #[test]
+    fn comic_info_manga() {
+        let info = r#"<?xml version="1.0"?>
+<ComicInfo xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <Series>Saga</Series>
+  <Number>3</Number>
+  <Writer>A, B</Writer>
+  <Penciller>C</Penciller>
+  <Manga>YesAndRightToLeft</Manga>
+</ComicInfo>"#;
+        let mut archive = comic(&[("1.png", png(1, 1)), ("ComicInfo.xml", info.as_bytes().to_vec())]);
+        let content = import_cbz(&mut archive, "file").unwrap().content;
+        assert_eq!(content.rendition.direction, PageDirection::Rtl);
+        assert_eq!(content.rendition.layout, Layout::PrePaginated);
+        assert_eq!(content.metadata["title"], ["Saga 3"]);
+        assert_eq!(content.metadata["creator"], ["A", "B"]);
+        assert_eq!(content.package_metadata.series, Some(Series { name: "Saga".into(), index: Some(3.0) }));
+        assert_eq!(content.package_metadata.contributors[0].role.as_deref(), Some("ill"));
+        // 没有子文件夹时目录只有一项
+        assert_eq!(content.toc.len(), 1);
+        assert_eq!(content.toc[0].label, "Saga 3");
+
+        let mut archive = comic(&[("1.png", png(1, 1)), ("ComicInfo.xml", b"<ComicInfo><Manga>Yes</Manga></ComicInfo>".to_vec())]);
+        assert_eq!(import_cbz(&mut archive, "file").unwrap().content.rendition.direction, PageDirection::Default);
+        assert!(import_cbz(&mut comic(&[("a.txt", Vec::new())]), "file").is_none());
+    }
+}
@@ -0,0 +1,443 @@
+use epub::doc::{EpubDoc, NavPoint};
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use crate::book::html_processor::title_and_heading;
+use crate::book::{decode_text, normalize_path, path_to_string, Landmark, PageTarget};
+use crate::book::nav::{parse_nav, parse_ncx_page_list, parse_package_nav};
+use crate::book::metadata::{parse_package_metadata, PackageMetadata};
+use crate::book::rendition::parse_rendition;
+use crate::book::media_overlay::{parse_overlay_index, OverlayIndex};
+use crate::book::{Contributor, Identifier, Rendition, Series};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub id: String,
+    pub content: String,
+    pub styles: String,  // 限定在正文容器内的书籍样式
+    pub path: PathBuf,
+    pub play_order: usize,
+    pub processed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookMetadata {
+    pub unique_identifier: Option<String>,
+    pub title: Option<String>,
+    pub author: Option<String>,  // 主要作者，多位时用顿号连接
+    pub description: Option<String>,
+    pub cover_id: Option<String>,
+    pub creators: Vec<Contributor>,
+    pub contributors: Vec<Contributor>,
+    pub language: Option<String>,
+    pub publisher: Option<String>,
+    pub date: Option<String>,
+    pub subjects: Vec<String>,
+    pub identifiers: Vec<Identifier>,
+    pub series: Option<Series>,
+    pub chapter_count: usize,
+    pub order_path: HashMap<usize, PathBuf>,
+    pub spine_to_order: HashMap<usize, usize>,  // Add this field
+    pub order_to_spine: HashMap<usize, usize>,  // Add this field
+}
+
+impl BookMetadata {
+    pub fn empty() -> Self {
+        Self {
+            unique_identifier: None,
+            title: None,
+            author: None,
+            description: None,
+            cover_id: None,
+            creators: Vec::new(),
+            contributors: Vec::new(),
+            language: None,
+            publisher: None,
+            date: None,
+            subjects: Vec::new(),
+            identifiers: Vec::new(),
+            series: None,
+            chapter_count: 0,
+            order_path: HashMap::new(),
+            spine_to_order: HashMap::new(),
+            order_to_spine: HashMap::new(),
+        }
+    }
+
+    /// 主要作者：角色为 aut 的创作者，没有角色信息时取第一个创作者
+    pub fn authors(&self) -> Vec<&Contributor> {
+        let authors: Vec<&Contributor> = self.creators.iter()
+            .filter(|c| c.role.as_deref() == Some("aut"))
+            .collect();
+        if authors.is_empty() {
+            self.creators.iter().take(1).collect()
+        } else {
+            authors
+        }
+    }
+
+    /// 书的 ISBN，取注明为 ISBN 的标识符
+    pub fn isbn(&self) -> Option<&str> {
+        self.identifiers.iter()
+            .find(|id| id.scheme.as_deref().is_some_and(|s| s.eq_ignore_ascii_case("isbn")))
+            .map(|id| id.value.trim_start_matches("urn:isbn:"))
+    }
+}
+
+impl From<&BookContent> for BookMetadata {
+    fn from(content: &BookContent) -> Self {
+        let package = content.package_metadata.clone();
+        let mut metadata = Self {
+            unique_identifier: content.unique_identifier.clone(),
+            title: content.metadata.get("title").and_then(|v| v.first()).cloned(),
+            author: content.metadata.get("creator").and_then(|v| v.first()).cloned(),
+            description: content.metadata.get("description").and_then(|v| v.first()).cloned(),
+            cover_id: content.cover_id.clone(),
+            creators: package.creators,
+            contributors: package.contributors,
+            language: package.language,
+            publisher: package.publisher,
+            date: package.date,
+            subjects: package.subjects,
+            identifiers: package.identifiers,
+            series: package.series,
+            chapter_count: content.spine.len(),
+            order_path: content.order_path.clone(),
+            spine_to_order: content.spine_to_order.clone(),
+            order_to_spine: content.order_to_spine.clone(),
+        };
+        let authors: Vec<&str> = metadata.authors().iter().map(|c| c.name.as_str()).collect();
+        if !authors.is_empty() {
+            metadata.author = Some(authors.join("、"));
+        }
+        metadata
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookContent {
+    pub current: usize,
+    pub spine: Vec<String>,
+    pub resources: HashMap<String, (PathBuf, String)>,
+    pub toc: Vec<NavPoint>,
+    pub metadata: HashMap<String, Vec<String>>,
+    pub root_base: PathBuf,
+    pub root_file: PathBuf,
+    pub extra_css: Vec<String>,
+    pub unique_identifier: Option<String>,
+    pub cover_id: Option<String>,
+    pub order_path: HashMap<usize, PathBuf>,
+    pub spine_to_order: HashMap<usize, usize>,  // Add this field
+    pub order_to_spine: HashMap<usize, usize>,  // Add this field
+    pub landmarks: Vec<Landmark>,
+    pub page_list: Vec<PageTarget>,
+    pub rendition: Rendition,
+    pub(crate) package_metadata: PackageMetadata,
+    pub(crate) overlays: OverlayIndex,
+}
+
+impl BookContent {
+    pub fn empty() -> Self {
+        Self {
+            current: 0,
+            spine: Vec::new(),
+            resources: HashMap::new(),
+            toc: Vec::new(),
+            metadata: HashMap::new(),
+            root_base: PathBuf::new(),
+            root_file: PathBuf::new(),
+            extra_css: Vec::new(),
+            unique_identifier: None,
+            cover_id: None,
+            order_path: HashMap::new(),
+            spine_to_order: HashMap::new(),
+            order_to_spine: HashMap::new(),
+            landmarks: Vec::new(),
+            page_list: Vec::new(),
+            rendition: Rendition::default(),
+            package_metadata: PackageMetadata::default(),
+            overlays: OverlayIndex::default(),
+        }
+    }
+
+    fn expand_toc(toc: Vec<NavPoint>) -> Vec<(usize, PathBuf)> {
+        let mut result = Vec::new();
+
+        // 遍历每个章节
+        for nav in toc {
+            // 添加当前章节路径
+            result.push((nav.play_order, nav.content.clone()));
+
+            // 递归展开子章节
+            if !nav.children.is_empty() {
+                result.extend(Self::expand_toc(nav.children.clone()));
+            }
+        }
+
+        result
+    }
+
+    // 借用已打开的文档，调用方可以继续用同一个 EpubDoc 读取章节
+    pub(crate) fn from_epub<R: Read + Seek>(doc: &mut EpubDoc<R>) -> Self {
+        // 章节内容在阅读时按需读取，这里只解析目录和书脊的对应关系
+        let opf_path = path_to_string(&doc.root_file);
+        let opf = doc.get_resource_by_path(&opf_path).map(|data| decode_text(&data).into_owned()).unwrap_or_default();
+        let (nav_path, guide) = parse_package_nav(&opf, &opf_path);
+        let nav = nav_path
+            .and_then(|path| doc.get_resource_by_path(&path).map(|data| parse_nav(&decode_text(&data), &path)))
+            .unwrap_or_default();
+
+        // 页码表优先取导航文档的 page-list，没有时取 NCX 的 pageList
+        let page_list = if nav.page_list.is_empty() {
+            let ncx_path = doc.resources.values()
+                .find(|(_, mime)| mime == "application/x-dtbncx+xml")
+                .map(|(path, _)| path_to_string(path));
+            ncx_path
+                .and_then(|path| doc.get_resource_by_path(&path).map(|data| parse_ncx_page_list(&decode_text(&data), &path)))
+                .unwrap_or_default()
+        } else {
+            nav.page_list.clone()
+        };
+
+        // 只有导航文档没有 NCX 的 EPUB3 书，目录取自导航文档
+        let mut toc = if doc.toc.is_empty() { nav.toc } else { doc.toc.clone() };
+        let (mut order_path, mut spine_to_order, mut order_to_spine) =
+            Self::map_toc(&toc, &doc.spine, &doc.resources);
+
+        // 目录缺失或没有一项能对应到书脊时，按书脊生成目录
+        if order_to_spine.is_empty() {
+            toc = Self::spine_toc(doc);
+            (order_path, spine_to_order, order_to_spine) =
+                Self::map_toc(&toc, &doc.spine, &doc.resources);
+        }
+
+        Self {
+            current: 0,
+            spine: doc.spine.clone(),
+            resources: doc.resources.clone(),
+            toc,
+            metadata: doc.metadata.clone(),
+            root_base: doc.root_base.clone(),
+            root_file: doc.root_file.clone(),
+            extra_css: doc.extra_css.clone(),
+            unique_identifier: doc.unique_identifier.clone(),
+            cover_id: doc.cover_id.clone(),
+            order_path,
+            spine_to_order,
+            order_to_spine,
+            landmarks: if nav.landmarks.is_empty() { guide } else { nav.landmarks },
+            page_list,
+            rendition: parse_rendition(&opf),
+            package_metadata: parse_package_metadata(&opf),
+            overlays: parse_overlay_index(&opf, &opf_path),
+        }
+    }
+
+    /// 由其他格式转换来的书：直接给出书脊、清单、目录和元数据（"title"、"creator" 等）
+    pub(crate) fn from_parts(
+        spine: Vec<String>,
+        resources: HashMap<String, (PathBuf, String)>,
+        toc: Vec<NavPoint>,
+        metadata: HashMap<String, Vec<String>>,
+    ) -> Self {
+        let (order_path, spine_to_order, order_to_spine) = Self::map_toc(&toc, &spine, &resources);
+        Self {
+            spine,
+            resources,
+            toc,
+            metadata,
+            order_path,
+            spine_to_order,
+            order_to_spine,
+            ..Self::empty()
+        }
+    }
+
+    // 目录顺序号到路径、书脊序号的双向对应关系
+    fn map_toc(
+        toc: &[NavPoint],
+        spine: &[String],
+        resources: &HashMap<String, (PathBuf, String)>,
+    ) -> (HashMap<usize, PathBuf>, HashMap<usize, usize>, HashMap<usize, usize>) {
+        let chapter_paths: Vec<(usize, PathBuf)> = Self::expand_toc(toc.to_vec());
+
+        let mut order_path = HashMap::new();
+        let mut spine_to_order = HashMap::new();
+        let mut order_to_spine = HashMap::new();
+
+        // Store all order-path mappings
+        for (play_order, path) in chapter_paths.iter() {
+            order_path.insert(*play_order, path.clone());
+        }
+
+        // 先按规范化路径分组目录项，避免对每个书脊项都遍历整个目录
+        let mut orders_by_path: HashMap<String, Vec<usize>> = HashMap::new();
+        for (play_order, order_path) in chapter_paths.iter() {
+            let normalized_order = toc_path(order_path);
+            orders_by_path.entry(normalized_order).or_default().push(*play_order);
+        }
+
+        // Map each spine entry to order
+        for (spine_idx, spine_id) in spine.iter().enumerate() {
+            // Get the full path for this spine ID from resources
+            if let Some((spine_path, _)) = resources.get(spine_id) {
+                let normalized_spine = toc_path(spine_path);
+
+                // Find orders that reference this spine path
+                if let Some(orders) = orders_by_path.get(&normalized_spine) {
+                    for play_order in orders {
+                        order_to_spine.insert(*play_order, spine_idx);
+                    }
+                    if let Some(min_order) = orders.iter().min() {
+                        spine_to_order.insert(spine_idx, *min_order);
+                    }
+                }
+            }
+        }
+
+        (order_path, spine_to_order, order_to_spine)
+    }
+
+    // 每个书脊项一个目录项，标题取文档的 <title>，
+    // 各章 <title> 相同（通常都是书名）或为空时取第一个标题元素
+    fn spine_toc<R: Read + Seek>(doc: &mut EpubDoc<R>) -> Vec<NavPoint> {
+        let book_title = doc.metadata.get("title").and_then(|v| v.first()).cloned();
+        let mut entries = Vec::new();
+        for spine_id in doc.spine.clone() {
+            let Some((path, mime)) = doc.resources.get(&spine_id).cloned() else { continue };
+            let (title, heading) = if matches!(mime.as_str(), "application/xhtml+xml" | "text/html") {
+                doc.get_resource(&spine_id)
+                    .map(|(data, _)| title_and_heading(&decode_text(&data)))
+                    .unwrap_or_default()
+            } else {
+                (None, None)
+            };
+            entries.push((path, title, heading));
+        }
+
+        let mut title_counts: HashMap<String, usize> = HashMap::new();
+        for (_, title, _) in entries.iter() {
+            if let Some(title) = title {
+                *title_counts.entry(title.clone()).or_default() += 1;
+            }
+        }
+
+        entries.into_iter()
+            .enumerate()
+            .map(|(idx, (path, title, heading))| {
+                let title = title.filter(|t| {
+                    title_counts.get(t) == Some(&1) && Some(t) != book_title.as_ref()
+                });
+                NavPoint {
+                    label: title.or(heading).unwrap_or_else(|| format!("第 {} 节", idx + 1)),
+                    content: path,
+                    children: Vec::new(),
+                    play_order: idx + 1,
+                }
+            })
+            .collect()
+    }
+
+    /// 打开新书时默认显示的目录项：书脊中最靠前的那一项
+    pub fn start_order(&self) -> Option<usize> {
+        self.order_to_spine.iter()
+            .min_by_key(|(play_order, spine_idx)| (**spine_idx, **play_order))
+            .map(|(play_order, _)| *play_order)
+    }
+
+    pub fn get_spine_index(&self, play_order: usize) -> Option<usize> {
+        self.order_to_spine.get(&play_order).copied()
+    }
+
+    /// 目录项指向的锚点（`#` 之后的部分）
+    pub fn anchor(&self, play_order: usize) -> Option<String> {
+        let path = self.order_path.get(&play_order)?.to_str()?;
+        path.split_once('#')
+            .map(|(_, fragment)| fragment.to_string())
+            .filter(|fragment| !fragment.is_empty())
+    }
+
+    /// 指向同一个书脊项的所有目录项及其锚点，按目录顺序排列
+    pub fn toc_anchors(&self, spine_index: usize) -> Vec<(usize, Option<String>)> {
+        let mut anchors: Vec<(usize, Option<String>)> = self.order_to_spine.iter()
+            .filter(|(_, idx)| **idx == spine_index)
+            .map(|(play_order, _)| (*play_order, self.anchor(*play_order)))
+            .collect();
+        anchors.sort();
+        anchors
+    }
+
+}
+
+// 目录项和书脊项比较用的路径：去掉锚点后规范化
+fn toc_path(path: &Path) -> String {
+    let path = path_to_string(path);
+    normalize_path(path.split('#').next().unwrap_or(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use super::*;
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+
+    // 没有 NCX 也没有导航文档的书
+    const OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:identifier id="uid">x</dc:identifier><dc:title>书名</dc:title></metadata>
+  <manifest>
+    <item id="a" href="Text/a.xhtml" media-type="application/xhtml+xml"/>
+    <item id="b" href="Text/b.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c" href="Text/c.xhtml" media-type="application/xhtml+xml"/>
+    <item id="d" href="Text/d.xhtml" media-type="application/xhtml+xml"/>
+    <item id="e" href="Text/e.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine><itemref idref="a"/><itemref idref="b"/><itemref idref="c"/><itemref idref="d"/><itemref idref="e"/></spine>
+</package>"#;
+
+    fn page(title: &str, body: &str) -> String {
+        format!(r#"<html xmlns="http://www.w3.org/1999/xhtml"><head><title>{}</title></head><body>{}</body></html>"#, title, body)
+    }
+
+    fn book(files: &[(&str, String)]) -> EpubDoc<Cursor<Vec<u8>>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        EpubDoc::from_reader(Cursor::new(zip.finish().unwrap().into_inner())).unwrap()
+    }
+
+    #[test]
+    fn spine_toc_without_ncx_or_nav() {
+        let mut doc = book(&[
+            ("META-INF/container.xml", CONTAINER.to_string()),
+            ("OEBPS/content.opf", OPF.to_string()),
+            ("OEBPS/Text/a.xhtml", page("序言", "<p>正文</p>")),
+            // <title> 是书名或与其他章节重复时取第一个标题元素
+            ("OEBPS/Text/b.xhtml", page("书名", "<h1>第一章</h1>")),
+            ("OEBPS/Text/c.xhtml", page("重复", "<div><h2>第二章</h2></div>")),
+            ("OEBPS/Text/d.xhtml", page("重复", "<h3>第三章</h3>")),
+            ("OEBPS/Text/e.xhtml", page("", "<p>没有标题</p>")),
+        ]);
+        let content = BookContent::from_epub(&mut doc);
+        let toc: Vec<(&str, PathBuf, usize)> = content.toc.iter()
+            .map(|nav| (nav.label.as_str(), nav.content.clone(), nav.play_order))
+            .collect();
+        assert_eq!(toc, [
+            ("序言", PathBuf::from("OEBPS/Text/a.xhtml"), 1),
+            ("第一章", PathBuf::from("OEBPS/Text/b.xhtml"), 2),
+            ("第二章", PathBuf::from("OEBPS/Text/c.xhtml"), 3),
+            ("第三章", PathBuf::from("OEBPS/Text/d.xhtml"), 4),
+            ("第 5 节", PathBuf::from("OEBPS/Text/e.xhtml"), 5),
+        ]);
+        // 每一项都对应到书脊
+        assert_eq!((1..=5).map(|order| content.get_spine_index(order)).collect::<Vec<_>>(), [0, 1, 2, 3, 4].map(Some));
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use crate::book::{BookContent, NavPoint};
+
+/// 转换为 EPUB 结构后的书：目录、书脊等结构，以及按完整路径存放在内存中的章节和图片
+pub(crate) struct Converted {
+    pub content: BookContent,
+    pub resources: HashMap<String, (Vec<u8>, String)>,
+}
+
+/// 生成一个章节的 XHTML 文档，`body` 是已转义的正文
+pub(crate) fn xhtml_page(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\"><head><title>{}</title></head>\n\
+         <body>{}</body></html>\n",
+        escape_text(title),
+        body,
+    )
+}
+
+/// 转义文本中的 HTML 特殊字符
+pub(crate) fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 按先序遍历给目录项编排顺序号，从 1 开始
+pub(crate) fn number_toc(toc: &mut [NavPoint]) {
+    fn number(toc: &mut [NavPoint], play_order: &mut usize) {
+        for nav in toc {
+            *play_order += 1;
+            nav.play_order = *play_order;
+            number(&mut nav.children, play_order);
+        }
+    }
+    number(toc, &mut 0);
+}
@@ -0,0 +1,32 @@
+use std::io::Cursor;
+use image::codecs::jpeg::JpegEncoder;
+use scraper::{Html, Selector};
+use crate::book::resolve_path;
+
+/// 缩略图的最大宽高（像素），按书库卡片尺寸的两倍生成
+pub const THUMBNAIL_SIZE: (u32, u32) = (240, 360);
+
+/// 把封面图片缩小为 JPEG 缩略图，无法识别的图片格式返回 None
+pub fn thumbnail(data: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(data).ok()?;
+    let (width, height) = THUMBNAIL_SIZE;
+    let thumbnail = image.thumbnail(width, height).to_rgb8();
+
+    let mut output = Cursor::new(Vec::new());
+    JpegEncoder::new_with_quality(&mut output, 85)
+        .encode_image(&thumbnail)
+        .ok()?;
+    Some(output.into_inner())
+}
+
+/// 文档中第一张图片（`<img>` 或 SVG `<image>`）的完整路径
+pub(crate) fn first_image(html: &str, document_path: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("img, image").unwrap();
+    document.select(&selector)
+        .filter_map(|el| {
+            let attrs = el.value();
+            attrs.attr("src").or_else(|| attrs.attr("href"))
+        })
+        .find_map(|src| resolve_path(document_path, src))
+}
@@ -0,0 +1,279 @@
+use std::sync::LazyLock;
+use regex::{Captures, Regex};
+use crate::book::{resolve_path, resource_url, BODY_ATTRIBUTE};
+
+/// 正文容器的选择器，书籍样式只在这个容器内生效
+pub const CONTENT_SCOPE: &str = ".epub-content";
+
+/// 把书籍 CSS 限定在正文容器内，并把其中的相对 `url()` 解析为资源地址。
+/// `css_path` 是样式所在文件的完整路径，内联样式传章节路径。
+pub fn scope_css(css: &str, css_path: &str) -> String {
+    static COMMENTS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)/\*.*?\*/").unwrap());
+    let css = COMMENTS.replace_all(css, "");
+    let css = resolve_urls(&css, css_path);
+    let css = unprefix_properties(&css);
+    scope_rules(&css, &|selector| scope_selector(selector, CONTENT_SCOPE))
+}
+
+/// 把已经限定在正文容器内的样式改为限定在 `scope` 内。
+/// 固定版式的跨页同时显示两页，每页的样式只作用于自己那一页。
+pub fn rescope_css(css: &str, scope: &str) -> String {
+    scope_rules(css, &|selector| match selector.strip_prefix(CONTENT_SCOPE) {
+        Some(rest) => format!("{}{}", scope, rest),
+        None => format!("{} {}", scope, selector),
+    })
+}
+
+/// 书籍样式是否把正文设为从右往左的竖排（`writing-mode: vertical-rl`）
+pub fn is_vertical_writing(css: &str) -> bool {
+    static WRITING_MODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)writing-mode\s*:\s*(?:vertical-rl|tb-rl)\b").unwrap());
+    WRITING_MODE.is_match(css)
+}
+
+/// 样式表中 `@import` 引用的文件，已解析为完整路径
+pub fn css_imports(css: &str, css_path: &str) -> Vec<String> {
+    static IMPORT_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(
+        r#"@import\s+(?:url\(\s*)?["']?([^"')\s;]+)["']?\s*\)?[^;]*;"#
+    ).unwrap());
+    IMPORT_REGEX.captures_iter(css)
+        .filter_map(|caps| resolve_path(css_path, &caps[1]))
+        .collect()
+}
+
+// 样式中的相对 `url()` 解析为资源地址，内联 style 属性也用它处理
+pub(crate) fn resolve_urls(css: &str, css_path: &str) -> String {
+    static URL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"url\(\s*(["']?)([^"')]+)["']?\s*\)"#).unwrap());
+    URL_REGEX.replace_all(css, |caps: &Captures| {
+        match resolve_path(css_path, &caps[2]) {
+            Some(path) => format!("url(\"{}\")", resource_url(&path)),
+            None => caps[0].to_string(),
+        }
+    }).into_owned()
+}
+
+// EPUB3 规定的 `-epub-` 前缀属性（竖排、文字方向、着重号等）换成标准写法，
+// webview 不认识这些前缀。`-epub-text-combine` 的取值与标准不同，单独转换。
+fn unprefix_properties(css: &str) -> String {
+    static PREFIX_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(
+        r"-epub-(writing-mode|text-orientation|text-emphasis[\w-]*|text-underline-position|line-break|word-break|hyphens)\b"
+    ).unwrap());
+    static COMBINE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"-epub-text-combine\s*:\s*horizontal").unwrap());
+    let css = COMBINE_REGEX.replace_all(css, "text-combine-upright: all");
+    PREFIX_REGEX.replace_all(&css, "$1").into_owned()
+}
+
+// 块内不含选择器的 @ 规则
+const DESCRIPTOR_AT_RULES: &[&str] = &[
+    "font-face", "keyframes", "-webkit-keyframes", "-moz-keyframes", "counter-style",
+    "font-feature-values", "font-palette-values", "property", "viewport", "-ms-viewport",
+];
+
+// 逐条处理规则，用 `scope` 改写每个选择器，遇到 @media 等条件规则时递归处理内部
+fn scope_rules(css: &str, scope: &dyn Fn(&str) -> String) -> String {
+    let mut output = String::with_capacity(css.len() + css.len() / 4);
+    let mut rest = css;
+
+    while let Some(open) = rest.find(['{', ';']) {
+        let prelude = rest[..open].trim();
+
+        // 不带块的 @ 规则（@import、@charset、@namespace），导入的样式由调用方单独加载
+        if rest.as_bytes()[open] == b';' {
+            rest = &rest[open + 1..];
+            continue;
+        }
+
+        let close = matching_brace(rest, open);
+        let body = &rest[open + 1..close];
+        rest = rest.get(close + 1..).unwrap_or("");
+
+        let at_rule = prelude.strip_prefix('@')
+            .map(|r| r.split(|c: char| c.is_whitespace() || c == '(').next().unwrap_or(""));
+        match at_rule {
+            // @page 会影响整个窗口，直接丢弃
+            Some("page") => {}
+            // @font-face、@keyframes 等不含选择器，原样保留
+            Some(name) if DESCRIPTOR_AT_RULES.contains(&name.to_ascii_lowercase().as_str()) => {
+                output.push_str(prelude);
+                output.push_str(" {");
+                output.push_str(body);
+                output.push_str("}\n");
+            }
+            // @media、@supports、@scope、@starting-style 等其余块规则里面是普通规则，递归处理
+            Some(_) => {
+                output.push_str(prelude);
+                output.push_str(" {\n");
+                output.push_str(&scope_rules(body, scope));
+                output.push_str("}\n");
+            }
+            None if prelude.is_empty() => {}
+            None => {
+                let selectors: Vec<String> = split_selectors(prelude)
+                    .into_iter()
+                    .map(scope)
+                    .collect();
+                output.push_str(&selectors.join(", "));
+                output.push_str(" {");
+                output.push_str(body);
+                output.push_str("}\n");
+            }
+        }
+    }
+
+    output
+}
+
+// 找到与 `open` 处的 '{' 配对的 '}'，缺失时视为到结尾
+fn matching_brace(css: &str, open: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in css[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + i;
+                }
+            }
+            _ => {}
+        }
+    }
+    css.len()
+}
+
+// 按顶层逗号拆分选择器，`:is(a, b)` 这类括号内的逗号不拆
+fn split_selectors(prelude: &str) -> Vec<&str> {
+    let mut selectors = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in prelude.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                selectors.push(prelude[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    selectors.push(prelude[start..].trim());
+    selectors.retain(|s| !s.is_empty());
+    selectors
+}
+
+// `html`、`:root` 对应正文容器本身，`body` 对应容器内由章节 `<body>` 改写成的元素，
+// 它保留了 body 的 class 和 id。其余选择器加上容器前缀
+fn scope_selector(selector: &str, scope: &str) -> String {
+    let (scope, suffix, rest) = if let Some((_, rest)) = strip_compound(selector, "html")
+        .or_else(|| strip_compound(selector, ":root"))
+    {
+        // `html > body`、`html body` 整体都对应 body
+        let inner = rest.trim_start();
+        let inner = inner.strip_prefix('>').unwrap_or(inner).trim_start();
+        match strip_compound(inner, "body") {
+            Some((body_suffix, body_rest)) => (body_scope(scope), body_suffix, body_rest),
+            None => (scope.to_string(), "", rest),
+        }
+    } else if let Some((body_suffix, rest)) = strip_compound(selector, "body") {
+        (body_scope(scope), body_suffix, rest)
+    } else {
+        return format!("{} {}", scope, selector);
+    };
+
+    let rest = rest.trim();
+    if rest.is_empty() {
+        format!("{}{}", scope, suffix)
+    } else {
+        format!("{}{} {}", scope, suffix, rest)
+    }
+}
+
+fn body_scope(scope: &str) -> String {
+    format!("{} [{}]", scope, BODY_ATTRIBUTE)
+}
+
+// 如果选择器以 `name` 这个简单选择器开头，返回它附带的类名等后缀和剩余部分
+fn strip_compound<'a>(selector: &'a str, name: &str) -> Option<(&'a str, &'a str)> {
+    let after = selector.strip_prefix(name)?;
+    let is_boundary = after.chars().next()
+        .is_none_or(|c| c.is_whitespace() || matches!(c, '.' | '#' | ':' | '[' | '>' | '+' | '~'));
+    if !is_boundary {
+        return None;
+    }
+    let end = after.find(|c: char| c.is_whitespace() || matches!(c, '>' | '+' | '~'))
+        .unwrap_or(after.len());
+    Some((&after[..end], &after[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_selectors() {
+        let css = scope_css("p, .note > span { color: red }", "OEBPS/Styles/style.css");
+        assert_eq!(css.trim(), ".epub-content p, .epub-content .note > span { color: red }");
+    }
+
+    #[test]
+    fn maps_html_and_body() {
+        let css = scope_css("html { font-size: 1em } body.calibre p { margin: 0 } :root { color: #000 }", "style.css");
+        assert!(css.contains(".epub-content { font-size: 1em }"));
+        assert!(css.contains(".epub-content [data-epub-body].calibre p { margin: 0 }"));
+        assert!(css.contains(".epub-content { color: #000 }"));
+        assert_eq!(scope_css("html > body#main {}", "style.css").trim(), ".epub-content [data-epub-body]#main {}");
+        // bodyText 这样的类型名不是 body
+        assert!(scope_css("bodytext {}", "style.css").contains(".epub-content bodytext"));
+    }
+
+    #[test]
+    fn recurses_into_conditional_rules() {
+        let css = scope_css("@media (min-width: 600px) { h1 { font-size: 2em } } @page { margin: 0 } @font-face { font-family: A }", "style.css");
+        assert!(css.contains("@media (min-width: 600px) {\n.epub-content h1 { font-size: 2em }"));
+        assert!(!css.contains("@page"));
+        assert!(css.contains("@font-face { font-family: A }"));
+    }
+
+    #[test]
+    fn scopes_selectors_in_other_block_rules() {
+        let css = scope_css("@scope (.note) { p { margin: 0 } } @starting-style { .box { opacity: 0 } } @-moz-document url-prefix() { body { color: red } }", "style.css");
+        assert!(css.contains("@scope (.note) {\n.epub-content p { margin: 0 }"));
+        assert!(css.contains("@starting-style {\n.epub-content .box { opacity: 0 }"));
+        assert!(css.contains(".epub-content [data-epub-body] { color: red }"));
+        let css = scope_css("@keyframes fade { from { opacity: 0 } to { opacity: 1 } }", "style.css");
+        assert_eq!(css.trim(), "@keyframes fade { from { opacity: 0 } to { opacity: 1 } }");
+    }
+
+    #[test]
+    fn resolves_urls_and_strips_comments() {
+        let css = scope_css("/* 注释 { } */ h1 { background: url('../Images/bg.png') } a { background: url(data:image/png;base64,AA) }", "OEBPS/Styles/style.css");
+        assert!(!css.contains("注释"));
+        assert!(css.contains(&format!("url(\"{}\")", resource_url("OEBPS/Images/bg.png"))));
+        assert!(css.contains("url(data:image/png;base64,AA)"));
+    }
+
+    #[test]
+    fn unprefixes_epub_properties() {
+        let css = scope_css("body { -epub-writing-mode: vertical-rl; -epub-text-combine: horizontal }", "style.css");
+        assert!(css.contains("writing-mode: vertical-rl"));
+        assert!(!css.contains("-epub-writing-mode") && !css.contains("-epub-text-combine"));
+        assert!(css.contains("text-combine-upright: all"));
+        assert!(is_vertical_writing(&css));
+    }
+
+    #[test]
+    fn rescopes_to_a_page() {
+        let css = scope_css("html { margin: 0 } body.c p { color: red } @media print { img { width: 100% } }", "style.css");
+        let css = rescope_css(&css, ".epub-content[data-spine=\"2\"]");
+        assert!(css.contains(".epub-content[data-spine=\"2\"] { margin: 0 }"));
+        assert!(css.contains(".epub-content[data-spine=\"2\"] [data-epub-body].c p { color: red }"));
+        assert!(css.contains("@media print {\n.epub-content[data-spine=\"2\"] img { width: 100% }"));
+    }
+
+    #[test]
+    fn imports() {
+        let css = "@import url(\"base.css\"); @import '../fonts.css' screen; p {}";
+        assert_eq!(css_imports(css, "OEBPS/Styles/style.css"), ["OEBPS/Styles/base.css", "OEBPS/fonts.css"]);
+        assert_eq!(scope_css(css, "OEBPS/Styles/style.css").trim(), ".epub-content p {}");
+    }
+}
@@ -0,0 +1,142 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use encoding_rs::{Encoding, BIG5, GB18030, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251};
+use regex::bytes::Regex;
+
+// 声明只会出现在文件开头，只在这一段里查找
+const DECLARATION_SCAN_BYTES: usize = 1024;
+
+// 猜测编码时取样的字节数
+const SAMPLE_BYTES: usize = 16 * 1024;
+
+// 简体和繁体中文的常用字，用来区分 GB18030 和 Big5 的解码结果
+const COMMON_HANZI: &str = "的一是不了在人有我他这个们中来上大为和国地到以说时要就出会可也你对生能而子那得于着下自之年过发后作里用道行所然家种事成方多经么去法学如都同现当没动面起看定天分还进好小部其些主样理心她本前开但因只从想实日者意无力它与长把机十民第公此已工使情明性知全三又关点正业外将两高间由问很最重并物手应向头文体相见被利什二等新己制身果加月话合回特代内信表化老给世位次度门任常先海通教儿原东声提立及比员解水名真论处走义各入几口认条平气题活更别打女变四神总何电数安少报才结反受目太量再感做接必场件听白却达光放强即像难且思王完设色记品住告类求据程北边死张该交规万取拉望觉领共确传师观清今切让识候带导笑飞风步改收根干造言联持每车亲极林服快往元证近失转夫令准始怎呢存未远叫台单影具字爱击流备连调深算质团集百需价花城石级离况请技示复病息究线似火断精满支视消越器容照须九增写称八功吗包片史乎查轻易早曾除找装广显吧念六引历首医局突专费号尽另周较注语仅考落青随选列红响虽推势参希古众构房半节土某案黑维护七兴派孩验责营星够章音跟志底站严巴例防族供效续施留讲型料终答紧黄绝奇察母京段依批群项故按河米围江织害双境客纪采举杀攻父密低朝友诉止细愿千值仍男钱破网热助倒育属坐帝限船脸职速刻乐否刚毛状率甚独球般普怕弹校苦创假久错承印晚试拿脑谁益阳若哪微送急血惊伤素药适波夜省初喜卫源食险待述陆习置居劳财环排福欢雷警获模充负云停木游龙树疑层冷冲射略范竟句室异激汉村静退既衣您宗积余痛检差富灵协角占配征修皮挥胜降阶审沉坚善妈读啊超免压银买皇养怀执副乱抗犯追帮宣佛岁航优怪香著田铁控税左右份穿艺背阵草脚概恶块顿敢守酒岛托央户烈洋哥索胡款靠评版宝座释景顾弟登货互付伯慢欧换闻危忙核暗姐介坏讨丽良序升监临亮露永呼味野架域沙掉括鱼杂误湾吉减编楚肯测败屋跑梦散温困剑渐封救贵枪缺楼县尚毫移娘朋画班智亦耳恩短掌恐遗固席松秘谢鲁遇康虑幸均销钟诗藏赶剧票损忽巨炮旧端探湖录叶春乡附吸予礼港雨呀板庭妇归睛饭额含顺输摇招婚脱补谓督毒油疗旅泽材灭逐莫笔亡鲜词圣择寻厂睡博勒烟授诺伦岸奥唐卖俄炸载洛健堂旁宫喝借君禁阴园谋宋避抓荣姑孙逃牙束跳顶玉镇雪午练迫爷篇肉嘴馆遍凡础洞卷坦牛宁纸诸训私庄祖丝翻暴森塔默握戏隐熟骨访弱蒙歌店鬼软典欲萨伙遭盘爸扩盖弄雄稳忘亿刺拥徒姆杨齐赛趣曲刀床迎冰虚玩析窗醒妻透购替塞努休虎扬途侵刑绿兄迅套贸毕唯谷轮库迹尤竞街促延震弃甲伟麻川申缓潜闪售灯针哲络抵朱抱鼓植纯夏忍页杰筑折郑贝尊吴秀混臣雅振染盛怒舞圆搞狂措姓残秋培迷诚宽宇猛摆梅毁伸摩盟末乃悲拍丁赵\
+這個們來為說時會對國後進過還實發從經點麼現當動面樣與開種關長機無體應頭見間問學將電數書員兩話結讓氣該給門東聲條總處義認變親邊張萬嗎覺傳觀師車聽難遠單愛擊備調質團須寫稱龍層殺陽聯興響號醫費較語選紅務報設區連錯";
+
+/// 识别文本的编码：先看 XML 声明、`<meta charset>` 或 `@charset`，再看 BOM，
+/// 都没有时按内容猜测。声明为 UTF-8 但内容并不是合法 UTF-8 的按没有声明处理。
+pub fn detect_encoding(data: &[u8]) -> &'static Encoding {
+    if let Some(encoding) = declared_encoding(data) {
+        if encoding != UTF_8 || std::str::from_utf8(data).is_ok() {
+            return encoding;
+        }
+    }
+    if let Some((encoding, _)) = Encoding::for_bom(data) {
+        return encoding;
+    }
+    if std::str::from_utf8(data).is_ok() {
+        return UTF_8;
+    }
+    guess_encoding(data)
+}
+
+/// 按检测到的编码把章节、样式表等文本转换为 UTF-8，去掉 BOM，无法解码的字节替换为 U+FFFD
+pub fn decode_text(data: &[u8]) -> Cow<'_, str> {
+    detect_encoding(data).decode_with_bom_removal(data).0
+}
+
+// 文件开头声明的编码。UTF-16 的文本里不可能按 ASCII 找到声明，声明了 UTF-16 的按 HTML 规范当作 UTF-8
+fn declared_encoding(data: &[u8]) -> Option<&'static Encoding> {
+    let head = &data[..data.len().min(DECLARATION_SCAN_BYTES)];
+    static DECLARATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(
+        r#"(?i)<\?xml[^>]*?encoding\s*=\s*["']([\w.:-]+)["']|<meta[^>]*?charset\s*=\s*["']?([\w.:-]+)|@charset\s+["']([\w.:-]+)["']"#,
+    ).unwrap());
+    let caps = DECLARATION.captures(head)?;
+    let label = caps.get(1).or(caps.get(2)).or(caps.get(3))?;
+    Encoding::for_label(label.as_bytes())
+        .map(|encoding| if encoding == UTF_16LE || encoding == UTF_16BE { UTF_8 } else { encoding })
+}
+
+// 用候选编码分别解码开头的一段内容，按解码出的字符打分，取得分最高的编码
+fn guess_encoding(data: &[u8]) -> &'static Encoding {
+    let sample = &data[..data.len().min(SAMPLE_BYTES)];
+    // 在最后一个 ASCII 字节处截断，避免把多字节字符切成两半
+    let sample = match sample.iter().rposition(u8::is_ascii) {
+        Some(end) if sample.len() < data.len() => &sample[..=end],
+        _ => sample,
+    };
+
+    // 旧书常见的非 Unicode 编码，按同分时的优先顺序排列。GB18030 兼容 GBK 和 GB2312，
+    // Windows-1251 是没有声明编码的俄文 FB2 最常用的编码
+    let mut best = (GB18030, i64::MIN);
+    for encoding in [GB18030, BIG5, SHIFT_JIS, WINDOWS_1251] {
+        let (text, _) = encoding.decode_without_bom_handling(sample);
+        let score: i64 = text.chars().map(char_score).sum();
+        if score > best.1 {
+            best = (encoding, score);
+        }
+    }
+    best.0
+}
+
+// 用错编码解码时通常得到生僻字、半角片假名或无法解码的字节，正确的编码则多是常用字和假名。
+// 西里尔字母的正文以小写为主，中文的双字节按 Windows-1251 解码时大小写字母和符号混杂，得分为负
+fn char_score(c: char) -> i64 {
+    static COMMON: LazyLock<HashSet<char>> = LazyLock::new(|| COMMON_HANZI.chars().collect());
+    match c {
+        '\u{FFFD}' => -5,
+        _ if c.is_ascii() => 0,
+        _ if COMMON.contains(&c) => 3,
+        '\u{3040}'..='\u{309F}' => 2,                                   // 平假名
+        '\u{30A0}'..='\u{30FF}' | '\u{3000}'..='\u{303F}' | '\u{FF01}'..='\u{FF5E}' => 1,  // 片假名、中日文标点、全角字符
+        '\u{FF61}'..='\u{FF9F}' => -2,                                  // 半角片假名
+        '\u{4E00}'..='\u{9FFF}' => 1,
+        '\u{0430}'..='\u{044F}' | 'ё' => 1,                             // 西里尔小写字母
+        _ => -1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{GBK, WINDOWS_1252};
+
+    const TEXT: &str = "第一章 他说这个时候我们还没有到家，天已经黑了。";
+
+    #[test]
+    fn utf8_and_bom() {
+        assert_eq!(detect_encoding(TEXT.as_bytes()), UTF_8);
+        let mut bom = b"\xEF\xBB\xBF".to_vec();
+        bom.extend_from_slice(TEXT.as_bytes());
+        assert_eq!(decode_text(&bom), TEXT);
+        let mut utf16le = vec![0xFF, 0xFE];
+        utf16le.extend(TEXT.encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(detect_encoding(&utf16le), UTF_16LE);
+        assert_eq!(decode_text(&utf16le), TEXT);
+    }
+
+    #[test]
+    fn declared_encoding_wins() {
+        let (body, _, _) = GB18030.encode(TEXT);
+        let mut data = br#"<?xml version="1.0" encoding="gbk"?><p>"#.to_vec();
+        data.extend_from_slice(&body);
+        assert_eq!(detect_encoding(&data), GBK);
+        assert!(decode_text(&data).contains(TEXT));
+        assert_eq!(detect_encoding(b"<meta charset=\"windows-1252\"><p>caf\xE9</p>"), WINDOWS_1252);
+        // 声明为 UTF-8 但内容不是 UTF-8 时按内容猜测
+        let mut wrong = br#"<meta charset="utf-8">"#.to_vec();
+        wrong.extend_from_slice(&body);
+        assert_eq!(detect_encoding(&wrong), GB18030);
+    }
+
+    #[test]
+    fn guesses_chinese_encodings() {
+        let (gbk, _, _) = GB18030.encode(TEXT);
+        assert_eq!(detect_encoding(&gbk), GB18030);
+        let traditional = "第一章 他說這個時候我們還沒有到家，天已經黑了。";
+        let (big5, _, _) = BIG5.encode(traditional);
+        assert_eq!(detect_encoding(&big5), BIG5);
+        assert_eq!(decode_text(&big5), traditional);
+    }
+
+    #[test]
+    fn guesses_cyrillic() {
+        let russian = "Глава первая. Было уже темно, когда мы вернулись домой, и ёлка стояла в углу.";
+        let (cp1251, _, _) = WINDOWS_1251.encode(russian);
+        assert_eq!(detect_encoding(&cp1251), WINDOWS_1251);
+        assert_eq!(decode_text(&cp1251), russian);
+        let (gbk, _, _) = GB18030.encode(TEXT);
+        assert_eq!(detect_encoding(&gbk), GB18030);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use sha1::{Digest, Sha1};
+use crate::book::normalize_path;
+
+/// IDPF 字体混淆算法
+pub const IDPF_FONT_ALGORITHM: &str = "http://www.idpf.org/2008/embedding";
+/// Adobe 字体混淆算法
+pub const ADOBE_FONT_ALGORITHM: &str = "http://ns.adobe.com/pdf/enc#RC";
+
+/// META-INF/encryption.xml 中登记的一个加密资源
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncryptedResource {
+    pub uri: String,
+    pub algorithm: String,
+}
+
+impl EncryptedResource {
+    /// 字体混淆不是 DRM，可以在读取时还原
+    pub fn is_font_obfuscation(&self) -> bool {
+        self.algorithm == IDPF_FONT_ALGORITHM || self.algorithm == ADOBE_FONT_ALGORITHM
+    }
+}
+
+/// 解析 encryption.xml，返回每个 `EncryptedData` 的算法和资源路径
+pub fn parse_encryption(xml: &str) -> Vec<EncryptedResource> {
+    let mut reader = Reader::from_str(xml);
+    let mut resources = Vec::new();
+    let mut algorithm: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let attr = |name: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == name)
+                        .and_then(|a| a.unescape_value().ok())
+                        .map(|v| v.into_owned())
+                };
+                match e.local_name().as_ref() {
+                    b"EncryptedData" => algorithm = None,
+                    b"EncryptionMethod" => algorithm = attr(b"Algorithm"),
+                    b"CipherReference" => {
+                        if let Some(uri) = attr(b"URI") {
+                            resources.push(EncryptedResource {
+                                uri,
+                                algorithm: algorithm.clone().unwrap_or_default(),
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    resources
+}
+
+/// 被混淆字体的还原密钥，按压缩包内路径索引
+#[derive(Debug, Clone, Default)]
+pub struct FontObfuscation {
+    fonts: HashMap<String, (Vec<u8>, usize)>,  // 路径 -> (密钥, 混淆的字节数)
+}
+
+impl FontObfuscation {
+    /// `identifiers` 是 OPF 中所有的 dc:identifier，Adobe 算法需要其中的 UUID
+    pub fn new(
+        resources: &[EncryptedResource],
+        unique_identifier: Option<&str>,
+        identifiers: &[String],
+    ) -> Self {
+        let idpf_key = unique_identifier.map(idpf_key);
+        let adobe_key = adobe_key(unique_identifier, identifiers);
+
+        let mut fonts = HashMap::new();
+        for resource in resources {
+            let key = match resource.algorithm.as_str() {
+                IDPF_FONT_ALGORITHM => idpf_key.clone().map(|key| (key, 1040)),
+                ADOBE_FONT_ALGORITHM => adobe_key.clone().map(|key| (key, 1024)),
+                _ => None,
+            };
+            // 登记的路径可能带 %xx 转义，统一成读取资源时使用的解码后路径
+            if let Some(key) = key {
+                fonts.insert(normalize_path(&resource.uri), key);
+            }
+        }
+        Self { fonts }
+    }
+
+    /// 如果 `path` 是被混淆的字体，原地还原文件头
+    pub fn deobfuscate(&self, path: &str, data: &mut [u8]) {
+        if let Some((key, length)) = self.fonts.get(path) {
+            for (i, byte) in data.iter_mut().take(*length).enumerate() {
+                *byte ^= key[i % key.len()];
+            }
+        }
+    }
+}
+
+// IDPF：去掉空白后的唯一标识符的 SHA-1
+fn idpf_key(unique_identifier: &str) -> Vec<u8> {
+    let identifier: String = unique_identifier.chars()
+        .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+        .collect();
+    Sha1::digest(identifier.as_bytes()).to_vec()
+}
+
+// Adobe：UUID 标识符的 16 个字节，优先使用唯一标识符
+fn adobe_key(unique_identifier: Option<&str>, identifiers: &[String]) -> Option<Vec<u8>> {
+    unique_identifier.into_iter()
+        .chain(identifiers.iter().map(String::as_str))
+        .find_map(|identifier| {
+            let hex: String = identifier.trim()
+                .trim_start_matches("urn:uuid:")
+                .chars()
+                .filter(|c| *c != '-')
+                .collect();
+            if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            (0..16)
+                .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+                .collect()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "urn:uuid:12345678-9abc-def0-1234-56789abcdef0";
+
+    fn font(algorithm: &str, uri: &str) -> EncryptedResource {
+        EncryptedResource { uri: uri.to_string(), algorithm: algorithm.to_string() }
+    }
+
+    fn sample() -> Vec<u8> {
+        (0..2000).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn parses_encryption_xml() {
+        let xml = r#"<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container" xmlns:enc="http://www.w3.org/2001/04/xmlenc#">
+  <enc:EncryptedData><enc:EncryptionMethod Algorithm="http://www.idpf.org/2008/embedding"/>
+    <enc:CipherData><enc:CipherReference URI="OEBPS/Fonts/My%20Font.otf"/></enc:CipherData></enc:EncryptedData>
+  <enc:EncryptedData><enc:EncryptionMethod Algorithm="http://www.w3.org/2001/04/xmlenc#aes128-cbc"/>
+    <enc:CipherData><enc:CipherReference URI="OEBPS/Text/ch1.xhtml"/></enc:CipherData></enc:EncryptedData>
+</encryption>"#;
+        let resources = parse_encryption(xml);
+        assert_eq!(resources, [
+            font(IDPF_FONT_ALGORITHM, "OEBPS/Fonts/My%20Font.otf"),
+            font("http://www.w3.org/2001/04/xmlenc#aes128-cbc", "OEBPS/Text/ch1.xhtml"),
+        ]);
+        assert!(resources[0].is_font_obfuscation());
+        assert!(!resources[1].is_font_obfuscation());
+    }
+
+    #[test]
+    fn idpf_round_trip() {
+        // 标识符中的空白不参与计算
+        let key = idpf_key(" urn:uuid:12345678-9abc-def0-1234-56789abcdef0\n");
+        let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "d5136d90a6cbd07e7ec7326acb8753f97a31c839");
+
+        let fonts = FontObfuscation::new(&[font(IDPF_FONT_ALGORITHM, "/OEBPS/Fonts/My%20Font.otf")], Some(UUID), &[]);
+        let original = sample();
+        let mut data = original.clone();
+        fonts.deobfuscate("OEBPS/Fonts/My Font.otf", &mut data);
+        assert_eq!(data[0], original[0] ^ key[0]);
+        assert_eq!(data[1039], original[1039] ^ key[1039 % 20]);
+        assert_eq!(data[1040..], original[1040..]);
+        fonts.deobfuscate("OEBPS/Fonts/My Font.otf", &mut data);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn adobe_round_trip() {
+        let key = adobe_key(Some("isbn:9780000000000"), &["urn:uuid:12345678-9ABC-def0-1234-56789abcdef0".to_string()]).unwrap();
+        assert_eq!(key, [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0]);
+        assert_eq!(adobe_key(Some("isbn:9780000000000"), &[]), None);
+
+        let fonts = FontObfuscation::new(&[font(ADOBE_FONT_ALGORITHM, "OEBPS/font.ttf")], Some(UUID), &[]);
+        let original = sample();
+        let mut data = original.clone();
+        fonts.deobfuscate("OEBPS/font.ttf", &mut data);
+        assert_eq!(data[1023], original[1023] ^ key[1023 % 16]);
+        assert_eq!(data[1024..], original[1024..]);
+        fonts.deobfuscate("OEBPS/font.ttf", &mut data);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn other_resources_are_untouched() {
+        let fonts = FontObfuscation::new(&[font(IDPF_FONT_ALGORITHM, "OEBPS/font.ttf")], None, &[]);
+        let original = sample();
+        let mut data = original.clone();
+        // 没有唯一标识符时无法还原
+        fonts.deobfuscate("OEBPS/font.ttf", &mut data);
+        assert_eq!(data, original);
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use epub::archive::ArchiveError;
+use epub::doc::DocError;
+use zip::result::ZipError;
+
+/// 打开书籍失败的原因，阅读器根据不同的原因给出对应的提示
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookError {
+    /// 文件不存在
+    NotFound(PathBuf),
+    /// 文件不是 zip 压缩包
+    NotZip(String),
+    /// 缺少 META-INF/container.xml
+    MissingContainer,
+    /// container.xml 没有指向 OPF，或指向的 OPF 不在压缩包里
+    MissingPackage(Option<String>),
+    /// OPF 结构不完整或无法解析
+    MalformedPackage(String),
+    /// 内容被 DRM 加密
+    Encrypted,
+    /// 书脊中没有可以显示的内容类型
+    UnsupportedMedia(String),
+    /// 文本文件中没有任何内容
+    EmptyText,
+    /// MOBI / AZW3 文件结构损坏
+    MalformedMobi(String),
+    /// FB2 文件不是有效的 XML 或没有正文
+    MalformedFb2(String),
+    /// 漫画压缩包中没有图片
+    NoImages,
+    Io(String),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "文件不存在: {}", path.display()),
+            Self::NotZip(reason) => write!(f, "文件不是有效的 EPUB 压缩包: {}", reason),
+            Self::MissingContainer => write!(f, "缺少 META-INF/container.xml"),
+            Self::MissingPackage(Some(path)) => write!(f, "找不到 OPF 文件: {}", path),
+            Self::MissingPackage(None) => write!(f, "container.xml 没有指定 OPF 文件"),
+            Self::MalformedPackage(reason) => write!(f, "OPF 文件格式错误: {}", reason),
+            Self::Encrypted => write!(f, "书籍受 DRM 保护，无法打开"),
+            Self::UnsupportedMedia(mime) => write!(f, "不支持的内容类型: {}", mime),
+            Self::EmptyText => write!(f, "文本文件是空的"),
+            Self::MalformedMobi(reason) => write!(f, "MOBI 文件格式错误: {}", reason),
+            Self::MalformedFb2(reason) => write!(f, "FB2 文件格式错误: {}", reason),
+            Self::NoImages => write!(f, "压缩包中没有图片"),
+            Self::Io(reason) => write!(f, "读取文件失败: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+impl From<std::io::Error> for BookError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl BookError {
+    /// 把 epub 库的错误归类。压缩包里缺文件时需要再看一眼是缺 container.xml 还是缺 OPF。
+    pub(crate) fn from_doc_error(path: &Path, error: DocError) -> Self {
+        match error {
+            DocError::IOError(e) => e.into(),
+            DocError::ArchiveError(ArchiveError::Zip(ZipError::FileNotFound)) => {
+                Self::diagnose_missing_entry(path)
+            }
+            DocError::ArchiveError(ArchiveError::Zip(ZipError::Io(e))) => e.into(),
+            DocError::ArchiveError(ArchiveError::IO(e)) => e.into(),
+            DocError::ArchiveError(ArchiveError::Zip(e)) => Self::NotZip(e.to_string()),
+            DocError::ArchiveError(e) => Self::MalformedPackage(e.to_string()),
+            DocError::XmlError(e) => {
+                let reason = e.to_string();
+                // container.xml 的 rootfile 缺失时 epub 库报的是属性缺失
+                if reason.contains("rootfile") || reason.contains("full-path") {
+                    Self::MissingPackage(None)
+                } else {
+                    Self::MalformedPackage(reason)
+                }
+            }
+            DocError::InvalidEpub => {
+                Self::MalformedPackage("缺少 manifest、spine 或 metadata".to_string())
+            }
+        }
+    }
+
+    // 只在打开失败时才会重新读取压缩包目录
+    fn diagnose_missing_entry(path: &Path) -> Self {
+        let archive = std::fs::File::open(path)
+            .map_err(Self::from)
+            .and_then(|file| zip::ZipArchive::new(file).map_err(|e| Self::NotZip(e.to_string())));
+        let mut archive = match archive {
+            Ok(archive) => archive,
+            Err(e) => return e,
+        };
+
+        let container = match archive.by_name("META-INF/container.xml") {
+            Ok(mut entry) => {
+                let mut text = String::new();
+                let _ = std::io::Read::read_to_string(&mut entry, &mut text);
+                text
+            }
+            Err(_) => return Self::MissingContainer,
+        };
+
+        let root_file = regex::Regex::new(r#"full-path\s*=\s*["']([^"']+)["']"#)
+            .ok()
+            .and_then(|re| re.captures(&container).map(|caps| caps[1].to_string()));
+        Self::MissingPackage(root_file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use crate::book::Book;
+    use super::*;
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+
+    // 书脊只有一项，媒体类型为 `mime`
+    fn opf(mime: &str) -> String {
+        format!(r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:identifier id="uid">x</dc:identifier><dc:title>t</dc:title></metadata>
+  <manifest><item id="c1" href="c1" media-type="{}"/></manifest>
+  <spine><itemref idref="c1"/></spine>
+</package>"#, mime)
+    }
+
+    const ENCRYPTION: &str = r#"<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container" xmlns:enc="http://www.w3.org/2001/04/xmlenc#">
+  <enc:EncryptedData><enc:EncryptionMethod Algorithm="{}"/>
+    <enc:CipherData><enc:CipherReference URI="OEBPS/c1"/></enc:CipherData></enc:EncryptedData>
+</encryption>"#;
+
+    // 在临时目录生成压缩包，打开后删除
+    fn open(name: &str, files: &[(&str, &str)]) -> Result<Book, BookError> {
+        let path = std::env::temp_dir().join(format!("fast_epub_{}_error_{}.epub", std::process::id(), name));
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        let book = Book::open(&path);
+        let _ = std::fs::remove_file(path);
+        book
+    }
+
+    fn error(result: Result<Book, BookError>) -> BookError {
+        match result {
+            Ok(_) => panic!("应当打开失败"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn missing_container_and_package() {
+        let xhtml = opf("application/xhtml+xml");
+        assert_eq!(error(open("no_container", &[("OEBPS/content.opf", &xhtml)])), BookError::MissingContainer);
+        assert_eq!(
+            error(open("no_package", &[("META-INF/container.xml", CONTAINER)])),
+            BookError::MissingPackage(Some("OEBPS/content.opf".to_string())),
+        );
+        let no_rootfile = r#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><rootfiles/></container>"#;
+        assert_eq!(error(open("no_rootfile", &[("META-INF/container.xml", no_rootfile)])), BookError::MissingPackage(None));
+    }
+
+    #[test]
+    fn encrypted() {
+        let xhtml = opf("application/xhtml+xml");
+        let book = [("META-INF/container.xml", CONTAINER), ("OEBPS/content.opf", xhtml.as_str()), ("OEBPS/c1", "<html/>")];
+        assert!(open("plain", &book).is_ok());
+
+        let rights = [&book[..], &[("META-INF/rights.xml", "<rights/>")]].concat();
+        assert_eq!(error(open("rights", &rights)), BookError::Encrypted);
+
+        let aes = ENCRYPTION.replace("{}", "http://www.w3.org/2001/04/xmlenc#aes128-cbc");
+        let drm = [&book[..], &[("META-INF/encryption.xml", aes.as_str())]].concat();
+        assert_eq!(error(open("aes", &drm)), BookError::Encrypted);
+
+        // 只有字体混淆时可以打开
+        let idpf = ENCRYPTION.replace("{}", "http://www.idpf.org/2008/embedding");
+        let fonts = [&book[..], &[("META-INF/encryption.xml", idpf.as_str())]].concat();
+        assert!(open("fonts", &fonts).is_ok());
+    }
+
+    #[test]
+    fn unsupported_media() {
+        let pdf = opf("application/pdf");
+        let book = [("META-INF/container.xml", CONTAINER), ("OEBPS/content.opf", pdf.as_str()), ("OEBPS/c1", "%PDF")];
+        assert_eq!(error(open("pdf", &book)), BookError::UnsupportedMedia("application/pdf".to_string()));
+    }
+}
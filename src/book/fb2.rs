@@ -0,0 +1,711 @@
+// FictionBook 2 电子书。整本书是一个 XML 文件：<description> 中是书名、作者等信息，
+// 第一个 <body> 是正文，按嵌套的 <section> 分章；之后带 name 的 <body> 是注释；
+// 图片以 base64 存放在 <binary> 中，通过 l:href="#id" 引用。
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use crate::book::convert::{escape_text, number_toc, xhtml_page, Converted};
+use crate::book::metadata::PackageMetadata;
+use crate::book::{decode_text, BookContent, BookError, Contributor, Identifier, NavPoint, Series};
+
+// 有些文件的 base64 没有补齐末尾的 =
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+// 转换后章节和图片的存放位置
+const TEXT_DIR: &str = "text";
+const IMAGE_DIR: &str = "images";
+
+// 诗歌、题记等 FB2 特有结构的样式
+const FB2_CSS: &str = "p { text-indent: 2em; margin: 0.3em 0; } \
+    h1, h2, h3, h4, h5, h6, .subtitle { text-align: center; } \
+    .epigraph { margin: 1em 0 1em 30%; font-style: italic; } \
+    .poem { margin: 1em 2em; } .stanza { margin: 0.8em 0; } \
+    .verse, .text-author, .subtitle, .image { text-indent: 0; } \
+    .text-author { text-align: right; font-style: italic; } \
+    .image { text-align: center; } .image img { max-width: 100%; }";
+
+/// 取出 FB2.ZIP 压缩包中的第一个 .fb2 文件
+pub(crate) fn unzip_fb2(data: &[u8]) -> Option<Vec<u8>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).ok()?;
+    let name = archive.file_names()
+        .find(|name| name.to_ascii_lowercase().ends_with(".fb2"))?
+        .to_string();
+    let mut file = archive.by_name(&name).ok()?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).ok()?;
+    Some(content)
+}
+
+/// 把 FB2 文档转换为书的结构和内存中的资源：
+/// 章节组成目录，<binary> 图片作为资源，title-info 作为元数据，注释正文作为脚注。
+pub(crate) fn import_fb2(data: &[u8]) -> Result<Converted, BookError> {
+    let root = parse(&decode_text(data))
+        .filter(|root| root.name == "FictionBook")
+        .ok_or_else(|| BookError::MalformedFb2("无法解析 XML".to_string()))?;
+
+    let mut manifest = HashMap::new();
+    let mut resources = HashMap::new();
+    let mut images = HashMap::new();  // binary id -> (资源 id, 图片路径)
+    for (i, binary) in root.children_named("binary").enumerate() {
+        let (Some(id), Some(mime)) = (binary.attr("id"), binary.attr("content-type")) else { continue };
+        if !mime.starts_with("image/") {
+            continue;
+        }
+        let encoded: String = binary.text().chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        let Ok(data) = BASE64.decode(encoded) else { continue };
+        let image_id = format!("image{}", i + 1);
+        let path = format!("{}/{:04}.{}", IMAGE_DIR, i + 1, image_extension(mime));
+        manifest.insert(image_id.clone(), (PathBuf::from(&path), mime.to_string()));
+        resources.insert(path.clone(), (data, mime.to_string()));
+        images.insert(id.to_string(), (image_id, path));
+    }
+
+    let info = Info::parse(root.child("description"));
+
+    // 正文按章节分页，之后的每个注释正文各占一页
+    let mut pages = Pages::default();
+    let mut toc = Vec::new();
+    for (i, body) in root.children_named("body").enumerate() {
+        if i == 0 {
+            toc.extend(pages.split(body, 1, info.title.as_deref().unwrap_or_default()));
+        } else {
+            let label = body.child("title").map(Element::text)
+                .filter(|t| !t.is_empty())
+                .or_else(|| body.attr("name").map(str::to_string))
+                .unwrap_or_else(|| "注释".to_string());
+            toc.push(pages.add(Page { element: body, depth: 1, split: false, notes: true, title: label.clone() }, label));
+        }
+    }
+    if pages.pages.is_empty() {
+        return Err(BookError::MalformedFb2("正文为空".to_string()));
+    }
+    number_toc(&mut toc);
+
+    let render = Renderer { images: &images, ids: &pages.ids };
+    let mut spine = Vec::new();
+    for (i, page) in pages.pages.iter().enumerate() {
+        let mut body = String::new();
+        render.page(page, &mut body);
+        let id = format!("c{}", i + 1);
+        let path = page_path(i);
+        resources.insert(path.clone(), (xhtml_page(&page.title, &body).into_bytes(), "application/xhtml+xml".to_string()));
+        manifest.insert(id.clone(), (PathBuf::from(path), "application/xhtml+xml".to_string()));
+        spine.push(id);
+    }
+
+    let cover_id = info.cover.as_ref()
+        .and_then(|href| images.get(href.trim_start_matches('#')))
+        .map(|(id, _)| id.clone());
+    let mut content = BookContent::from_parts(spine, manifest, toc, info.metadata());
+    content.package_metadata = info.package;
+    content.unique_identifier = info.identifier;
+    content.cover_id = cover_id;
+    content.extra_css.push(FB2_CSS.to_string());
+    Ok(Converted { content, resources })
+}
+
+// 第 index 页的文件名和完整路径
+fn page_file(index: usize) -> String {
+    format!("{:04}.xhtml", index + 1)
+}
+
+fn page_path(index: usize) -> String {
+    format!("{}/{}", TEXT_DIR, page_file(index))
+}
+
+fn image_extension(mime: &str) -> &str {
+    match mime {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/svg+xml" => "svg",
+        _ => mime.trim_start_matches("image/"),
+    }
+}
+
+// 简化的 XML 树，元素和属性都按本地名存放（l:href 存为 href）
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+struct Element {
+    name: String,
+    attrs: HashMap<String, String>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(start: &BytesStart) -> Self {
+        let attrs = start.attributes()
+            .flatten()
+            .map(|a| {
+                let value = a.unescape_value()
+                    .map(|v| v.into_owned())
+                    .unwrap_or_else(|_| String::from_utf8_lossy(&a.value).into_owned());
+                (String::from_utf8_lossy(a.key.local_name().as_ref()).into_owned(), value)
+            })
+            .collect();
+        Self {
+            name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+            attrs,
+            children: Vec::new(),
+        }
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(String::as_str)
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements().filter(move |element| element.name == name)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name).map(Element::text).filter(|text| !text.is_empty())
+    }
+
+    // 全部文本，空白已合并
+    fn text(&self) -> String {
+        fn collect(element: &Element, out: &mut String) {
+            for node in &element.children {
+                match node {
+                    Node::Text(text) => out.push_str(text),
+                    Node::Element(child) => {
+                        collect(child, out);
+                        // 标题中的多个段落之间加空格
+                        if matches!(child.name.as_str(), "p" | "v" | "subtitle" | "empty-line") {
+                            out.push(' ');
+                        }
+                    }
+                }
+            }
+        }
+        let mut text = String::new();
+        collect(self, &mut text);
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+// 解析为树，XML 有错误时保留出错之前的内容
+fn parse(xml: &str) -> Option<Element> {
+    let mut reader = Reader::from_str(xml);
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = None;
+
+    fn close(stack: &mut Vec<Element>, root: &mut Option<Element>) {
+        if let Some(element) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(Node::Element(element)),
+                None => *root = Some(element),
+            }
+        }
+    }
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => stack.push(Element::new(&e)),
+            Ok(Event::Empty(e)) => {
+                stack.push(Element::new(&e));
+                close(&mut stack, &mut root);
+            }
+            Ok(Event::End(_)) => close(&mut stack, &mut root),
+            Ok(Event::Text(t)) => {
+                // 未声明的 HTML 实体（如 &nbsp;）保留原文
+                let text = t.unescape()
+                    .map(|text| text.into_owned())
+                    .unwrap_or_else(|_| String::from_utf8_lossy(&t).into_owned());
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(Node::Text(text));
+                }
+            }
+            Ok(Event::CData(t)) => {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(Node::Text(String::from_utf8_lossy(&t).into_owned()));
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut root);
+    }
+    root
+}
+
+// 一页的内容：一个 <section> 或 <body>。`split` 时子章节在各自的页面中，这一页只有标题等开头部分
+struct Page<'a> {
+    element: &'a Element,
+    depth: usize,
+    split: bool,
+    notes: bool,
+    title: String,
+}
+
+#[derive(Default)]
+struct Pages<'a> {
+    pages: Vec<Page<'a>>,
+    ids: HashMap<String, usize>,  // 元素 id -> 所在页
+}
+
+impl<'a> Pages<'a> {
+    // 按章节分页，返回这一层的目录。有子章节的章节把开头部分单独作为一页
+    fn split(&mut self, section: &'a Element, depth: usize, fallback_title: &str) -> Vec<NavPoint> {
+        let title = section.child("title").map(Element::text).filter(|t| !t.is_empty());
+        let has_sections = section.children_named("section").next().is_some();
+        if !has_sections {
+            let label = title.clone().unwrap_or_else(|| fallback_title.to_string());
+            let page = Page { element: section, depth, split: false, notes: false, title: label.clone() };
+            let nav = self.add(page, label);
+            // 没有标题的章节不放进目录
+            return if title.is_some() { vec![nav] } else { Vec::new() };
+        }
+
+        let has_intro = section.elements().any(|child| child.name != "section");
+        let nav = has_intro.then(|| {
+            let label = title.clone().unwrap_or_else(|| fallback_title.to_string());
+            let page = Page { element: section, depth, split: true, notes: false, title: label.clone() };
+            self.add(page, label)
+        });
+        let fallback = title.as_deref().unwrap_or(fallback_title).to_string();
+        let children: Vec<NavPoint> = section.children_named("section")
+            .flat_map(|child| self.split(child, depth + 1, &fallback))
+            .collect();
+        match nav {
+            Some(mut nav) if title.is_some() && section.name == "section" => {
+                nav.children = children;
+                vec![nav]
+            }
+            // 正文开头的书名页和各章节并列
+            Some(nav) if title.is_some() => std::iter::once(nav).chain(children).collect(),
+            _ => children,
+        }
+    }
+
+    fn add(&mut self, page: Page<'a>, label: String) -> NavPoint {
+        let index = self.pages.len();
+        collect_ids(page.element, page.split, index, &mut self.ids);
+        self.pages.push(page);
+        NavPoint {
+            label,
+            content: PathBuf::from(page_path(index)),
+            children: Vec::new(),
+            play_order: 0,
+        }
+    }
+}
+
+// 记录页面中所有元素的 id。`split` 时不进入子章节
+fn collect_ids(element: &Element, split: bool, page: usize, ids: &mut HashMap<String, usize>) {
+    if let Some(id) = element.attr("id") {
+        ids.entry(id.to_string()).or_insert(page);
+    }
+    for child in element.elements() {
+        if !(split && child.name == "section") {
+            collect_ids(child, false, page, ids);
+        }
+    }
+}
+
+// 把 FB2 元素转换为 XHTML
+struct Renderer<'a> {
+    images: &'a HashMap<String, (String, String)>,
+    ids: &'a HashMap<String, usize>,
+}
+
+impl Renderer<'_> {
+    fn page(&self, page: &Page, out: &mut String) {
+        let element = page.element;
+        out.push_str(&format!("<section{}>", self.id_attr(element)));
+        for child in element.elements() {
+            match child.name.as_str() {
+                "section" if page.split => {}
+                // 注释正文中的每一节是一条脚注，和 EPUB 的脚注一样在正文中隐藏，点击引用时弹出
+                "section" if page.notes => {
+                    out.push_str(&format!(r#"<aside epub:type="footnote"{}>"#, self.id_attr(child)));
+                    self.children(child, page.depth + 1, out);
+                    out.push_str("</aside>");
+                }
+                _ => self.block(child, page.depth, out),
+            }
+        }
+        out.push_str("</section>");
+    }
+
+    fn children(&self, element: &Element, depth: usize, out: &mut String) {
+        for child in element.elements() {
+            self.block(child, depth, out);
+        }
+    }
+
+    fn block(&self, element: &Element, depth: usize, out: &mut String) {
+        let id = self.id_attr(element);
+        match element.name.as_str() {
+            "title" => {
+                let level = depth.clamp(1, 6);
+                let lines: Vec<String> = element.children_named("p")
+                    .map(|p| self.inline_children(p))
+                    .collect();
+                out.push_str(&format!("<h{0}{1}>{2}</h{0}>", level, id, lines.join("<br/>")));
+            }
+            "section" => {
+                out.push_str(&format!("<section{}>", id));
+                self.children(element, depth + 1, out);
+                out.push_str("</section>");
+            }
+            "p" => out.push_str(&format!("<p{}>{}</p>", id, self.inline_children(element))),
+            "v" => out.push_str(&format!(r#"<p class="verse"{}>{}</p>"#, id, self.inline_children(element))),
+            "subtitle" => out.push_str(&format!(r#"<p class="subtitle"{}>{}</p>"#, id, self.inline_children(element))),
+            "text-author" => out.push_str(&format!(r#"<p class="text-author"{}>{}</p>"#, id, self.inline_children(element))),
+            "date" => out.push_str(&format!(r#"<p class="text-author">{}</p>"#, escape_text(&element.text()))),
+            "empty-line" => out.push_str("<br/>"),
+            "image" => out.push_str(&format!(r#"<div class="image"{}>{}</div>"#, id, self.image(element))),
+            "epigraph" | "cite" | "annotation" | "poem" | "stanza" => {
+                let tag = if matches!(element.name.as_str(), "epigraph" | "cite") { "blockquote" } else { "div" };
+                out.push_str(&format!(r#"<{} class="{}"{}>"#, tag, element.name, id));
+                self.children(element, depth + 1, out);
+                out.push_str(&format!("</{}>", tag));
+            }
+            "table" => {
+                out.push_str(&format!("<table{}>", id));
+                for row in element.children_named("tr") {
+                    out.push_str("<tr>");
+                    for cell in row.elements().filter(|c| matches!(c.name.as_str(), "th" | "td")) {
+                        let mut attrs = String::new();
+                        for name in ["colspan", "rowspan", "align", "valign"] {
+                            if let Some(value) = cell.attr(name) {
+                                attrs.push_str(&format!(r#" {}="{}""#, name, escape_text(value)));
+                            }
+                        }
+                        out.push_str(&format!("<{0}{1}>{2}</{0}>", cell.name, attrs, self.inline_children(cell)));
+                    }
+                    out.push_str("</tr>");
+                }
+                out.push_str("</table>");
+            }
+            _ => self.children(element, depth, out),
+        }
+    }
+
+    fn inline_children(&self, element: &Element) -> String {
+        let mut out = String::new();
+        for node in &element.children {
+            match node {
+                Node::Text(text) => out.push_str(&escape_text(text)),
+                Node::Element(child) => self.inline(child, &mut out),
+            }
+        }
+        out
+    }
+
+    fn inline(&self, element: &Element, out: &mut String) {
+        let tag = match element.name.as_str() {
+            "strong" => "strong",
+            "emphasis" => "em",
+            "strikethrough" => "del",
+            "sub" => "sub",
+            "sup" => "sup",
+            "code" => "code",
+            "image" => {
+                out.push_str(&self.image(element));
+                return;
+            }
+            "a" => {
+                let href = self.href(element.attr("href").unwrap_or_default());
+                let content = self.inline_children(element);
+                if element.attr("type") == Some("note") {
+                    out.push_str(&format!(r#"<sup><a epub:type="noteref" href="{}">{}</a></sup>"#, escape_text(&href), content));
+                } else {
+                    out.push_str(&format!(r#"<a href="{}">{}</a>"#, escape_text(&href), content));
+                }
+                return;
+            }
+            _ => "span",
+        };
+        out.push_str(&format!("<{0}>{1}</{0}>", tag, self.inline_children(element)));
+    }
+
+    fn image(&self, element: &Element) -> String {
+        let src = element.attr("href")
+            .and_then(|href| self.images.get(href.trim_start_matches('#')));
+        match src {
+            Some((_, src)) => format!(
+                r#"<img src="../{}" alt="{}"/>"#,
+                escape_text(src),
+                escape_text(element.attr("alt").or(element.attr("title")).unwrap_or_default()),
+            ),
+            None => String::new(),
+        }
+    }
+
+    // 书内链接 #id 改为指向 id 所在的页面
+    fn href(&self, href: &str) -> String {
+        match href.strip_prefix('#') {
+            Some(id) => match self.ids.get(id) {
+                Some(&page) => format!("{}#{}", page_file(page), id),
+                None => href.to_string(),
+            },
+            None => href.to_string(),
+        }
+    }
+
+    fn id_attr(&self, element: &Element) -> String {
+        element.attr("id")
+            .map(|id| format!(r#" id="{}""#, escape_text(id)))
+            .unwrap_or_default()
+    }
+}
+
+// <description> 中的书籍信息
+struct Info {
+    title: Option<String>,
+    annotation: Option<String>,
+    cover: Option<String>,  // 封面图片的 l:href
+    identifier: Option<String>,
+    package: PackageMetadata,
+}
+
+impl Info {
+    fn parse(description: Option<&Element>) -> Self {
+        let title_info = description.and_then(|d| d.child("title-info"));
+        let publish_info = description.and_then(|d| d.child("publish-info"));
+        let document_info = description.and_then(|d| d.child("document-info"));
+
+        let people = |role: &str, name: &str| -> Vec<Contributor> {
+            title_info.into_iter()
+                .flat_map(|info| info.children_named(name))
+                .filter_map(person_name)
+                .map(|name| Contributor { name, role: Some(role.to_string()), file_as: None })
+                .collect()
+        };
+        let title_text = |name: &str| title_info.and_then(|info| info.child_text(name));
+
+        let mut subjects: Vec<String> = title_info.into_iter()
+            .flat_map(|info| info.children_named("genre"))
+            .map(Element::text)
+            .filter(|genre| !genre.is_empty())
+            .collect();
+        if let Some(keywords) = title_text("keywords") {
+            subjects.extend(keywords.split([',', ';']).map(str::trim).filter(|k| !k.is_empty()).map(str::to_string));
+        }
+
+        let series = title_info.and_then(|info| info.child("sequence"))
+            .or_else(|| publish_info.and_then(|info| info.child("sequence")))
+            .and_then(|sequence| {
+                let name = sequence.attr("name")?.trim();
+                (!name.is_empty()).then(|| Series {
+                    name: name.to_string(),
+                    index: sequence.attr("number").and_then(|n| n.trim().parse().ok()),
+                })
+            });
+        let date = title_info.and_then(|info| info.child("date"))
+            .and_then(|date| date.attr("value").map(str::to_string).or_else(|| Some(date.text())))
+            .filter(|date| !date.is_empty())
+            .or_else(|| publish_info.and_then(|info| info.child_text("year")));
+
+        Self {
+            title: title_text("book-title"),
+            annotation: title_info.and_then(|info| info.child("annotation"))
+                .map(|annotation| {
+                    annotation.elements().map(Element::text).filter(|p| !p.is_empty()).collect::<Vec<_>>().join("\n")
+                })
+                .filter(|text| !text.is_empty()),
+            cover: title_info.and_then(|info| info.child("coverpage"))
+                .and_then(|cover| cover.child("image"))
+                .and_then(|image| image.attr("href"))
+                .map(str::to_string),
+            identifier: document_info.and_then(|info| info.child_text("id")),
+            package: PackageMetadata {
+                creators: people("aut", "author"),
+                contributors: people("trl", "translator"),
+                language: title_text("lang"),
+                publisher: publish_info.and_then(|info| info.child_text("publisher")),
+                date,
+                subjects,
+                identifiers: publish_info.and_then(|info| info.child_text("isbn"))
+                    .map(|value| Identifier { value, scheme: Some("ISBN".to_string()) })
+                    .into_iter()
+                    .collect(),
+                series,
+            },
+        }
+    }
+
+    fn metadata(&self) -> HashMap<String, Vec<String>> {
+        let mut metadata = HashMap::new();
+        if let Some(title) = &self.title {
+            metadata.insert("title".to_string(), vec![title.clone()]);
+        }
+        let authors: Vec<String> = self.package.creators.iter().map(|c| c.name.clone()).collect();
+        if !authors.is_empty() {
+            metadata.insert("creator".to_string(), authors);
+        }
+        if let Some(annotation) = &self.annotation {
+            metadata.insert("description".to_string(), vec![annotation.clone()]);
+        }
+        metadata
+    }
+}
+
+// 作者、译者的姓名：名、父名、姓依次连接，都没有时用昵称
+fn person_name(person: &Element) -> Option<String> {
+    let parts: Vec<String> = ["first-name", "middle-name", "last-name"].iter()
+        .filter_map(|name| person.child_text(name))
+        .collect();
+    if parts.is_empty() {
+        person.child_text("nickname")
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use encoding_rs::WINDOWS_1251;
+    use crate::book::{process_html_content, NOTEREF_ATTRIBUTE, NOTE_ATTRIBUTE};
+    use super::*;
+
+    const FB2: &str = r##"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0" xmlns:l="http://www.w3.org/1999/xlink">
+  <description>
+    <title-info>
+      <genre>sf</genre>
+      <author><first-name>Ivan</first-name><middle-name>P.</middle-name><last-name>Petrov</last-name></author>
+      <book-title>Test Book</book-title>
+      <annotation><p>First line.</p><p>Second line.</p></annotation>
+      <keywords>space, robots</keywords>
+      <date value="2001-01-01">2001</date>
+      <coverpage><image l:href="#cover.png"/></coverpage>
+      <lang>ru</lang>
+      <translator><nickname>tr</nickname></translator>
+      <sequence name="Saga" number="2"/>
+    </title-info>
+    <document-info><id>doc-1</id></document-info>
+    <publish-info><publisher>Pub</publisher><isbn>978-5-00-000000-0</isbn></publish-info>
+  </description>
+  <body>
+    <title><p>Test Book</p></title>
+    <section>
+      <title><p>Part One</p></title>
+      <epigraph><p>Quote</p></epigraph>
+      <section id="ch1">
+        <title><p>Chapter 1</p></title>
+        <p>Text<a l:href="#n1" type="note">1</a> and <image l:href="#cover.png"/></p>
+      </section>
+      <section>
+        <title><p>Chapter 2</p></title>
+        <p>See <a l:href="#ch1">chapter 1</a>.</p>
+      </section>
+    </section>
+  </body>
+  <body name="notes">
+    <title><p>Notes</p></title>
+    <section id="n1"><title><p>1</p></title><p>A note.</p></section>
+  </body>
+  <binary id="cover.png" content-type="image/png">iVBORw0KGgo</binary>
+  <binary id="data.bin" content-type="application/octet-stream">AAAA</binary>
+</FictionBook>"##;
+
+    fn page(converted: &Converted, index: usize) -> String {
+        String::from_utf8(converted.resources[&page_path(index)].0.clone()).unwrap()
+    }
+
+    #[test]
+    fn nested_sections_become_toc() {
+        let converted = import_fb2(FB2.as_bytes()).unwrap();
+        let toc = &converted.content.toc;
+        let labels: Vec<&str> = toc.iter().map(|nav| nav.label.as_str()).collect();
+        assert_eq!(labels, ["Test Book", "Part One", "Notes"]);
+        let chapters: Vec<(&str, PathBuf)> = toc[1].children.iter()
+            .map(|nav| (nav.label.as_str(), nav.content.clone()))
+            .collect();
+        assert_eq!(chapters, [("Chapter 1", PathBuf::from("text/0003.xhtml")), ("Chapter 2", PathBuf::from("text/0004.xhtml"))]);
+        assert_eq!(converted.content.spine, ["c1", "c2", "c3", "c4", "c5"]);
+        // 有子章节的章节只有开头部分，书内链接指向 id 所在的页面
+        assert!(page(&converted, 1).contains("Quote") && !page(&converted, 1).contains("Chapter 1"));
+        assert!(page(&converted, 3).contains(r##"href="0003.xhtml#ch1""##));
+    }
+
+    #[test]
+    fn binary_images() {
+        let converted = import_fb2(FB2.as_bytes()).unwrap();
+        let (data, mime) = &converted.resources["images/0001.png"];
+        assert_eq!(data, &BASE64.decode("iVBORw0KGgo").unwrap());
+        assert_eq!(mime, "image/png");
+        assert_eq!(converted.content.resources["image1"], (PathBuf::from("images/0001.png"), "image/png".to_string()));
+        assert_eq!(converted.content.cover_id.as_deref(), Some("image1"));
+        // 非图片的 binary 不作为资源
+        assert_eq!(converted.resources.keys().filter(|path| path.starts_with(IMAGE_DIR)).count(), 1);
+        assert!(page(&converted, 2).contains(r#"<img src="../images/0001.png" alt=""/>"#));
+    }
+
+    #[test]
+    fn title_info_metadata() {
+        let converted = import_fb2(FB2.as_bytes()).unwrap();
+        let content = &converted.content;
+        assert_eq!(content.metadata["title"], ["Test Book"]);
+        assert_eq!(content.metadata["creator"], ["Ivan P. Petrov"]);
+        assert_eq!(content.metadata["description"], ["First line.\nSecond line."]);
+        assert_eq!(content.unique_identifier.as_deref(), Some("doc-1"));
+        let package = &content.package_metadata;
+        assert_eq!(package.contributors, [Contributor { name: "tr".into(), role: Some("trl".into()), file_as: None }]);
+        assert_eq!(package.language.as_deref(), Some("ru"));
+        assert_eq!(package.publisher.as_deref(), Some("Pub"));
+        assert_eq!(package.date.as_deref(), Some("2001-01-01"));
+        assert_eq!(package.subjects, ["sf", "space", "robots"]);
+        assert_eq!(package.identifiers, [Identifier { value: "978-5-00-000000-0".into(), scheme: Some("ISBN".into()) }]);
+        assert_eq!(package.series, Some(Series { name: "Saga".into(), index: Some(2.0) }));
+    }
+
+    #[test]
+    fn notes_become_footnotes() {
+        let converted = import_fb2(FB2.as_bytes()).unwrap();
+        let chapter = page(&converted, 2);
+        assert!(chapter.contains(r##"<sup><a epub:type="noteref" href="0005.xhtml#n1">1</a></sup>"##));
+        let notes = page(&converted, 4);
+        assert!(notes.contains(r#"<aside epub:type="footnote" id="n1">"#));
+        // 和 EPUB 的脚注一样在正文中隐藏，引用处弹出
+        let (notes, _) = process_html_content(&notes, "text/0005.xhtml");
+        assert!(notes.contains(NOTE_ATTRIBUTE));
+        let (chapter, _) = process_html_content(&chapter, "text/0003.xhtml");
+        assert!(chapter.contains(NOTEREF_ATTRIBUTE));
+    }
+
+    #[test]
+    fn undeclared_windows_1251() {
+        let fb2 = FB2.replace(r#" encoding="utf-8""#, "").replace("Chapter 1", "Глава первая");
+        let (data, _, _) = WINDOWS_1251.encode(&fb2);
+        let converted = import_fb2(&data).unwrap();
+        assert_eq!(converted.content.toc[1].children[0].label, "Глава первая");
+    }
+
+    #[test]
+    fn zipped_and_malformed() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("book.FB2", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(FB2.as_bytes()).unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+        assert_eq!(unzip_fb2(&zip).as_deref(), Some(FB2.as_bytes()));
+        assert!(unzip_fb2(FB2.as_bytes()).is_none());
+        assert!(import_fb2(b"<html/>").is_err());
+        assert!(import_fb2(b"<FictionBook><description/></FictionBook>").is_err());
+    }
+}
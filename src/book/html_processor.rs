@@ -0,0 +1,333 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use regex::Regex;
+use html5ever::{namespace_url, ns, LocalName, QualName};
+use scraper::node::Element;
+use scraper::{ElementRef, Html, Node, Selector};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use crate::book::{is_external, normalize_path, resolve_path};
+use crate::book::css::resolve_urls;
+use crate::book::sanitizer::{sanitize, Removal};
+
+/// 书籍资源使用的自定义协议名，桌面端在启动时注册同名协议
+pub const RESOURCE_SCHEME: &str = "epub";
+
+// 路径中需要转义的字符，保留 '/' 作为分隔符
+pub(crate) const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ').add(b'"').add(b'#').add(b'%').add(b'<').add(b'>')
+    .add(b'?').add(b'`').add(b'{').add(b'}');
+
+/// 把压缩包内的资源路径转换为 webview 可以请求的地址。
+/// Windows 和 Android 的 webview 会把自定义协议映射为 `http://<协议名>.<主机>/`。
+pub fn resource_url(path: &str) -> String {
+    let encoded = utf8_percent_encode(path.trim_start_matches('/'), PATH_SEGMENT);
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.book/{}", RESOURCE_SCHEME, encoded)
+    } else {
+        format!("{}://book/{}", RESOURCE_SCHEME, encoded)
+    }
+}
+
+/// 章节中引用的样式表（已解析为完整路径）和 `<style>` 内联样式
+pub fn extract_stylesheets(content: &str, chapter_path: &str) -> (Vec<String>, Vec<String>) {
+    let document = Html::parse_document(content);
+    let link_selector = Selector::parse("link[href]").unwrap();
+    let style_selector = Selector::parse("style").unwrap();
+
+    let links = document.select(&link_selector)
+        .filter(is_stylesheet)
+        .filter_map(|link| resolve_path(chapter_path, link.value().attr("href")?))
+        .collect();
+    let inline = document.select(&style_selector)
+        .map(|style| style.text().collect::<String>())
+        .collect();
+    (links, inline)
+}
+
+// rel 含 stylesheet 的链接，跳过备用样式表；没有 rel 时按 type="text/css" 判断
+fn is_stylesheet(link: &ElementRef) -> bool {
+    match link.value().attr("rel") {
+        Some(rel) => {
+            let rel = rel.to_ascii_lowercase();
+            let mut tokens = rel.split_whitespace();
+            tokens.clone().any(|t| t == "stylesheet") && !tokens.any(|t| t == "alternate")
+        }
+        None => link.value().attr("type").is_some_and(|t| t.trim().eq_ignore_ascii_case("text/css")),
+    }
+}
+
+/// 书内链接改写后存放目标的属性，值为压缩包内完整路径加锚点
+pub const LINK_ATTRIBUTE: &str = "data-epub-href";
+
+/// 注释引用链接上的标记属性，点击时弹出注释内容而不是跳转
+pub const NOTEREF_ATTRIBUTE: &str = "data-epub-noteref";
+
+/// 章节 `<body>` 改写成的 div 上的标记属性。正文插入页面时 `<body>` 本身会被丢弃，
+/// 改写后 body 上的 class 和 id 得以保留，书籍样式中的 `body` 选择器对应这个元素
+pub const BODY_ATTRIBUTE: &str = "data-epub-body";
+
+/// 正文中脚注容器（`<aside epub:type="footnote">` 等）上的标记属性，阅读时隐藏
+pub const NOTE_ATTRIBUTE: &str = "data-epub-note";
+
+/// 解析章节正文并改写为阅读器可以直接显示的 HTML：
+/// 按白名单清理脚本等内容，去掉样式元素，书内链接和资源引用按章节自身路径解析，并标记注释。
+/// 同时返回清理时删除的内容。
+pub fn process_html_content(content: &str, chapter_path: &str) -> (String, Vec<Removal>) {
+    let mut document = Html::parse_document(&expand_self_closing(content));
+    let removals = sanitize(&mut document);
+
+    // 先在只读的树上找出要删除的样式元素和依赖上下文判断的注释引用，再逐个改写属性。
+    // 样式由阅读器限定作用域后单独注入，原样留在正文里会影响整个界面。
+    let mut removed = Vec::new();
+    let mut noterefs = HashSet::new();
+    for element in document.root_element().descendants().filter_map(ElementRef::wrap) {
+        match element.value().name() {
+            "style" | "link" => removed.push(element.id()),
+            "a" if is_noteref(&element) => {
+                noterefs.insert(element.id());
+            }
+            _ => {}
+        }
+    }
+    for id in removed {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    let ids: Vec<_> = document.tree.nodes().map(|node| node.id()).collect();
+    for id in ids {
+        let Some(mut node) = document.tree.get_mut(id) else { continue };
+        if let Node::Element(element) = node.value() {
+            rewrite_element(element, chapter_path, noterefs.contains(&id));
+        }
+    }
+
+    (document.html(), removals)
+}
+
+// 章节是 XHTML，`<span id="p1"/>` 这类自闭合的非空元素在 HTML 解析器里会被当成开始标签，
+// 把后面的内容都包进去，解析前先展开成成对的标签
+fn expand_self_closing(content: &str) -> Cow<'_, str> {
+    const VOID_ELEMENTS: &[&str] = &[
+        "area", "base", "br", "col", "embed", "hr", "img", "input",
+        "link", "meta", "param", "source", "track", "wbr",
+    ];
+    static SELF_CLOSING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"<([a-zA-Z][\w:.-]*)((?:\s+[^\s/>=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'>]+))?)*)\s*/>"#).unwrap());
+    SELF_CLOSING.replace_all(content, |caps: &regex::Captures| {
+        if VOID_ELEMENTS.contains(&caps[1].to_ascii_lowercase().as_str()) {
+            caps[0].to_string()
+        } else {
+            format!("<{}{}></{}>", &caps[1], &caps[2], &caps[1])
+        }
+    })
+}
+
+// 改写一个元素的属性：
+// 书内链接解析为完整路径放到 data-epub-href，原 href 换成锚点，避免 webview 真的导航离开阅读器页面；
+// src、srcset、SVG 的 href/xlink:href、<object data>、<video poster> 换成资源地址；
+// 内联 style 中的 url() 同样解析；<body> 改写为带标记属性的 div。
+fn rewrite_element(element: &mut Element, chapter_path: &str, noteref: bool) {
+    let name = element.name.local.to_string();
+    let mut added: Vec<(&str, String)> = Vec::new();
+
+    for (key, value) in element.attrs.iter_mut() {
+        let rewritten = match (&*key.local, name.as_str()) {
+            ("href", "a" | "area") if key.ns == ns!() => {
+                let href = value.trim();
+                let fragment = href.split_once('#').map(|(_, f)| f).unwrap_or("");
+                let target = if href.starts_with('#') {
+                    Some(normalize_path(chapter_path))
+                } else {
+                    resolve_path(chapter_path, href)
+                };
+                target.map(|path| {
+                    added.push((LINK_ATTRIBUTE, format!("{}#{}", path, fragment)));
+                    format!("#{}", fragment)
+                })
+            }
+            ("src", _) | ("poster", "video") | ("data", "object") | ("href", "image" | "use") => {
+                resource_link(value, chapter_path)
+            }
+            ("srcset", _) => Some(rewrite_srcset(value, chapter_path)),
+            ("style", _) => Some(resolve_urls(value, chapter_path)),
+            _ => None,
+        };
+        if let Some(rewritten) = rewritten {
+            *value = rewritten.into();
+        }
+    }
+
+    if noteref {
+        added.push((NOTEREF_ATTRIBUTE, String::new()));
+    }
+    if name == "body" {
+        element.name = QualName::new(None, ns!(html), LocalName::from("div"));
+        added.push((BODY_ATTRIBUTE, String::new()));
+    }
+    if name == "aside" && is_note_container(element) {
+        added.push((NOTE_ATTRIBUTE, String::new()));
+    }
+    if !added.is_empty() {
+        for (attr, value) in added {
+            element.attrs.push((QualName::new(None, ns!(), LocalName::from(attr)), value.into()));
+        }
+        // Element::attr 按名字二分查找，属性必须保持有序
+        element.attrs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    }
+}
+
+// `srcset` 中每个候选地址单独改写，保留宽度或像素密度描述。
+// 按 HTML 规范，地址到空白为止，其中可以有逗号（如 data: 地址），地址末尾的逗号才是分隔符
+fn rewrite_srcset(srcset: &str, chapter_path: &str) -> String {
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+        let (url, after) = rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len()));
+        let trimmed = url.trim_end_matches(',');
+        let descriptor = if trimmed.len() < url.len() {
+            rest = after;
+            ""
+        } else {
+            let end = after.find(',').unwrap_or(after.len());
+            rest = &after[end..];
+            after[..end].trim()
+        };
+        let url = resource_link(trimmed, chapter_path).unwrap_or_else(|| trimmed.to_string());
+        candidates.push(if descriptor.is_empty() { url } else { format!("{} {}", url, descriptor) });
+    }
+    candidates.join(", ")
+}
+
+// 单个资源引用换成资源地址，保留锚点（如 SVG 精灵图的 `#icon`）。外部地址和纯锚点不处理。
+fn resource_link(href: &str, chapter_path: &str) -> Option<String> {
+    let path = resolve_path(chapter_path, href)?;
+    let url = resource_url(&path);
+    Some(match href.split_once('#') {
+        Some((_, fragment)) if !fragment.is_empty() => format!("{}#{}", url, fragment),
+        _ => url,
+    })
+}
+
+// 注释引用：带 `epub:type`/`role` 为 noteref 的书内链接，
+// 以及 `<sup><a href="#fn1">` 和 `<a href="#fn1"><sup>` 这类常见写法
+fn is_noteref(link: &ElementRef) -> bool {
+    let Some(href) = link.value().attr("href") else { return false };
+    if is_external(href.trim()) {
+        return false;
+    }
+    let semantic = ["epub:type", "role"].iter()
+        .filter_map(|attr| link.value().attr(attr))
+        .any(|value| value.split_whitespace().any(|t| t == "noteref" || t == "doc-noteref"));
+    let in_sup = link.parent()
+        .and_then(ElementRef::wrap)
+        .is_some_and(|parent| parent.value().name() == "sup");
+    let wraps_sup = link.children()
+        .find(|child| !child.value().as_text().is_some_and(|t| t.trim().is_empty()))
+        .and_then(ElementRef::wrap)
+        .is_some_and(|child| child.value().name() == "sup");
+    semantic || in_sup || wraps_sup
+}
+
+// `<aside epub:type="footnote">` 及 endnote、rearnote 和对应的 ARIA role
+fn is_note_container(element: &Element) -> bool {
+    ["epub:type", "role"].iter()
+        .filter_map(|attr| element.attr(attr))
+        .flat_map(|value| value.split_whitespace())
+        .map(|t| t.trim_start_matches("doc-").trim_end_matches('s'))
+        .any(|t| matches!(t, "footnote" | "endnote" | "rearnote"))
+}
+
+/// 从已处理的章节正文中取出 id 为 `id` 的注释内容。
+/// 目标是链接或行内元素时（如 `<p><a id="fn1">1</a> 注释…</p>`）取它所在的整段。
+pub fn note_content(html: &str, id: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("[id], a[name]").unwrap();
+    let target = document.select(&selector)
+        .find(|el| el.value().id() == Some(id) || el.value().attr("name") == Some(id))?;
+
+    let note = if matches!(target.value().name(), "a" | "span" | "sup" | "sub") {
+        target.parent().and_then(ElementRef::wrap).unwrap_or(target)
+    } else {
+        target
+    };
+    let inner = note.inner_html();
+    let inner = inner.trim();
+    (!inner.is_empty()).then(|| inner.to_string())
+}
+
+/// 文档 `<title>` 的文本，空白已合并
+pub fn html_title(html: &str) -> Option<String> {
+    element_text(&Html::parse_document(html), "title")
+}
+
+/// 文档中第一个 `<h1>`～`<h6>` 的文本，空白已合并
+pub fn first_heading(html: &str) -> Option<String> {
+    element_text(&Html::parse_document(html), HEADINGS)
+}
+
+// 同时取 `<title>` 和第一个标题元素，文档只解析一次
+pub(crate) fn title_and_heading(html: &str) -> (Option<String>, Option<String>) {
+    let document = Html::parse_document(html);
+    (element_text(&document, "title"), element_text(&document, HEADINGS))
+}
+
+const HEADINGS: &str = "h1, h2, h3, h4, h5, h6";
+
+fn element_text(document: &Html, selector: &str) -> Option<String> {
+    let selector = Selector::parse(selector).unwrap();
+    document.select(&selector)
+        .map(|el| el.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAPTER: &str = "OEBPS/Text/ch1.xhtml";
+
+    #[test]
+    fn resource_links() {
+        assert_eq!(resource_link("../Images/a b.png", CHAPTER), Some(resource_url("OEBPS/Images/a b.png")));
+        assert_eq!(resource_link("../Images/a%20b.png", CHAPTER), Some(resource_url("OEBPS/Images/a b.png")));
+        assert_eq!(resource_link("img.png?v=1", CHAPTER), Some(resource_url("OEBPS/Text/img.png")));
+        // 保留 SVG 精灵图的锚点
+        assert_eq!(resource_link("../Images/icons.svg#star", CHAPTER), Some(format!("{}#star", resource_url("OEBPS/Images/icons.svg"))));
+        for href in ["#star", "https://example.com/a.png", "//example.com/a.png", "data:image/png;base64,AAAA", ""] {
+            assert_eq!(resource_link(href, CHAPTER), None, "{}", href);
+        }
+    }
+
+    #[test]
+    fn srcset_descriptors() {
+        let (small, large) = (resource_url("OEBPS/Images/s.png"), resource_url("OEBPS/Images/l.png"));
+        assert_eq!(rewrite_srcset("../Images/s.png 480w, ../Images/l.png 1080w", CHAPTER), format!("{} 480w, {} 1080w", small, large));
+        assert_eq!(rewrite_srcset(" ../Images/s.png,  ../Images/l.png  2x ", CHAPTER), format!("{}, {} 2x", small, large));
+        assert_eq!(rewrite_srcset("../Images/s.png 1x,\n  https://example.com/l.png 2x", CHAPTER), format!("{} 1x, https://example.com/l.png 2x", small));
+        // data: 地址中的逗号不是分隔符
+        assert_eq!(
+            rewrite_srcset("data:image/png;base64,AAAA 1x, ../Images/l.png 2x", CHAPTER),
+            format!("data:image/png;base64,AAAA 1x, {} 2x", large),
+        );
+        assert_eq!(rewrite_srcset("", CHAPTER), "");
+    }
+
+    #[test]
+    fn rewrites_resource_attributes() {
+        let html = r#"<html><body><img src="../Images/s.png" srcset="../Images/s.png 1x, ../Images/l.png 2x"/>
+<img src="data:image/gif;base64,R0lGOD"/><img src="https://example.com/x.png"/>
+<video poster="../Images/p.png"><source src="../Video/v.mp4"/></video></body></html>"#;
+        let (content, _) = process_html_content(html, CHAPTER);
+        assert!(content.contains(&format!(r#"srcset="{} 1x, {} 2x""#, resource_url("OEBPS/Images/s.png"), resource_url("OEBPS/Images/l.png"))));
+        assert!(content.contains(&format!(r#"poster="{}""#, resource_url("OEBPS/Images/p.png"))));
+        assert!(content.contains(&format!(r#"src="{}""#, resource_url("OEBPS/Video/v.mp4"))));
+        assert!(content.contains(r#"src="data:image/gif;base64,R0lGOD""#));
+        assert!(content.contains(r#"src="https://example.com/x.png""#));
+    }
+}
@@ -0,0 +1,726 @@
+use epub::doc::{EpubDoc, NavPoint};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use sha1::{Digest, Sha1};
+use crate::book::cover::first_image;
+use crate::book::encryption::parse_encryption;
+use crate::book::cbz::import_cbz;
+use crate::book::fb2::{import_fb2, unzip_fb2};
+use crate::book::mobi::import_mobi;
+use crate::book::txt::import_txt;
+use crate::book::{BookArchive, BookContent, BookError, ChapterPatterns, BookMetadata, Chapter, ChapterCache, DEFAULT_CHAPTER_CACHE_BYTES};
+use crate::book::{EncryptedResource, FontObfuscation, Landmark, PageTarget};
+use crate::book::{page_viewport, parse_smil, FixedPage, MediaOverlay, Removal, Rendition, DEFAULT_ACTIVE_CLASS, SPINE_ATTRIBUTE};
+use crate::book::{css_imports, decode_text, extract_stylesheets, normalize_path, note_content, path_to_string, process_html_content, rescope_css, scope_css, CONTENT_SCOPE};
+
+/// 一本已打开的书。不依赖任何界面，阅读器、命令行工具和测试都通过它读取书籍。
+#[derive(Debug)]
+pub struct Book {
+    path: PathBuf,
+    archive: BookArchive,
+    content: BookContent,
+    metadata: BookMetadata,
+    chapters: ChapterCache,
+    stylesheets: HashMap<String, String>,
+    removals: HashMap<String, Vec<Removal>>,  // 各章节清理时删除的内容，按章节路径存放
+}
+
+const MAX_IMPORT_DEPTH: usize = 4;
+
+// 按 Kindle 格式打开的文件扩展名
+const MOBI_EXTENSIONS: [&str; 3] = ["mobi", "azw3", "azw"];
+
+impl Book {
+    /// 打开 EPUB 文件并解析目录、书脊和元数据，整个过程只打开一次压缩包。
+    /// `.txt` 文件按默认的章节规则导入，Kindle、FB2 和 CBZ 格式的文件转换后打开。
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, BookError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(BookError::NotFound(path.to_path_buf()));
+        }
+        if has_extension(path, "txt") {
+            return Self::open_txt(path, &ChapterPatterns::default());
+        }
+        if MOBI_EXTENSIONS.iter().any(|ext| has_extension(path, ext)) {
+            return Self::open_mobi(path);
+        }
+        if has_extension(path, "fb2") || is_fb2_zip(path) {
+            return Self::open_fb2(path);
+        }
+        if has_extension(path, "cbz") {
+            return Self::open_cbz(path);
+        }
+
+        let mut doc = EpubDoc::new(path)
+            .map_err(|e| BookError::from_doc_error(path, e))?;
+        let encrypted = doc.get_resource_str_by_path("META-INF/encryption.xml")
+            .map(|xml| parse_encryption(&xml))
+            .unwrap_or_default();
+        Self::check_readable(&mut doc, &encrypted)?;
+        let content = BookContent::from_epub(&mut doc);
+        let fonts = FontObfuscation::new(
+            &encrypted,
+            content.unique_identifier.as_deref(),
+            content.metadata.get("identifier").map(Vec::as_slice).unwrap_or_default(),
+        );
+
+        Ok(Self::assemble(path, content, BookArchive::new(doc, fonts)))
+    }
+
+    /// 打开 TXT 小说，按 `patterns` 识别卷和章节标题，书名取文件名。
+    /// 文本编码自动识别，GBK 等编码的文本可以直接打开。
+    pub fn open_txt<P: AsRef<Path>>(path: P, patterns: &ChapterPatterns) -> Result<Self, BookError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(BookError::NotFound(path.to_path_buf()));
+        }
+        let data = std::fs::read(path)?;
+        let title = path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let converted = import_txt(&decode_text(&data), &title, patterns)
+            .ok_or(BookError::EmptyText)?;
+        Ok(Self::assemble(path, converted.content, BookArchive::from_resources(converted.resources)))
+    }
+
+    /// 打开没有 DRM 的 Kindle 电子书。MOBI 文件中带有 KF8 部分时优先使用 KF8。
+    pub fn open_mobi<P: AsRef<Path>>(path: P) -> Result<Self, BookError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(BookError::NotFound(path.to_path_buf()));
+        }
+        let data = std::fs::read(path)?;
+        let converted = import_mobi(&data)?;
+        Ok(Self::assemble(path, converted.content, BookArchive::from_resources(converted.resources)))
+    }
+
+    /// 打开 FictionBook 电子书，`.fb2.zip` 压缩包中取第一个 `.fb2` 文件
+    pub fn open_fb2<P: AsRef<Path>>(path: P) -> Result<Self, BookError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(BookError::NotFound(path.to_path_buf()));
+        }
+        let data = std::fs::read(path)?;
+        let data = if data.starts_with(b"PK") {
+            unzip_fb2(&data).ok_or_else(|| BookError::MalformedFb2("压缩包中没有 FB2 文件".to_string()))?
+        } else {
+            data
+        };
+        let converted = import_fb2(&data)?;
+        Ok(Self::assemble(path, converted.content, BookArchive::from_resources(converted.resources)))
+    }
+
+    /// 打开 CBZ 漫画，每张图片一页，按固定版式显示
+    pub fn open_cbz<P: AsRef<Path>>(path: P) -> Result<Self, BookError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(BookError::NotFound(path.to_path_buf()));
+        }
+        let file = BufReader::new(File::open(path)?);
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| BookError::NotZip(e.to_string()))?;
+        let title = path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let converted = import_cbz(&mut archive, &title).ok_or(BookError::NoImages)?;
+        Ok(Self::assemble(path, converted.content, BookArchive::from_comic(archive, converted.resources)))
+    }
+
+    fn assemble(path: &Path, content: BookContent, archive: BookArchive) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            metadata: (&content).into(),
+            content,
+            archive,
+            chapters: ChapterCache::new(DEFAULT_CHAPTER_CACHE_BYTES),
+            stylesheets: HashMap::new(),
+            removals: HashMap::new(),
+        }
+    }
+
+    // 拒绝 DRM 加密的书和书脊里没有可显示内容的书
+    fn check_readable(
+        doc: &mut EpubDoc<BufReader<File>>,
+        encrypted: &[EncryptedResource],
+    ) -> Result<(), BookError> {
+        // Adobe ADEPT 等 DRM 会附带 rights.xml
+        if doc.get_resource_by_path("META-INF/rights.xml").is_some() {
+            return Err(BookError::Encrypted);
+        }
+        // 字体混淆不算 DRM，读取字体时再还原
+        if encrypted.iter().any(|resource| !resource.is_font_obfuscation()) {
+            return Err(BookError::Encrypted);
+        }
+
+        if doc.spine.is_empty() {
+            return Err(BookError::MalformedPackage("书脊为空".to_string()));
+        }
+        let spine_mimes: Vec<&str> = doc.spine.iter()
+            .filter_map(|id| doc.resources.get(id))
+            .map(|(_, mime)| mime.as_str())
+            .collect();
+        if !spine_mimes.iter().any(|mime| is_document_mime(mime)) {
+            let mime = spine_mimes.first().copied().unwrap_or("unknown");
+            return Err(BookError::UnsupportedMedia(mime.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// 设置章节缓存的内存上限（字节），超出的部分立即淘汰
+    pub fn set_cache_limit(&mut self, bytes: usize) {
+        self.chapters.set_limit(bytes);
+    }
+
+    pub fn with_cache_limit(mut self, bytes: usize) -> Self {
+        self.set_cache_limit(bytes);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn metadata(&self) -> &BookMetadata {
+        &self.metadata
+    }
+
+    pub fn content(&self) -> &BookContent {
+        &self.content
+    }
+
+    pub fn toc(&self) -> &[NavPoint] {
+        &self.content.toc
+    }
+
+    pub fn landmarks(&self) -> &[Landmark] {
+        &self.content.landmarks
+    }
+
+    pub fn page_list(&self) -> &[PageTarget] {
+        &self.content.page_list
+    }
+
+    /// 落在这个书脊项里的印刷页码及其锚点，按书中顺序排列。
+    /// 章节开头不是新的一页时，先放上前面章节的最后一页（锚点为空，表示章节开头）。
+    pub fn page_anchors(&self, spine_index: usize) -> Vec<(String, Option<String>)> {
+        let mut previous = None;
+        let mut anchors = Vec::new();
+        for page in &self.content.page_list {
+            let Some((idx, anchor)) = self.locate(&page.href) else { continue };
+            if idx < spine_index {
+                previous = Some(page.label.clone());
+            } else if idx == spine_index {
+                anchors.push((page.label.clone(), anchor));
+            }
+        }
+        let starts_with_page = anchors.first().is_some_and(|(_, anchor)| anchor.is_none());
+        if let (false, Some(label)) = (starts_with_page, previous) {
+            anchors.insert(0, (label, None));
+        }
+        anchors
+    }
+
+    /// 页码对应的书内地址。没有完全相同的页码时，数字页码取不超过它的最近一页。
+    pub fn page_href(&self, label: &str) -> Option<String> {
+        let label = label.trim();
+        let pages = &self.content.page_list;
+        if let Some(page) = pages.iter().find(|page| page.label.eq_ignore_ascii_case(label)) {
+            return Some(page.href.clone());
+        }
+        let number: u32 = label.parse().ok()?;
+        pages.iter()
+            .filter_map(|page| page.label.parse::<u32>().ok().map(|n| (n, page)))
+            .filter(|(n, _)| *n <= number)
+            .max_by_key(|(n, _)| *n)
+            .map(|(_, page)| page.href.clone())
+    }
+
+    /// 正文开始的位置（bodymatter 地标），首次打开时跳过封面、扉页等
+    pub fn start_location(&self) -> Option<(usize, Option<String>)> {
+        self.content.landmarks.iter()
+            .find(|landmark| landmark.kind == "bodymatter")
+            .and_then(|landmark| self.locate(&landmark.href))
+    }
+
+    pub fn spine_len(&self) -> usize {
+        self.content.spine.len()
+    }
+
+    /// 按完整路径读取书中的任意资源，返回内容和 MIME 类型
+    pub fn resource(&self, path: &str) -> Option<(Vec<u8>, String)> {
+        self.archive.read(path)
+    }
+
+    /// 共享的压缩包句柄，供资源协议等在阅读器之外按需读取资源
+    pub fn archive(&self) -> BookArchive {
+        self.archive.clone()
+    }
+
+    /// 封面图片及其 MIME 类型。OPF 没有登记封面图片时，取第一个书脊项中的第一张图片。
+    pub fn cover(&self) -> Option<(Vec<u8>, String)> {
+        let cover_path = self.content.cover_id.as_ref()
+            .and_then(|id| self.content.resources.get(id))
+            .map(|(path, _)| path_to_string(path));
+        // 有的书把封面登记为一个只含图片的 XHTML 页面
+        let page = match cover_path {
+            Some(path) => match self.archive.read(&path) {
+                Some((data, mime)) if mime.starts_with("image/") => return Some((data, mime)),
+                Some((data, _)) => Some((data, path)),
+                None => None,
+            },
+            None => None,
+        };
+        let (html, page_path) = match page {
+            Some(page) => page,
+            None => {
+                let (path, _) = self.content.spine.first()
+                    .and_then(|id| self.content.resources.get(id))?;
+                let path = path_to_string(path);
+                (self.archive.read(&path)?.0, path)
+            }
+        };
+
+        let image_path = first_image(&decode_text(&html), &page_path)?;
+        self.archive.read(&image_path)
+            .filter(|(_, mime)| mime.starts_with("image/"))
+    }
+
+    /// 书的身份标识，用作封面缓存等按书存放的数据的键。
+    /// 优先用 OPF 的唯一标识符，没有时用文件路径。
+    pub fn identity(&self) -> String {
+        let source = self.content.unique_identifier.as_deref()
+            .map(str::trim)
+            .filter(|uid| !uid.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| path_to_string(&self.path));
+        Sha1::digest(source.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// 按目录顺序号读取章节
+    pub fn chapter(&mut self, play_order: usize) -> Chapter {
+        // 目录项能对应到书脊时走缓存
+        if let Some(chapter) = self.get_spine_index(play_order)
+            .and_then(|idx| self.chapter_by_spine(idx))
+        {
+            return Chapter { play_order, ..chapter };
+        }
+
+        if let Some(path) = self.content.order_path.get(&play_order).cloned() {
+            // 不在书脊中的目录项，路径已是压缩包内的完整路径，去掉锚点后直接读取
+            let path_str = path_to_string(&path);
+            let normalized_path = normalize_path(path_str.split('#').next().unwrap_or(""));
+            let content = self.archive.read(&normalized_path).map(|(data, _)| data);
+
+            if let Some(content) = content {
+                let (processed_content, styles) = self.render(&decode_text(&content), &normalized_path);
+                return Chapter {
+                    id: path.display().to_string(),
+                    content: processed_content,
+                    styles,
+                    path,
+                    play_order,
+                    processed: true,
+                };
+            }
+            Chapter {
+                id: path.display().to_string(),
+                content: "无法读取章节".into(),
+                styles: String::new(),
+                path,
+                play_order,
+                processed: true,
+            }
+        } else {
+            Chapter {
+                id: play_order.to_string(),
+                content: "章节不存在".into(),
+                styles: String::new(),
+                path: PathBuf::new(),
+                play_order,
+                processed: true,
+            }
+        }
+    }
+
+    /// 按书脊序号读取章节，`play_order` 取该书脊项或它之前最近的目录项
+    pub fn chapter_by_spine(&mut self, spine_index: usize) -> Option<Chapter> {
+        let play_order = (0..=spine_index).rev()
+            .find_map(|idx| self.spine_to_order(idx))
+            .unwrap_or(0);
+        if let Some(cached) = self.chapters.get(spine_index) {
+            return Some(Chapter { play_order, ..cached });
+        }
+
+        let spine_id = self.content.spine.get(spine_index)?.clone();
+        let (path, _) = self.content.resources.get(&spine_id)?.clone();
+        let (raw_content, _) = self.archive.read(&path_to_string(&path))?;
+        let (processed, styles) = self.render(
+            &decode_text(&raw_content),
+            &path_to_string(&path),
+        );
+
+        let chapter = Chapter {
+            id: spine_id,
+            content: processed,
+            styles,
+            path,
+            play_order,
+            processed: true,
+        };
+        self.chapters.insert(spine_index, chapter.clone());
+        Some(chapter)
+    }
+
+    /// 已打开的章节在清理时删除的内容，按书脊顺序排列。章节按需处理，没打开过的章节不在其中。
+    pub fn removals(&self) -> Vec<(String, Vec<Removal>)> {
+        self.content.spine.iter()
+            .filter_map(|id| self.content.resources.get(id))
+            .map(|(path, _)| path_to_string(path))
+            .filter_map(|path| {
+                let removals = self.removals.get(&path)?.clone();
+                Some((path, removals))
+            })
+            .collect()
+    }
+
+    /// 书中是否有媒体叠加朗读
+    pub fn has_media_overlays(&self) -> bool {
+        !self.content.overlays.items.is_empty()
+    }
+
+    /// 书脊项的朗读片段，只保留指向该书脊项本身的片段。没有媒体叠加时返回 `None`。
+    pub fn media_overlay(&self, spine_index: usize) -> Option<MediaOverlay> {
+        let spine_id = self.content.spine.get(spine_index)?;
+        let smil_path = self.content.overlays.items.get(spine_id)?;
+        let chapter_path = self.content.resources.get(spine_id).map(|(path, _)| normalize_path(&path_to_string(path)))?;
+        let (data, _) = self.archive.read(smil_path)?;
+
+        let clips: Vec<_> = parse_smil(&decode_text(&data), smil_path)
+            .into_iter()
+            .filter(|clip| clip.text.split('#').next() == Some(chapter_path.as_str()))
+            .collect();
+        (!clips.is_empty()).then(|| MediaOverlay {
+            clips,
+            active_class: self.content.overlays.active_class.clone()
+                .unwrap_or_else(|| DEFAULT_ACTIVE_CLASS.to_string()),
+        })
+    }
+
+    pub fn rendition(&self) -> &Rendition {
+        &self.content.rendition
+    }
+
+    /// 固定版式书脊项要显示的页面：窗口方向允许时包含同一跨页的另一页，按从左到右排列。
+    /// `rtl` 为实际使用的翻页方向（可能被用户覆盖）。流式排版的书脊项返回空列表。
+    pub fn fixed_pages(&mut self, spine_index: usize, landscape: bool, rtl: bool) -> Vec<FixedPage> {
+        let rendition = &self.content.rendition;
+        if !rendition.is_fixed_layout(spine_index) {
+            return Vec::new();
+        }
+        let indices = if rendition.shows_spread(landscape) {
+            rendition.spread_pages(spine_index, rtl)
+        } else {
+            vec![spine_index]
+        };
+        let fallback = rendition.viewport;
+
+        indices.into_iter()
+            .filter_map(|idx| {
+                let chapter = self.chapter_by_spine(idx)?;
+                let scope = format!("{}[{}=\"{}\"]", CONTENT_SCOPE, SPINE_ATTRIBUTE, idx);
+                Some(FixedPage {
+                    spine_index: idx,
+                    viewport: page_viewport(&chapter.content).or(fallback),
+                    content: chapter.content,
+                    styles: rescope_css(&chapter.styles, &scope),
+                })
+            })
+            .collect()
+    }
+
+    // 处理章节正文，并收集它引用的样式表和内联样式
+    fn render(&mut self, html: &str, chapter_path: &str) -> (String, String) {
+        let (links, inline) = extract_stylesheets(html, chapter_path);
+        let mut styles = String::new();
+        for path in links {
+            styles.push_str(&self.stylesheet(&path, 0));
+        }
+        for css in inline.iter().chain(self.content.extra_css.clone().iter()) {
+            for import in css_imports(css, chapter_path) {
+                styles.push_str(&self.stylesheet(&import, 1));
+            }
+            styles.push_str(&scope_css(css, chapter_path));
+        }
+
+        let (content, removals) = process_html_content(html, chapter_path);
+        if removals.is_empty() {
+            self.removals.remove(chapter_path);
+        } else {
+            self.removals.insert(chapter_path.to_string(), removals);
+        }
+        (content, styles)
+    }
+
+    // 读取并限定作用域后的样式表，同一本书的章节通常共用几份样式表，所以单独缓存
+    fn stylesheet(&mut self, path: &str, depth: usize) -> String {
+        if let Some(css) = self.stylesheets.get(path) {
+            return css.clone();
+        }
+        let Some((data, _)) = self.archive.read(path) else {
+            return String::new();
+        };
+        let raw = decode_text(&data);
+
+        let mut scoped = String::new();
+        // 限制 @import 的嵌套层数，防止循环引用
+        if depth < MAX_IMPORT_DEPTH {
+            for import in css_imports(&raw, path) {
+                scoped.push_str(&self.stylesheet(&import, depth + 1));
+            }
+        }
+        scoped.push_str(&scope_css(&raw, path));
+        self.stylesheets.insert(path.to_string(), scoped.clone());
+        scoped
+    }
+
+    pub fn get_spine_index(&self, play_order: usize) -> Option<usize> {
+        self.content.get_spine_index(play_order)
+    }
+
+    pub fn start_order(&self) -> Option<usize> {
+        self.content.start_order()
+    }
+
+    pub fn anchor(&self, play_order: usize) -> Option<String> {
+        self.content.anchor(play_order)
+    }
+
+    pub fn toc_anchors(&self, spine_index: usize) -> Vec<(usize, Option<String>)> {
+        self.content.toc_anchors(spine_index)
+    }
+
+    /// 把书内链接（完整路径，可带锚点）定位到书脊序号和锚点
+    pub fn locate(&self, href: &str) -> Option<(usize, Option<String>)> {
+        let (path, fragment) = match href.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment.to_string()).filter(|f| !f.is_empty())),
+            None => (href, None),
+        };
+        // 链接已解码并去掉了 `..`，清单中的路径也要同样处理才能比较
+        let spine_index = self.content.spine.iter().position(|id| {
+            self.content.resources.get(id)
+                .is_some_and(|(spine_path, _)| normalize_path(&path_to_string(spine_path)) == path)
+        })?;
+        Some((spine_index, fragment))
+    }
+
+    /// 读取书内链接指向的注释内容，注释可以在当前章节或单独的注释文件里
+    pub fn footnote(&mut self, href: &str) -> Option<String> {
+        let (spine_index, anchor) = self.locate(href)?;
+        let chapter = self.chapter_by_spine(spine_index)?;
+        note_content(&chapter.content, &anchor?)
+    }
+
+    pub fn spine_to_order(&self, spine_index: usize) -> Option<usize> {
+        self.content.spine_to_order.get(&spine_index).copied()
+    }
+}
+
+// 文件扩展名，不区分大小写
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+// 压缩的 FB2：文件名以 `.fb2.zip` 结尾，或是其他 `.zip` 压缩包中有 `.fb2` 文件
+fn is_fb2_zip(path: &Path) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if name.ends_with(".fb2.zip") {
+        return true;
+    }
+    has_extension(path, "zip") && File::open(path).ok()
+        .and_then(|file| zip::ZipArchive::new(BufReader::new(file)).ok())
+        .is_some_and(|archive| archive.file_names().any(|name| name.to_ascii_lowercase().ends_with(".fb2")))
+}
+
+// 书脊中 webview 能直接显示的内容类型
+fn is_document_mime(mime: &str) -> bool {
+    matches!(mime,
+        "application/xhtml+xml" | "text/html" | "image/svg+xml" |
+        "application/xml" | "text/xml"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use crate::book::resource_url;
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+
+    const OPF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:identifier id="uid">urn:uuid:0f6c1e2a-0000-4000-8000-000000000001</dc:identifier>
+    <dc:title>测试之书</dc:title>
+    <dc:creator opf:role="aut">作者甲</dc:creator>
+    <dc:language>zh</dc:language>
+    <meta name="cover" content="cover"/>
+  </metadata>
+  <manifest>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="cover" href="Images/cover.png" media-type="image/png"/>
+    <item id="css" href="Styles/style.css" media-type="text/css"/>
+    <item id="c1" href="Text/chapter%201.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="Text/ch2.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine toc="ncx">
+    <itemref idref="c1"/>
+    <itemref idref="c2"/>
+  </spine>
+</package>"#;
+
+    const NCX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint id="n1" playOrder="1">
+      <navLabel><text>第一章</text></navLabel><content src="Text/chapter%201.xhtml"/>
+    </navPoint>
+    <navPoint id="n2" playOrder="2">
+      <navLabel><text>第二章</text></navLabel><content src="Text/ch2.xhtml"/>
+      <navPoint id="n3" playOrder="3">
+        <navLabel><text>第二节</text></navLabel><content src="Text/ch2.xhtml#s2"/>
+      </navPoint>
+    </navPoint>
+  </navMap>
+</ncx>"#;
+
+    const CHAPTER_1: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><head><title>第一章</title>
+<link rel="stylesheet" type="text/css" href="../Styles/style.css"/></head>
+<body class="calibre"><h1 id="top">第一章</h1><p>第一章正文</p>
+<p><a href="ch2.xhtml#s2">下一节</a><img src="../Images/cover.png" alt=""/></p></body></html>"#;
+
+    const CHAPTER_2: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><head><title>第二章</title></head>
+<body><h1>第二章</h1><p><a href="chapter%201.xhtml#top">回到第一章</a></p>
+<h2 id="s2">第二节</h2><p>第二节正文</p></body></html>"#;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    // 在临时目录生成一本小 EPUB，每个测试用不同的文件名
+    fn fixture(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("fast_epub_{}_{}.epub", std::process::id(), name));
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let files: [(&str, &[u8]); 8] = [
+            ("mimetype", b"application/epub+zip"),
+            ("META-INF/container.xml", CONTAINER.as_bytes()),
+            ("OEBPS/content.opf", OPF.as_bytes()),
+            ("OEBPS/toc.ncx", NCX.as_bytes()),
+            ("OEBPS/Images/cover.png", PNG),
+            ("OEBPS/Styles/style.css", b"body.calibre { margin: 0 } p { text-indent: 2em }"),
+            ("OEBPS/Text/chapter 1.xhtml", CHAPTER_1.as_bytes()),
+            ("OEBPS/Text/ch2.xhtml", CHAPTER_2.as_bytes()),
+        ];
+        for (name, data) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn open_reports_missing_and_invalid_files() {
+        let missing = std::env::temp_dir().join("fast_epub_missing.epub");
+        assert!(matches!(Book::open(&missing), Err(BookError::NotFound(_))));
+
+        let path = std::env::temp_dir().join(format!("fast_epub_{}_notzip.epub", std::process::id()));
+        std::fs::write(&path, b"not a zip").unwrap();
+        assert!(matches!(Book::open(&path), Err(BookError::NotZip(_))));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn metadata() {
+        let path = fixture("metadata");
+        let book = Book::open(&path).unwrap();
+        let metadata = book.metadata();
+        assert_eq!(metadata.title.as_deref(), Some("测试之书"));
+        assert_eq!(metadata.author.as_deref(), Some("作者甲"));
+        assert_eq!(metadata.language.as_deref(), Some("zh"));
+        assert_eq!(book.spine_len(), 2);
+        assert_eq!(book.cover().map(|(_, mime)| mime).as_deref(), Some("image/png"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn toc() {
+        let path = fixture("toc");
+        let book = Book::open(&path).unwrap();
+        let toc = book.toc();
+        let labels: Vec<&str> = toc.iter().map(|nav| nav.label.as_str()).collect();
+        assert_eq!(labels, ["第一章", "第二章"]);
+        assert_eq!(toc[1].children[0].label, "第二节");
+        assert_eq!(book.get_spine_index(toc[1].children[0].play_order), Some(1));
+        assert_eq!(book.anchor(toc[1].children[0].play_order).as_deref(), Some("s2"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn chapter() {
+        let path = fixture("chapter");
+        let mut book = Book::open(&path).unwrap();
+        let chapter = book.chapter(book.toc()[0].play_order);
+        assert!(chapter.content.contains("第一章正文"));
+        // 书内链接解析为完整路径，图片换成资源地址
+        assert!(chapter.content.contains(r#"data-epub-href="OEBPS/Text/ch2.xhtml#s2""#));
+        assert!(chapter.content.contains(&resource_url("OEBPS/Images/cover.png")));
+        // body 的 class 保留下来，书籍样式中的 body 选择器仍然匹配
+        assert!(chapter.content.contains(r#"class="calibre""#));
+        assert!(chapter.styles.contains(".epub-content [data-epub-body].calibre"));
+        assert!(chapter.styles.contains(".epub-content p { text-indent: 2em }"));
+
+        let second = book.chapter_by_spine(1).unwrap();
+        assert!(second.content.contains("第二节正文"));
+        assert!(book.chapter_by_spine(2).is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn resource() {
+        let path = fixture("resource");
+        let book = Book::open(&path).unwrap();
+        let (data, mime) = book.resource("OEBPS/Images/cover.png").unwrap();
+        assert_eq!(data, PNG);
+        assert_eq!(mime, "image/png");
+        assert!(book.resource("OEBPS/Images/missing.png").is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn locate() {
+        let path = fixture("locate");
+        let mut book = Book::open(&path).unwrap();
+        assert_eq!(book.locate("OEBPS/Text/ch2.xhtml#s2"), Some((1, Some("s2".to_string()))));
+        assert_eq!(book.locate("OEBPS/Text/ch2.xhtml"), Some((1, None)));
+        assert_eq!(book.locate("OEBPS/Text/missing.xhtml"), None);
+
+        // 清单中的路径带 %20 转义，链接解析后是解码的路径
+        let chapter = book.chapter_by_spine(1).unwrap();
+        assert!(chapter.content.contains(r#"data-epub-href="OEBPS/Text/chapter 1.xhtml#top""#));
+        assert_eq!(book.locate("OEBPS/Text/chapter 1.xhtml#top"), Some((0, Some("top".to_string()))));
+        let _ = std::fs::remove_file(path);
+    }
+}
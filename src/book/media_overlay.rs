@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use crate::book::nav::full_href;
+use crate::book::resolve_path;
+
+/// 朗读片段当前高亮所用的类名，书籍没有用 `media:active-class` 指定时使用
+pub const DEFAULT_ACTIVE_CLASS: &str = "-epub-media-overlay-active";
+
+/// 媒体叠加中的一段：正文片段及对应的朗读音频区间
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlayClip {
+    pub text: String,      // 正文片段的完整路径加锚点
+    pub audio: String,     // 音频文件的完整路径
+    pub begin: f64,        // 秒
+    pub end: Option<f64>,  // 未声明时播放到音频结束
+}
+
+impl OverlayClip {
+    /// 正文片段的锚点
+    pub fn fragment(&self) -> Option<&str> {
+        self.text.split_once('#').map(|(_, fragment)| fragment).filter(|f| !f.is_empty())
+    }
+}
+
+/// 一个书脊项的朗读内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaOverlay {
+    pub clips: Vec<OverlayClip>,
+    pub active_class: String,
+}
+
+// OPF 中登记的媒体叠加：清单项 id -> SMIL 文件的完整路径
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct OverlayIndex {
+    pub items: HashMap<String, String>,
+    pub active_class: Option<String>,
+}
+
+/// 读取清单项的 `media-overlay` 属性和 `media:active-class` 元数据
+pub(crate) fn parse_overlay_index(opf: &str, opf_path: &str) -> OverlayIndex {
+    let mut reader = Reader::from_str(opf);
+    let mut hrefs: HashMap<String, String> = HashMap::new();
+    let mut overlays: Vec<(String, String)> = Vec::new();  // (清单项 id, SMIL 清单项 id)
+    let mut active_class = None;
+    let mut in_active_class = false;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let attr = |name: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == name)
+                        .and_then(|a| a.unescape_value().ok())
+                        .map(|v| v.into_owned())
+                };
+                match e.local_name().as_ref() {
+                    b"item" => {
+                        let Some(id) = attr(b"id") else { continue };
+                        if let Some(overlay) = attr(b"media-overlay") {
+                            overlays.push((id.clone(), overlay));
+                        }
+                        if let Some(path) = attr(b"href").and_then(|href| resolve_path(opf_path, &href)) {
+                            hrefs.insert(id, path);
+                        }
+                    }
+                    b"meta" => {
+                        in_active_class = attr(b"property").as_deref() == Some("media:active-class");
+                    }
+                    _ => in_active_class = false,
+                }
+            }
+            Ok(Event::Text(t)) if in_active_class => {
+                if let Ok(text) = t.unescape() {
+                    let class = text.trim();
+                    if !class.is_empty() {
+                        active_class = Some(class.to_string());
+                        in_active_class = false;
+                    }
+                }
+            }
+            Ok(Event::End(_)) => in_active_class = false,
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    let items = overlays.into_iter()
+        .filter_map(|(id, overlay)| Some((id, hrefs.get(&overlay)?.clone())))
+        .collect();
+    OverlayIndex { items, active_class }
+}
+
+/// 解析 SMIL 文件中的 `<par>`，按文档顺序返回每个正文片段和对应的音频区间
+pub fn parse_smil(smil: &str, smil_path: &str) -> Vec<OverlayClip> {
+    let mut reader = Reader::from_str(smil);
+    let mut clips = Vec::new();
+    let mut in_par = false;
+    let mut text = None;
+    let mut audio = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let attr = |name: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == name)
+                        .and_then(|a| a.unescape_value().ok())
+                        .map(|v| v.into_owned())
+                };
+                match e.local_name().as_ref() {
+                    b"par" => {
+                        in_par = true;
+                        text = None;
+                        audio = None;
+                    }
+                    b"text" if in_par => {
+                        text = attr(b"src").and_then(|src| full_href(smil_path, &src));
+                    }
+                    b"audio" if in_par => {
+                        let path = attr(b"src").and_then(|src| resolve_path(smil_path, &src));
+                        audio = path.map(|path| {
+                            let begin = attr(b"clipBegin").and_then(|v| parse_clock(&v)).unwrap_or(0.0);
+                            let end = attr(b"clipEnd").and_then(|v| parse_clock(&v));
+                            (path, begin, end)
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::End(e)) if e.local_name().as_ref() == b"par" => {
+                in_par = false;
+                // 只有文字没有音频的片段无法朗读，跳过
+                if let (Some(text), Some((audio, begin, end))) = (text.take(), audio.take()) {
+                    clips.push(OverlayClip { text, audio, begin, end });
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    clips
+}
+
+// SMIL 时钟值：`0:01:02.5`、`01:02.5`、`12.5s`、`500ms`、`1.5min`、`1h`，或不带单位的秒数
+fn parse_clock(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.contains(':') {
+        let parts: Vec<f64> = value.split(':')
+            .map(|part| part.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        return match parts.as_slice() {
+            [h, m, s] => Some(h * 3600.0 + m * 60.0 + s),
+            [m, s] => Some(m * 60.0 + s),
+            _ => None,
+        };
+    }
+    let (number, factor) = if let Some(n) = value.strip_suffix("ms") {
+        (n, 0.001)
+    } else if let Some(n) = value.strip_suffix("min") {
+        (n, 60.0)
+    } else if let Some(n) = value.strip_suffix('h') {
+        (n, 3600.0)
+    } else if let Some(n) = value.strip_suffix('s') {
+        (n, 1.0)
+    } else {
+        (value, 1.0)
+    };
+    number.trim().parse::<f64>().ok().map(|n| n * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(value: Option<f64>, expected: f64) -> bool {
+        value.is_some_and(|v| (v - expected).abs() < 1e-9)
+    }
+
+    #[test]
+    fn clock_values() {
+        assert!(approx(parse_clock("1:02:03.250"), 3723.25));
+        assert!(approx(parse_clock("02:03.5"), 123.5));
+        assert!(approx(parse_clock("12.5s"), 12.5));
+        assert!(approx(parse_clock("500ms"), 0.5));
+        assert!(approx(parse_clock("1.5min"), 90.0));
+        assert!(approx(parse_clock("1h"), 3600.0));
+        assert!(approx(parse_clock(" 7 "), 7.0));
+        assert_eq!(parse_clock("1:2:3:4"), None);
+        assert_eq!(parse_clock("abc"), None);
+    }
+
+    #[test]
+    fn nested_sequences() {
+        let smil = r#"<smil xmlns="http://www.w3.org/ns/SMIL" xmlns:epub="http://www.idpf.org/2007/ops" version="3.0">
+<body>
+  <seq epub:textref="../Text/ch1.xhtml" epub:type="chapter">
+    <par id="p1"><text src="../Text/ch1.xhtml#s1"/><audio src="../Audio/ch1.mp3" clipBegin="0:00:00.000" clipEnd="0:00:02.500"/></par>
+    <seq epub:type="figure">
+      <par id="p2"><text src="../Text/ch1.xhtml#fig1"/><audio src="../Audio/ch1.mp3" clipBegin="2.5s" clipEnd="4500ms"/></par>
+    </seq>
+    <par id="p3"><text src="../Text/ch1.xhtml#s3"/></par>
+    <par id="p4"><text src="../Text/ch1.xhtml#s4"/><audio src="../Audio/ch1.mp3" clipBegin="4.5s"/></par>
+  </seq>
+</body></smil>"#;
+        let clips = parse_smil(smil, "OEBPS/Smil/ch1.smil");
+        assert_eq!(clips, [
+            OverlayClip { text: "OEBPS/Text/ch1.xhtml#s1".into(), audio: "OEBPS/Audio/ch1.mp3".into(), begin: 0.0, end: Some(2.5) },
+            OverlayClip { text: "OEBPS/Text/ch1.xhtml#fig1".into(), audio: "OEBPS/Audio/ch1.mp3".into(), begin: 2.5, end: Some(4.5) },
+            // 没有音频的片段跳过
+            OverlayClip { text: "OEBPS/Text/ch1.xhtml#s4".into(), audio: "OEBPS/Audio/ch1.mp3".into(), begin: 4.5, end: None },
+        ]);
+        assert_eq!(clips[1].fragment(), Some("fig1"));
+    }
+
+    #[test]
+    fn overlay_index() {
+        let opf = r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata><meta property="media:active-class">highlight</meta></metadata>
+  <manifest>
+    <item id="ch1" href="Text/ch1.xhtml" media-type="application/xhtml+xml" media-overlay="ch1_smil"/>
+    <item id="ch1_smil" href="Smil/ch1.smil" media-type="application/smil+xml"/>
+    <item id="ch2" href="Text/ch2.xhtml" media-type="application/xhtml+xml" media-overlay="missing"/>
+  </manifest>
+</package>"#;
+        let index = parse_overlay_index(opf, "OEBPS/content.opf");
+        assert_eq!(index.active_class.as_deref(), Some("highlight"));
+        assert_eq!(index.items.len(), 1);
+        assert_eq!(index.items["ch1"], "OEBPS/Smil/ch1.smil");
+    }
+}
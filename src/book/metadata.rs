@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+/// 作者、译者等参与者
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contributor {
+    pub name: String,
+    pub role: Option<String>,     // MARC 角色代码，如 aut、trl、edt
+    pub file_as: Option<String>,  // 排序用的名字，如 “Doe, Jane”
+}
+
+/// 书的标识符，`scheme` 为 ISBN、UUID、DOI 等，未注明时为空
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identifier {
+    pub value: String,
+    pub scheme: Option<String>,
+}
+
+/// 所属丛书及在丛书中的序号
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub index: Option<f64>,
+}
+
+// OPF `<metadata>` 中的结构化信息，由 BookMetadata 对外提供
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct PackageMetadata {
+    pub creators: Vec<Contributor>,
+    pub contributors: Vec<Contributor>,
+    pub language: Option<String>,
+    pub publisher: Option<String>,
+    pub date: Option<String>,
+    pub subjects: Vec<String>,
+    pub identifiers: Vec<Identifier>,
+    pub series: Option<Series>,
+}
+
+// `<metadata>` 中的一个元素
+struct Entry {
+    name: String,
+    id: Option<String>,
+    attrs: HashMap<String, String>,  // 按本地名存放，opf:role 存为 role
+    text: String,
+}
+
+/// 解析 OPF 的 `<metadata>`，同时兼容 EPUB2 的 `opf:` 属性和 EPUB3 的 `refines`
+pub(crate) fn parse_package_metadata(opf: &str) -> PackageMetadata {
+    let entries = metadata_entries(opf);
+
+    // EPUB3 用 <meta refines="#id" property="…"> 补充其他元素的属性
+    let mut refines: HashMap<&str, Vec<&Entry>> = HashMap::new();
+    for entry in entries.iter().filter(|e| e.name == "meta") {
+        if let Some(target) = entry.attrs.get("refines") {
+            refines.entry(target.trim_start_matches('#')).or_default().push(entry);
+        }
+    }
+    let refined = |entry: &Entry, property: &str| -> Option<&Entry> {
+        let id = entry.id.as_deref()?;
+        refines.get(id)?.iter()
+            .find(|meta| meta.attrs.get("property").map(String::as_str) == Some(property))
+            .copied()
+    };
+    let non_empty = |text: &str| Some(text.trim().to_string()).filter(|t| !t.is_empty());
+
+    let mut metadata = PackageMetadata::default();
+    let mut calibre_series = None;
+    let mut calibre_index = None;
+
+    for entry in &entries {
+        match entry.name.as_str() {
+            "creator" | "contributor" => {
+                let Some(name) = non_empty(&entry.text) else { continue };
+                let contributor = Contributor {
+                    name,
+                    role: entry.attrs.get("role").cloned()
+                        .or_else(|| refined(entry, "role").and_then(|m| non_empty(&m.text))),
+                    file_as: entry.attrs.get("file-as").cloned()
+                        .or_else(|| refined(entry, "file-as").and_then(|m| non_empty(&m.text))),
+                };
+                if entry.name == "creator" {
+                    metadata.creators.push(contributor);
+                } else {
+                    metadata.contributors.push(contributor);
+                }
+            }
+            "language" if metadata.language.is_none() => metadata.language = non_empty(&entry.text),
+            "publisher" if metadata.publisher.is_none() => metadata.publisher = non_empty(&entry.text),
+            "date" => {
+                // EPUB2 可能有多个带 opf:event 的日期，优先取出版日期
+                let is_publication = entry.attrs.get("event").map(String::as_str) == Some("publication");
+                if metadata.date.is_none() || is_publication {
+                    metadata.date = non_empty(&entry.text).or(metadata.date.take());
+                }
+            }
+            "subject" => metadata.subjects.extend(non_empty(&entry.text)),
+            "identifier" => {
+                let Some(value) = non_empty(&entry.text) else { continue };
+                let scheme = entry.attrs.get("scheme").cloned()
+                    .or_else(|| refined(entry, "identifier-type").and_then(identifier_type))
+                    .or_else(|| urn_scheme(&value));
+                metadata.identifiers.push(Identifier { value, scheme });
+            }
+            "meta" => match entry.attrs.get("name").map(String::as_str) {
+                Some("calibre:series") => calibre_series = entry.attrs.get("content").and_then(|c| non_empty(c)),
+                Some("calibre:series_index") => {
+                    calibre_index = entry.attrs.get("content").and_then(|c| c.trim().parse().ok());
+                }
+                _ if entry.attrs.get("property").map(String::as_str) == Some("belongs-to-collection") => {
+                    // 未注明类型的集合也按丛书处理，明确标为其他类型的跳过
+                    let is_series = refined(entry, "collection-type")
+                        .is_none_or(|m| m.text.trim() == "series");
+                    if let (true, None, Some(name)) = (is_series, &metadata.series, non_empty(&entry.text)) {
+                        metadata.series = Some(Series {
+                            name,
+                            index: refined(entry, "group-position").and_then(|m| m.text.trim().parse().ok()),
+                        });
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    if metadata.series.is_none() {
+        metadata.series = calibre_series.map(|name| Series { name, index: calibre_index });
+    }
+    metadata
+}
+
+// 读取 <metadata> 下的元素，其他部分跳过
+fn metadata_entries(opf: &str) -> Vec<Entry> {
+    let mut reader = Reader::from_str(opf);
+    let mut entries = Vec::new();
+    let mut in_metadata = false;
+    let mut current: Option<Entry> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"metadata" => in_metadata = true,
+                _ if in_metadata => current = Some(entry(&e)),
+                _ => {}
+            },
+            Ok(Event::Empty(e)) if in_metadata => entries.push(entry(&e)),
+            Ok(Event::Text(t)) => {
+                if let (Some(entry), Ok(text)) = (current.as_mut(), t.unescape()) {
+                    entry.text.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"metadata" => break,
+                _ => entries.extend(current.take()),
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+fn entry(element: &BytesStart) -> Entry {
+    let attrs: HashMap<String, String> = element.attributes()
+        .flatten()
+        .filter_map(|a| {
+            let key = String::from_utf8(a.key.local_name().as_ref().to_vec()).ok()?;
+            Some((key, a.unescape_value().ok()?.into_owned()))
+        })
+        .collect();
+    Entry {
+        name: String::from_utf8_lossy(element.local_name().as_ref()).into_owned(),
+        id: attrs.get("id").cloned(),
+        attrs,
+        text: String::new(),
+    }
+}
+
+// ONIX 代码表 5 中的标识符类型
+fn identifier_type(meta: &Entry) -> Option<String> {
+    let scheme = match meta.text.trim() {
+        "02" | "15" => "ISBN",
+        "06" => "DOI",
+        "22" => "URN",
+        other if !other.is_empty() && !other.bytes().all(|b| b.is_ascii_digit()) => other,
+        _ => return None,
+    };
+    Some(scheme.to_string())
+}
+
+// `urn:isbn:…`、`urn:uuid:…` 形式的标识符自带类型
+fn urn_scheme(value: &str) -> Option<String> {
+    let rest = value.strip_prefix("urn:")?;
+    let (scheme, _) = rest.split_once(':')?;
+    Some(scheme.to_ascii_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::book::BookMetadata;
+    use super::*;
+
+    fn contributor(name: &str, role: Option<&str>, file_as: Option<&str>) -> Contributor {
+        Contributor { name: name.into(), role: role.map(Into::into), file_as: file_as.map(Into::into) }
+    }
+
+    fn isbn(identifiers: Vec<Identifier>) -> Option<String> {
+        let metadata = BookMetadata { identifiers, ..BookMetadata::empty() };
+        metadata.isbn().map(str::to_string)
+    }
+
+    #[test]
+    fn epub2_package() {
+        let opf = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:title>Title</dc:title>
+    <dc:creator opf:role="aut" opf:file-as="Doe, Jane">Jane Doe</dc:creator>
+    <dc:contributor opf:role="trl">Li Lei</dc:contributor>
+    <dc:creator> </dc:creator>
+    <dc:language>zh</dc:language>
+    <dc:publisher>Press</dc:publisher>
+    <dc:date opf:event="modification">2020-05-01</dc:date>
+    <dc:date opf:event="publication">2019</dc:date>
+    <dc:subject>Fiction</dc:subject>
+    <dc:subject>History</dc:subject>
+    <dc:identifier id="id" opf:scheme="ISBN">9787000000000</dc:identifier>
+    <dc:identifier>urn:uuid:1234</dc:identifier>
+    <meta name="calibre:series" content="Saga"/>
+    <meta name="calibre:series_index" content="2.5"/>
+  </metadata>
+  <manifest><item id="x" href="x.xhtml" media-type="application/xhtml+xml"/></manifest>
+</package>"#;
+        let metadata = parse_package_metadata(opf);
+        assert_eq!(metadata.creators, [contributor("Jane Doe", Some("aut"), Some("Doe, Jane"))]);
+        assert_eq!(metadata.contributors, [contributor("Li Lei", Some("trl"), None)]);
+        assert_eq!(metadata.language.as_deref(), Some("zh"));
+        assert_eq!(metadata.publisher.as_deref(), Some("Press"));
+        // 多个日期时取出版日期
+        assert_eq!(metadata.date.as_deref(), Some("2019"));
+        assert_eq!(metadata.subjects, ["Fiction", "History"]);
+        assert_eq!(metadata.identifiers, [
+            Identifier { value: "9787000000000".into(), scheme: Some("ISBN".into()) },
+            Identifier { value: "urn:uuid:1234".into(), scheme: Some("UUID".into()) },
+        ]);
+        assert_eq!(metadata.series, Some(Series { name: "Saga".into(), index: Some(2.5) }));
+    }
+
+    #[test]
+    fn epub3_refines() {
+        let opf = r##"<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:abcd</dc:identifier>
+    <dc:identifier id="isbn">9780000000002</dc:identifier>
+    <meta refines="#isbn" property="identifier-type" scheme="onix:codelist5">15</meta>
+    <dc:creator id="c1">Jane Doe</dc:creator>
+    <meta refines="#c1" property="role" scheme="marc:relators">aut</meta>
+    <meta refines="#c1" property="file-as">Doe, Jane</meta>
+    <meta refines="#c1" property="display-seq">1</meta>
+    <dc:creator id="c2">Illustrator</dc:creator>
+    <meta refines="#c2" property="role" scheme="marc:relators">ill</meta>
+    <dc:date>2021-01-01</dc:date>
+    <meta property="belongs-to-collection" id="set">Anthology</meta>
+    <meta refines="#set" property="collection-type">set</meta>
+    <meta property="belongs-to-collection" id="series">Saga</meta>
+    <meta refines="#series" property="collection-type">series</meta>
+    <meta refines="#series" property="group-position">3</meta>
+    <meta name="calibre:series" content="Other"/>
+  </metadata>
+</package>"##;
+        let metadata = parse_package_metadata(opf);
+        assert_eq!(metadata.creators, [
+            contributor("Jane Doe", Some("aut"), Some("Doe, Jane")),
+            contributor("Illustrator", Some("ill"), None),
+        ]);
+        assert_eq!(metadata.date.as_deref(), Some("2021-01-01"));
+        // 标为其他类型的集合跳过，belongs-to-collection 优先于 calibre:series
+        assert_eq!(metadata.series, Some(Series { name: "Saga".into(), index: Some(3.0) }));
+        assert_eq!(metadata.identifiers[0].scheme.as_deref(), Some("UUID"));
+        assert_eq!(metadata.identifiers[1].scheme.as_deref(), Some("ISBN"));
+        assert_eq!(isbn(metadata.identifiers).as_deref(), Some("9780000000002"));
+    }
+
+    #[test]
+    fn untyped_collection_is_a_series() {
+        let opf = r#"<package><metadata>
+  <meta property="belongs-to-collection">Saga</meta>
+  <meta name="calibre:series" content="Other"/>
+  <meta name="calibre:series_index" content="9"/>
+</metadata></package>"#;
+        assert_eq!(parse_package_metadata(opf).series, Some(Series { name: "Saga".into(), index: None }));
+    }
+
+    #[test]
+    fn isbn_detection() {
+        let identifiers = |opf: &str| parse_package_metadata(&format!("<package><metadata>{}</metadata></package>", opf)).identifiers;
+        let urn = identifiers("<identifier>urn:isbn:9780000000002</identifier>");
+        assert_eq!(urn[0].scheme.as_deref(), Some("ISBN"));
+        assert_eq!(isbn(urn).as_deref(), Some("9780000000002"));
+        let lowercase = identifiers(r#"<identifier scheme="isbn">9780000000002</identifier>"#);
+        assert_eq!(isbn(lowercase).as_deref(), Some("9780000000002"));
+        let doi = identifiers(r##"<identifier id="d">10.1000/1</identifier><meta refines="#d" property="identifier-type">06</meta>"##);
+        assert_eq!(doi[0].scheme.as_deref(), Some("DOI"));
+        assert_eq!(isbn(doi), None);
+        // 未注明类型的标识符不当作 ISBN
+        assert_eq!(isbn(identifiers("<identifier>9780000000002</identifier>")), None);
+    }
+}
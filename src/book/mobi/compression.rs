@@ -0,0 +1,245 @@
+// MOBI 正文记录的两种压缩方式：PalmDOC（LZ77 变体）和 HUFF/CDIC（哈夫曼编码加短语字典）
+
+/// 解压 PalmDOC 压缩的一条记录
+pub(super) fn palmdoc_decompress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        let c = data[i];
+        i += 1;
+        match c {
+            // 之后的 1～8 个字节原样输出
+            0x01..=0x08 => {
+                let end = (i + c as usize).min(data.len());
+                out.extend_from_slice(&data[i..end]);
+                i = end;
+            }
+            // 两个字节表示回溯距离（11 位）和长度（3 位，加 3）
+            0x80..=0xBF => {
+                let Some(&next) = data.get(i) else { break };
+                i += 1;
+                let pair = (((c as usize) << 8) | next as usize) & 0x3FFF;
+                let distance = pair >> 3;
+                let length = (pair & 0x07) + 3;
+                if distance == 0 || distance > out.len() {
+                    continue;
+                }
+                // 回溯的区间可以和正在写入的部分重叠，只能逐字节复制
+                let start = out.len() - distance;
+                for k in 0..length {
+                    out.push(out[start + k]);
+                }
+            }
+            // 空格加一个字符
+            0xC0..=0xFF => {
+                out.push(b' ');
+                out.push(c ^ 0x80);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// HUFF/CDIC 解码器，由一条 HUFF 记录和之后的若干 CDIC 记录构造
+pub(super) struct HuffReader {
+    dict1: Vec<(u32, bool, u64)>,  // 按编码的最高 8 位查表：(码长, 是否确定码长, 最大编码)
+    mincode: Vec<u64>,
+    maxcode: Vec<u64>,
+    dictionary: Vec<(Vec<u8>, bool)>,  // 短语及是否已经展开
+}
+
+impl HuffReader {
+    pub(super) fn new(huff: &[u8], cdics: &[&[u8]]) -> Option<Self> {
+        if huff.get(0..4)? != b"HUFF" {
+            return None;
+        }
+        let off1 = read_u32(huff, 8)? as usize;
+        let off2 = read_u32(huff, 12)? as usize;
+
+        let mut dict1 = Vec::with_capacity(256);
+        for i in 0..256 {
+            let v = read_u32(huff, off1 + i * 4)?;
+            let codelen = v & 0x1F;
+            let term = v & 0x80 != 0;
+            let maxcode = (((v >> 8) as u64 + 1) << (32 - codelen)).wrapping_sub(1);
+            dict1.push((codelen, term, maxcode));
+        }
+
+        // 码长为 1～32 时的最小、最大编码，下标即码长
+        let mut mincode = vec![0u64];
+        let mut maxcode = vec![0u64];
+        for codelen in 1..=32u32 {
+            let min = read_u32(huff, off2 + (codelen as usize - 1) * 8)? as u64;
+            let max = read_u32(huff, off2 + (codelen as usize - 1) * 8 + 4)? as u64;
+            mincode.push(min << (32 - codelen));
+            maxcode.push(((max + 1) << (32 - codelen)).wrapping_sub(1));
+        }
+
+        let mut dictionary = Vec::new();
+        for cdic in cdics {
+            if cdic.get(0..4)? != b"CDIC" {
+                return None;
+            }
+            let phrases = read_u32(cdic, 8)? as usize;
+            let bits = read_u32(cdic, 12)?;
+            let count = (1usize << bits.min(31)).min(phrases.saturating_sub(dictionary.len()));
+            for k in 0..count {
+                let offset = read_u16(cdic, 16 + k * 2)? as usize;
+                let blen = read_u16(cdic, 16 + offset)? as usize;
+                let start = 18 + offset;
+                let slice = cdic.get(start..start + (blen & 0x7FFF))?.to_vec();
+                dictionary.push((slice, blen & 0x8000 != 0));
+            }
+        }
+
+        Some(Self { dict1, mincode, maxcode, dictionary })
+    }
+
+    /// 解压一条记录
+    pub(super) fn decompress(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.unpack(data, &mut out, 0);
+        out
+    }
+
+    fn unpack(&mut self, data: &[u8], out: &mut Vec<u8>, depth: usize) {
+        // 短语本身也可能是压缩的，限制嵌套层数防止损坏的文件无限递归
+        if depth > 32 {
+            return;
+        }
+        let mut padded = data.to_vec();
+        padded.extend_from_slice(&[0; 8]);
+        let mut bits_left = data.len() as i64 * 8;
+        let mut pos = 0;
+        let mut x = read_u64(&padded, pos);
+        let mut n: i64 = 32;
+
+        loop {
+            if n <= 0 {
+                pos += 4;
+                x = read_u64(&padded, pos);
+                n += 32;
+            }
+            let code = (x >> n) & 0xFFFF_FFFF;
+            let (mut codelen, term, mut maxcode) = self.dict1[(code >> 24) as usize];
+            if !term {
+                while (codelen as usize) < 32 && code < self.mincode[codelen as usize] {
+                    codelen += 1;
+                }
+                maxcode = self.maxcode[codelen as usize];
+            }
+            if codelen == 0 {
+                return;
+            }
+            n -= codelen as i64;
+            bits_left -= codelen as i64;
+            if bits_left < 0 {
+                break;
+            }
+            let index = (maxcode.wrapping_sub(code) >> (32 - codelen)) as usize;
+            let Some((phrase, expanded)) = self.dictionary.get(index).cloned() else { return };
+            if expanded {
+                out.extend_from_slice(&phrase);
+            } else {
+                let mut unpacked = Vec::new();
+                self.unpack(&phrase, &mut unpacked, depth + 1);
+                out.extend_from_slice(&unpacked);
+                self.dictionary[index] = (unpacked, true);
+            }
+        }
+    }
+}
+
+pub(super) fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+pub(super) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    data.get(offset..offset + 8)
+        .map(|b| u64::from_be_bytes(b.try_into().unwrap_or([0; 8])))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palmdoc_literals() {
+        assert_eq!(palmdoc_decompress(b"plain text"), b"plain text");
+        assert_eq!(palmdoc_decompress(&[0x03, 0xC0, 0x80, 0x01, b'!']), [0xC0, 0x80, 0x01, b'!']);
+        // 越过结尾的原样字节数按实际长度截断
+        assert_eq!(palmdoc_decompress(&[0x08, b'a']), b"a");
+    }
+
+    #[test]
+    fn palmdoc_space_pairs() {
+        assert_eq!(palmdoc_decompress(&[b'a', 0xE8, 0xE9]), b"a h i");
+    }
+
+    // 所有编码都是 8 位：首字节为 c 的编码对应第 255 - c 个短语
+    fn huff_record() -> Vec<u8> {
+        let mut huff = b"HUFF".to_vec();
+        huff.extend_from_slice(&24u32.to_be_bytes());
+        huff.extend_from_slice(&24u32.to_be_bytes());
+        huff.extend_from_slice(&(24u32 + 1024).to_be_bytes());
+        huff.extend_from_slice(&[0; 8]);
+        for _ in 0..256 {
+            huff.extend_from_slice(&((255u32 << 8) | 0x80 | 8).to_be_bytes());
+        }
+        huff.extend_from_slice(&[0; 32 * 8]);
+        huff
+    }
+
+    // 第 0 个短语是展开的 "Hello "，第 1 个短语本身压缩为两个第 0 个短语
+    fn cdic_record() -> Vec<u8> {
+        let mut cdic = b"CDIC".to_vec();
+        cdic.extend_from_slice(&16u32.to_be_bytes());
+        cdic.extend_from_slice(&2u32.to_be_bytes());
+        cdic.extend_from_slice(&1u32.to_be_bytes());
+        cdic.extend_from_slice(&4u16.to_be_bytes());
+        cdic.extend_from_slice(&12u16.to_be_bytes());
+        cdic.extend_from_slice(&0x8006u16.to_be_bytes());
+        cdic.extend_from_slice(b"Hello ");
+        cdic.extend_from_slice(&2u16.to_be_bytes());
+        cdic.extend_from_slice(&[0xFF, 0xFF]);
+        cdic
+    }
+
+    #[test]
+    fn huff_cdic() {
+        let huff = huff_record();
+        let cdic = cdic_record();
+        let mut reader = HuffReader::new(&huff, &[&cdic]).unwrap();
+        assert_eq!(reader.decompress(&[0xFF]), b"Hello ");
+        assert_eq!(reader.decompress(&[0xFE, 0xFF]), b"Hello Hello Hello ");
+        // 编码指向不存在的短语时停止
+        assert_eq!(reader.decompress(&[0xFF, 0x00, 0xFF]), b"Hello ");
+        assert_eq!(reader.decompress(&[]), b"");
+    }
+
+    #[test]
+    fn huff_truncated() {
+        let huff = huff_record();
+        let cdic = cdic_record();
+        assert!(HuffReader::new(&huff[..100], &[&cdic]).is_none());
+        assert!(HuffReader::new(&huff[..huff.len() - 1], &[&cdic]).is_none());
+        assert!(HuffReader::new(&huff, &[&cdic[..cdic.len() - 3]]).is_none());
+        assert!(HuffReader::new(&huff, &[b"CDI"]).is_none());
+        assert!(HuffReader::new(b"HUFX", &[]).is_none());
+    }
+
+    #[test]
+    fn palmdoc_back_references() {
+        // 距离 3、长度 3+3，与正在写入的部分重叠
+        assert_eq!(palmdoc_decompress(&[b'a', b'b', b'c', 0x80, 0x1B]), b"abcabcabc");
+        // 距离超出已输出的内容时跳过
+        assert_eq!(palmdoc_decompress(&[b'a', 0x80, 0x50, b'b']), b"ab");
+        assert_eq!(palmdoc_decompress(&[b'a', 0x80]), b"a");
+    }
+}
@@ -0,0 +1,270 @@
+// MOBI 的 INDX 索引表。目录（NCX）以及 KF8 的骨架表、片段表都以这种格式存放：
+// 一条 INDX 头记录（含 TAGX 标签定义），之后是若干条数据记录和 CNCX 字符串记录。
+use std::collections::HashMap;
+use encoding_rs::{UTF_8, WINDOWS_1252};
+use super::compression::read_u32;
+
+/// 索引表中的一项：名称和各标签的取值
+#[derive(Debug, Clone)]
+pub(super) struct IndexEntry {
+    pub name: String,
+    pub tags: HashMap<u8, Vec<u32>>,
+}
+
+impl IndexEntry {
+    pub fn tag(&self, tag: u8, i: usize) -> Option<u32> {
+        self.tags.get(&tag).and_then(|values| values.get(i)).copied()
+    }
+}
+
+/// 读取索引表，`records` 为全部记录，`index` 为 INDX 头记录的序号。
+/// 同时返回按偏移量查找的 CNCX 字符串。
+pub(super) fn read_index(records: &[&[u8]], index: usize) -> Option<(Vec<IndexEntry>, Cncx)> {
+    let header = records.get(index)?;
+    let (header_len, _, record_count, encoding) = indx_header(header)?;
+    let cncx_count = read_u32(header, 52)? as usize;
+
+    // TAGX：控制字节数以及每个标签的 (标签, 每项的值个数, 位掩码, 结束标记)
+    let tagx = header.get(header_len..)?;
+    if tagx.get(0..4)? != b"TAGX" {
+        return None;
+    }
+    let tagx_len = read_u32(tagx, 4)? as usize;
+    let control_bytes = read_u32(tagx, 8)? as usize;
+    let tag_defs: Vec<[u8; 4]> = tagx.get(12..tagx_len)?
+        .chunks_exact(4)
+        .map(|c| [c[0], c[1], c[2], c[3]])
+        .collect();
+
+    let cncx_records: Vec<&[u8]> = (0..cncx_count)
+        .filter_map(|i| records.get(index + record_count + 1 + i).copied())
+        .collect();
+    let cncx = Cncx { records: cncx_records.iter().map(|r| r.to_vec()).collect(), utf8: encoding == 65001 };
+
+    let mut entries = Vec::new();
+    for record in (index + 1..=index + record_count).filter_map(|i| records.get(i)) {
+        let (_, idxt, count, _) = indx_header(record)?;
+        let mut positions: Vec<usize> = (0..count)
+            .filter_map(|j| super::compression::read_u16(record, idxt + 4 + j * 2).map(usize::from))
+            .collect();
+        // 最后一项到 IDXT 之前结束
+        positions.push(idxt);
+        for pair in positions.windows(2) {
+            let Some(entry) = record.get(pair[0]..pair[1]) else { continue };
+            let Some(&name_len) = entry.first() else { continue };
+            let Some(name) = entry.get(1..1 + name_len as usize) else { continue };
+            let tags = tag_map(control_bytes, &tag_defs, &entry[1 + name_len as usize..]);
+            entries.push(IndexEntry { name: cncx.decode(name), tags });
+        }
+    }
+    Some((entries, cncx))
+}
+
+// INDX 记录头：(头长度, IDXT 偏移, 记录数或项数, 文本编码)
+fn indx_header(record: &[u8]) -> Option<(usize, usize, usize, u32)> {
+    if record.get(0..4)? != b"INDX" {
+        return None;
+    }
+    Some((
+        read_u32(record, 4)? as usize,
+        read_u32(record, 20)? as usize,
+        read_u32(record, 24)? as usize,
+        read_u32(record, 28)?,
+    ))
+}
+
+// 按 TAGX 定义解析一项的标签值。控制字节中各标签的位掩码给出值的个数；
+// 掩码的位全为 1 且多于一位时，值的个数改由之后的一个变长整数给出字节数
+fn tag_map(control_count: usize, tag_defs: &[[u8; 4]], data: &[u8]) -> HashMap<u8, Vec<u32>> {
+    let mut tags = HashMap::new();
+    let Some(control) = data.get(..control_count) else { return tags };
+    let mut control = control.iter();
+    let mut current = control.next().copied().unwrap_or(0);
+    let mut data = &data[control_count..];
+
+    // (标签, 值个数, 值的总字节数, 每项的值个数)
+    let mut present = Vec::new();
+    for &[tag, per_entry, mask, end] in tag_defs {
+        if end == 1 {
+            current = control.next().copied().unwrap_or(0);
+            continue;
+        }
+        let value = current & mask;
+        if value == 0 {
+            continue;
+        }
+        if value == mask && mask.count_ones() > 1 {
+            let (bytes, consumed) = decint(data);
+            data = &data[consumed..];
+            present.push((tag, None, Some(bytes as usize), per_entry));
+        } else {
+            let count = if value == mask { 1 } else { value >> mask.trailing_zeros() };
+            present.push((tag, Some(count as usize), None, per_entry));
+        }
+    }
+
+    for (tag, count, bytes, per_entry) in present {
+        let mut values = Vec::new();
+        match (count, bytes) {
+            (Some(count), _) => {
+                for _ in 0..count * per_entry as usize {
+                    let (value, consumed) = decint(data);
+                    data = &data[consumed..];
+                    values.push(value);
+                }
+            }
+            (None, Some(bytes)) => {
+                let mut total = 0;
+                while total < bytes && !data.is_empty() {
+                    let (value, consumed) = decint(data);
+                    data = &data[consumed..];
+                    total += consumed;
+                    values.push(value);
+                }
+            }
+            _ => {}
+        }
+        tags.insert(tag, values);
+    }
+    tags
+}
+
+/// 正向变长整数：每字节 7 位，最高位为 1 的字节是最后一个。返回值和占用的字节数
+pub(super) fn decint(data: &[u8]) -> (u32, usize) {
+    let mut value: u32 = 0;
+    for (i, &byte) in data.iter().take(5).enumerate() {
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 != 0 {
+            return (value, i + 1);
+        }
+    }
+    (value, data.len().min(5))
+}
+
+/// 索引表引用的字符串，按偏移量存放在 CNCX 记录中，每条记录 64K
+pub(super) struct Cncx {
+    records: Vec<Vec<u8>>,
+    utf8: bool,
+}
+
+impl Cncx {
+    pub fn get(&self, offset: u32) -> Option<String> {
+        let record = self.records.get((offset >> 16) as usize)?;
+        let pos = (offset & 0xFFFF) as usize;
+        let (len, consumed) = decint(record.get(pos..)?);
+        let text = record.get(pos + consumed..pos + consumed + len as usize)?;
+        Some(self.decode(text))
+    }
+
+    fn decode(&self, text: &[u8]) -> String {
+        let encoding = if self.utf8 { UTF_8 } else { WINDOWS_1252 };
+        encoding.decode_without_bom_handling(text).0.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    // INDX 头记录：头长 56，1 条数据记录，UTF-8，1 条 CNCX 记录，之后是 TAGX
+    fn header_record() -> Vec<u8> {
+        let mut header = b"INDX".to_vec();
+        header.extend(u32s(&[56, 0, 0, 0, 0, 1, 65001]));
+        header.resize(52, 0);
+        header.extend(u32s(&[1]));
+        header.extend_from_slice(b"TAGX");
+        header.extend(u32s(&[12 + 5 * 4, 1]));
+        for def in [[1, 1, 0x01, 0], [3, 1, 0x02, 0], [4, 1, 0x04, 0], [6, 2, 0x08, 0], [0, 0, 0, 1]] {
+            header.extend_from_slice(&def);
+        }
+        header
+    }
+
+    fn data_record() -> Vec<u8> {
+        let entries: [&[u8]; 2] = [
+            // 名称 "0"，标签 1、3、4、6 都有值，300 占两个字节
+            &[1, b'0', 0x0F, 0x8A, 0x86, 0x80, 0x85, 0x02, 0xAC],
+            // 名称 "1"，只有标签 1 和 4
+            &[1, b'1', 0x05, 0x94, 0x81],
+        ];
+        let mut record = b"INDX".to_vec();
+        record.extend(u32s(&[32, 0, 0, 0, 0, 2, 0]));
+        let mut positions = Vec::new();
+        for entry in entries {
+            positions.push(record.len() as u16);
+            record.extend_from_slice(entry);
+        }
+        let idxt = record.len() as u32;
+        record[20..24].copy_from_slice(&idxt.to_be_bytes());
+        record.extend_from_slice(b"IDXT");
+        for position in positions {
+            record.extend_from_slice(&position.to_be_bytes());
+        }
+        record
+    }
+
+    fn cncx_record() -> Vec<u8> {
+        let mut cncx = vec![0x85];
+        cncx.extend_from_slice(b"Intro");
+        cncx.push(0x86);
+        cncx.extend_from_slice("第一".as_bytes());
+        cncx
+    }
+
+    #[test]
+    fn reads_index_entries() {
+        let (header, data, cncx) = (header_record(), data_record(), cncx_record());
+        let (entries, strings) = read_index(&[&header, &data, &cncx], 0).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "0");
+        assert_eq!(entries[0].tag(1, 0), Some(10));
+        assert_eq!(entries[0].tag(6, 0), Some(5));
+        assert_eq!(entries[0].tag(6, 1), Some(300));
+        assert_eq!(entries[0].tag(3, 0).and_then(|offset| strings.get(offset)).as_deref(), Some("第一"));
+        assert_eq!(entries[1].tag(1, 0), Some(20));
+        assert_eq!(entries[1].tag(4, 0), Some(1));
+        assert_eq!(entries[1].tag(3, 0), None);
+        assert_eq!(strings.get(0).as_deref(), Some("Intro"));
+        assert_eq!(strings.get(100), None);
+    }
+
+    #[test]
+    fn truncated_index() {
+        let (header, data, cncx) = (header_record(), data_record(), cncx_record());
+        assert!(read_index(&[&header[..40]], 0).is_none());
+        assert!(read_index(&[&header[..60], &data], 0).is_none());
+        assert!(read_index(&[&header], 3).is_none());
+        // 数据记录被截断时，不完整的项跳过
+        for len in 0..data.len() {
+            if let Some((entries, _)) = read_index(&[&header, &data[..len], &cncx], 0) {
+                assert!(entries.len() <= 2);
+            }
+        }
+        // CNCX 记录缺失时标题取不到
+        let (entries, strings) = read_index(&[&header, &data], 0).unwrap();
+        assert_eq!(strings.get(entries[0].tag(3, 0).unwrap()), None);
+    }
+
+    #[test]
+    fn tag_value_counts() {
+        let defs = [[7, 1, 0x03, 0], [0, 0, 0, 1]];
+        // 掩码的位全为 1：先给出值的总字节数
+        assert_eq!(tag_map(1, &defs, &[0x03, 0x83, 0x81, 0x01, 0x82])[&7], [1, 130]);
+        // 否则掩码中的值就是值的个数
+        assert_eq!(tag_map(1, &defs, &[0x02, 0x81, 0x82])[&7], [1, 2]);
+        assert!(tag_map(1, &defs, &[]).is_empty());
+        assert_eq!(tag_map(1, &defs, &[0x02])[&7], [0, 0]);
+    }
+
+    #[test]
+    fn variable_width_integers() {
+        assert_eq!(decint(&[0x85]), (5, 1));
+        assert_eq!(decint(&[0x02, 0xAC]), (300, 2));
+        assert_eq!(decint(&[0x01, 0x02]), (130, 2));
+        assert_eq!(decint(&[]), (0, 0));
+    }
+}
@@ -0,0 +1,318 @@
+// KF8 正文：FDST 把解压后的文本分为若干流，第 0 个流是 XHTML，其余是 CSS、SVG 等。
+// XHTML 流由骨架表和片段表描述：每个文件先是骨架，片段依次插入骨架中的指定位置。
+// 书内链接写作 kindle:pos:fid:XXXX:off:YYYYYYYYYY，资源写作 kindle:embed:XXXX 和 kindle:flow:XXXX，
+// 编号都是 32 进制。
+use std::sync::LazyLock;
+use regex::bytes::{Captures, Regex};
+use crate::book::html_title;
+use super::compression::read_u32;
+use super::index::{read_index, IndexEntry};
+use super::{build_toc, part_file, part_path, Images, MobiBook, MobiHeader, TocItem, NULL_INDEX, TEXT_DIR};
+
+// 组装后的一个文件：在 XHTML 流中的起始位置和内容
+struct Part {
+    start: usize,
+    data: Vec<u8>,
+}
+
+// 片段表中的一项：插入位置和长度
+struct Fragment {
+    insert_at: usize,
+    length: usize,
+}
+
+static POS_LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i-u)kindle:pos:fid:([0-9a-v]{4}):off:([0-9a-v]{10})").unwrap());
+static EMBED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i-u)kindle:embed:([0-9a-v]{4})(?:\?[^"')\s]*)?"#).unwrap());
+static FLOW: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i-u)kindle:flow:([0-9a-v]{4})(?:\?[^"')\s]*)?"#).unwrap());
+// KF8 的定位属性，转换后不再需要
+static AID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i-u)\s+aid\s*=\s*["'][^"']*["']"#).unwrap());
+// 片段中最后一个带 id 的标签
+static ID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i-u)<[^>]*\sid\s*=\s*["']([^"']+)["']"#).unwrap());
+
+pub(super) fn convert(text: &[u8], records: &[&[u8]], base: usize, header: &MobiHeader, images: &Images) -> MobiBook {
+    let index = |value: u32| (value != NULL_INDEX).then(|| base + value as usize);
+    let flows = split_flows(text, index(header.fdst_index).and_then(|i| records.get(i).copied()));
+
+    let fragments: Vec<Fragment> = index(header.fragment_index)
+        .and_then(|i| read_index(records, i))
+        .map(|(entries, _)| {
+            entries.iter()
+                .map(|entry| Fragment {
+                    insert_at: entry.name.trim().parse().unwrap_or(0),
+                    length: entry.tag(6, 1).unwrap_or(0) as usize,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let skeletons: Vec<IndexEntry> = index(header.skeleton_index)
+        .and_then(|i| read_index(records, i))
+        .map(|(entries, _)| entries)
+        .unwrap_or_default();
+
+    let html = flows.first().copied().unwrap_or_default();
+    let parts = assemble(html, &skeletons, &fragments);
+
+    let locator = Locator {
+        parts: &parts,
+        fragments: &fragments,
+    };
+
+    // 其他流存为样式表或 SVG 文件，其中的图片地址相对于 styles 目录
+    let styles: Vec<(String, Vec<u8>, String)> = flows.iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, data)| {
+            let (path, mime) = flow_path(i, data);
+            let data = rewrite_embeds(data, images);
+            (path, data, mime.to_string())
+        })
+        .collect();
+
+    let mut converted = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let data = POS_LINK.replace_all(&part.data, |caps: &Captures| {
+            match (base32(&caps[1]), base32(&caps[2])) {
+                (Some(fid), Some(offset)) => locator.locate(fid as usize, offset as usize).into_bytes(),
+                _ => Vec::new(),
+            }
+        });
+        let data = rewrite_embeds(&data, images);
+        let data = FLOW.replace_all(&data, |caps: &Captures| {
+            let index = base32(&caps[1]).unwrap_or(0) as usize;
+            match flows.get(index) {
+                Some(content) => format!("../{}", flow_path(index, content).0).into_bytes(),
+                None => Vec::new(),
+            }
+        });
+        let data = AID.replace_all(&data, &b""[..]);
+        converted.push((part_path(i), header.decode(&data)));
+    }
+
+    // NCX 目录项用 kindle:pos 的两个编号（片段序号、偏移）指向正文
+    let toc: Vec<TocItem> = index(header.ncx_index)
+        .and_then(|i| read_index(records, i))
+        .map(|(entries, cncx)| {
+            entries.iter()
+                .map(|entry| {
+                    let href = match (entry.tag(6, 0), entry.tag(6, 1)) {
+                        (Some(fid), Some(offset)) => locator.locate(fid as usize, offset as usize),
+                        _ => part_file(0),
+                    };
+                    TocItem {
+                        label: entry.tag(3, 0).and_then(|offset| cncx.get(offset)).unwrap_or_default(),
+                        href: format!("{}/{}", TEXT_DIR, href),
+                        depth: entry.tag(4, 0).unwrap_or(0),
+                        parent: entry.tag(21, 0).map(|p| p as usize),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let toc = if toc.is_empty() {
+        converted.iter().enumerate()
+            .map(|(i, (path, html))| TocItem {
+                label: html_title(html).unwrap_or_else(|| format!("第 {} 部分", i + 1)),
+                href: path.clone(),
+                depth: 0,
+                parent: None,
+            })
+            .collect()
+    } else {
+        toc
+    };
+
+    MobiBook { parts: converted, styles, toc: build_toc(toc) }
+}
+
+// 按 FDST 记录切分文本流，没有 FDST 时整段文本就是 XHTML 流
+fn split_flows<'a>(text: &'a [u8], fdst: Option<&[u8]>) -> Vec<&'a [u8]> {
+    let Some(fdst) = fdst.filter(|r| r.starts_with(b"FDST")) else {
+        return vec![text];
+    };
+    let count = read_u32(fdst, 8).unwrap_or(0) as usize;
+    (0..count)
+        .filter_map(|i| {
+            let start = read_u32(fdst, 12 + i * 8)? as usize;
+            let end = read_u32(fdst, 16 + i * 8)? as usize;
+            text.get(start..end.min(text.len()))
+        })
+        .collect()
+}
+
+// 每个骨架之后紧跟着它的片段，按片段表的顺序依次插入骨架
+fn assemble(html: &[u8], skeletons: &[IndexEntry], fragments: &[Fragment]) -> Vec<Part> {
+    if skeletons.is_empty() {
+        return vec![Part { start: 0, data: html.to_vec() }];
+    }
+    let mut parts = Vec::new();
+    let mut next_fragment = 0;
+    for skeleton in skeletons {
+        let count = skeleton.tag(1, 0).unwrap_or(0) as usize;
+        let start = skeleton.tag(6, 0).unwrap_or(0) as usize;
+        let length = skeleton.tag(6, 1).unwrap_or(0) as usize;
+        let Some(mut data) = html.get(start..start + length).map(<[u8]>::to_vec) else { continue };
+
+        let mut cursor = start + length;
+        for fragment in fragments.iter().skip(next_fragment).take(count) {
+            let at = fragment.insert_at.saturating_sub(start).min(data.len());
+            let end = (cursor + fragment.length).min(html.len());
+            let content = html.get(cursor..end).unwrap_or_default();
+            data.splice(at..at, content.iter().copied());
+            cursor = end;
+        }
+        next_fragment += count;
+        parts.push(Part { start, data });
+    }
+    parts
+}
+
+// 把 kindle:pos 的片段序号和偏移定位到 “partNNNN.xhtml#id”
+struct Locator<'a> {
+    parts: &'a [Part],
+    fragments: &'a [Fragment],
+}
+
+impl Locator<'_> {
+    // 位置所在的文件，以及这个位置之前最近的 id，没有 id 时指向文件开头
+    fn locate(&self, fid: usize, offset: usize) -> String {
+        let Some(fragment) = self.fragments.get(fid) else { return part_file(0) };
+        let pos = fragment.insert_at + offset;
+        let index = self.parts.iter()
+            .rposition(|part| part.start <= pos && pos < part.start + part.data.len())
+            .or_else(|| self.parts.iter().rposition(|part| part.start <= pos))
+            .unwrap_or(0);
+        let file = part_file(index);
+        let Some(part) = self.parts.get(index) else { return file };
+
+        // 位置在标签开头或内部时把整个标签算进来
+        let mut end = (pos - part.start.min(pos)).min(part.data.len());
+        let next_open = part.data[end..].iter().position(|&b| b == b'<');
+        let next_close = part.data[end..].iter().position(|&b| b == b'>');
+        if let Some(close) = next_close {
+            let in_tag = match next_open {
+                Some(open) => open == 0 || close < open,
+                None => true,
+            };
+            if in_tag {
+                end += close + 1;
+            }
+        }
+        match ID.captures_iter(&part.data[..end]).last() {
+            Some(caps) => format!("{}#{}", file, String::from_utf8_lossy(&caps[1])),
+            None => file,
+        }
+    }
+}
+
+fn rewrite_embeds(data: &[u8], images: &Images) -> Vec<u8> {
+    EMBED.replace_all(data, |caps: &Captures| {
+        let index = base32(&caps[1]).unwrap_or(0);
+        match images.path(index) {
+            Some(path) => format!("../{}", path).into_bytes(),
+            // 字体等其他资源暂不转换
+            None => caps[0].to_vec(),
+        }
+    }).into_owned()
+}
+
+// 第 i 个流的存放路径和类型，内容以 < 开头的是 SVG
+fn flow_path(index: usize, data: &[u8]) -> (String, &'static str) {
+    let svg = data.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'<');
+    if svg {
+        (format!("styles/flow{:04}.svg", index), "image/svg+xml")
+    } else {
+        (format!("styles/flow{:04}.css", index), "text/css")
+    }
+}
+
+fn base32(digits: &[u8]) -> Option<u32> {
+    u32::from_str_radix(std::str::from_utf8(digits).ok()?, 32).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn skeleton(fragments: u32, start: u32, length: u32) -> IndexEntry {
+        IndexEntry {
+            name: String::new(),
+            tags: HashMap::from([(1, vec![fragments]), (6, vec![start, length])]),
+        }
+    }
+
+    const SKELETON: &str = "<html><body></body></html>";
+    const FIRST: &str = r#"<p id="a">One</p>"#;
+    const SECOND: &str = r#"<p id="b">Two</p>"#;
+
+    // 第一个文件是骨架加两个片段，第二个文件只有骨架
+    fn stream() -> (Vec<u8>, Vec<IndexEntry>, Vec<Fragment>) {
+        let html = [SKELETON, FIRST, SECOND, SKELETON].concat().into_bytes();
+        let second_start = (SKELETON.len() + FIRST.len() + SECOND.len()) as u32;
+        let skeletons = vec![
+            skeleton(2, 0, SKELETON.len() as u32),
+            skeleton(0, second_start, SKELETON.len() as u32),
+        ];
+        let fragments = vec![
+            Fragment { insert_at: 12, length: FIRST.len() },
+            Fragment { insert_at: 12 + FIRST.len(), length: SECOND.len() },
+        ];
+        (html, skeletons, fragments)
+    }
+
+    #[test]
+    fn assembles_skeletons_and_fragments() {
+        let (html, skeletons, fragments) = stream();
+        let parts = assemble(&html, &skeletons, &fragments);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].data, format!("<html><body>{}{}</body></html>", FIRST, SECOND).as_bytes());
+        assert_eq!(parts[1].data, SKELETON.as_bytes());
+        assert_eq!(parts[1].start, SKELETON.len() + FIRST.len() + SECOND.len());
+    }
+
+    #[test]
+    fn locates_positions() {
+        let (html, skeletons, fragments) = stream();
+        let parts = assemble(&html, &skeletons, &fragments);
+        let locator = Locator { parts: &parts, fragments: &fragments };
+        assert_eq!(locator.locate(0, 0), "part0001.xhtml#a");
+        assert_eq!(locator.locate(1, 0), "part0001.xhtml#b");
+        assert_eq!(locator.locate(1, 12), "part0001.xhtml#b");
+        assert_eq!(locator.locate(5, 0), "part0001.xhtml");
+    }
+
+    #[test]
+    fn truncated_stream() {
+        let (html, skeletons, fragments) = stream();
+        for len in 0..html.len() {
+            let parts = assemble(&html[..len], &skeletons, &fragments);
+            let locator = Locator { parts: &parts, fragments: &fragments };
+            locator.locate(1, 100);
+        }
+        // 骨架超出文本时跳过
+        let parts = assemble(&html[..10], &skeletons, &fragments);
+        assert!(parts.is_empty());
+    }
+
+    #[test]
+    fn splits_flows() {
+        let text = b"<html/>body{}<svg/>";
+        let mut fdst = b"FDST".to_vec();
+        for value in [12u32, 3, 0, 7, 7, 13, 13, 40] {
+            fdst.extend_from_slice(&value.to_be_bytes());
+        }
+        assert_eq!(split_flows(text, Some(&fdst)), [&b"<html/>"[..], b"body{}", b"<svg/>"]);
+        // FDST 记录被截断时只取完整的几项
+        assert_eq!(split_flows(text, Some(&fdst[..24])), [&b"<html/>"[..]]);
+        assert_eq!(split_flows(text, None), [&text[..]]);
+        assert_eq!(flow_path(2, b"<svg/>"), ("styles/flow0002.svg".to_string(), "image/svg+xml"));
+        assert_eq!(flow_path(1, b"body{}"), ("styles/flow0001.css".to_string(), "text/css"));
+    }
+
+    #[test]
+    fn base32_numbers() {
+        assert_eq!(base32(b"000v"), Some(31));
+        assert_eq!(base32(b"0010"), Some(32));
+        assert_eq!(base32(b"zz"), None);
+    }
+}
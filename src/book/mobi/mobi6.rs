@@ -0,0 +1,163 @@
+// MOBI6 正文：一整段 HTML，章节间用 <mbp:pagebreak/> 分隔，
+// 书内链接写作 <a filepos=N> 指向正文的字节位置，图片写作 <img recindex=N>
+use std::collections::{BTreeSet, HashMap};
+use std::sync::LazyLock;
+use regex::bytes::{Captures, Regex};
+use crate::book::convert::xhtml_page;
+use crate::book::first_heading;
+use super::index::read_index;
+use super::{build_toc, part_file, part_path, Images, MobiBook, MobiHeader, TocItem, NULL_INDEX, TEXT_DIR};
+
+static FILEPOS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i-u)\bfilepos\s*=\s*["']?0*(\d+)["']?"#).unwrap());
+// 只保留 <body> 中的内容
+static BODY_START: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is-u)^.*?<body[^>]*>").unwrap());
+static BODY_END: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is-u)</body>.*$").unwrap());
+// 章节间的分页符
+static PAGEBREAK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i-u)<mbp:pagebreak[^>]*>").unwrap());
+// insert_anchors 插入的锚点
+static ANCHOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"id="filepos(\d+)""#).unwrap());
+static RECINDEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i-u)\b(?:hi|lo)?recindex\s*=\s*["']?0*(\d+)["']?"#).unwrap());
+// 其余 mbp: 标签直接去掉
+static MBP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i-u)</?mbp:[^>]*>").unwrap());
+
+pub(super) fn convert(text: &[u8], records: &[&[u8]], header: &MobiHeader, images: &Images) -> MobiBook {
+    // NCX 目录：(标题, 字节位置, 层级, 父项)
+    let ncx: Vec<(String, usize, u32, Option<usize>)> = match header.ncx_index {
+        NULL_INDEX => Vec::new(),
+        index => read_index(records, index as usize)
+            .map(|(entries, cncx)| {
+                entries.iter()
+                    .filter_map(|entry| {
+                        let pos = entry.tag(1, 0)? as usize;
+                        let label = entry.tag(3, 0).and_then(|offset| cncx.get(offset)).unwrap_or_default();
+                        Some((label, pos, entry.tag(4, 0).unwrap_or(0), entry.tag(21, 0).map(|p| p as usize)))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    // 在所有被链接的位置插入锚点
+    let mut targets: BTreeSet<usize> = FILEPOS.captures_iter(text)
+        .filter_map(|caps| std::str::from_utf8(&caps[1]).ok()?.parse().ok())
+        .collect();
+    targets.extend(ncx.iter().map(|(_, pos, _, _)| *pos));
+    let text = insert_anchors(text, &targets);
+
+    // 只保留 <body> 中的内容，再按分页符切分
+    let text = BODY_START.replace(&text, &b""[..]).into_owned();
+    let text = BODY_END.replace(&text, &b""[..]).into_owned();
+    let mut chunks: Vec<&[u8]> = PAGEBREAK.split(&text).collect();
+    chunks.retain(|chunk| !chunk.iter().all(u8::is_ascii_whitespace));
+
+    // 锚点所在的章节
+    let mut anchor_parts: HashMap<usize, usize> = HashMap::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        for caps in ANCHOR.captures_iter(chunk) {
+            if let Some(pos) = std::str::from_utf8(&caps[1]).ok().and_then(|p| p.parse().ok()) {
+                anchor_parts.insert(pos, i);
+            }
+        }
+    }
+    let href = |pos: usize| -> String {
+        let part = anchor_parts.get(&pos).copied().unwrap_or(0);
+        format!("{}#filepos{}", part_file(part), pos)
+    };
+
+    let title = header.metadata().0.get("title").and_then(|t| t.first().cloned()).unwrap_or_default();
+
+    let mut parts = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let chunk = FILEPOS.replace_all(chunk, |caps: &Captures| {
+            let pos = std::str::from_utf8(&caps[1]).ok().and_then(|p| p.parse().ok()).unwrap_or(0);
+            format!(r#"href="{}""#, href(pos)).into_bytes()
+        });
+        let chunk = RECINDEX.replace_all(&chunk, |caps: &Captures| {
+            let index = std::str::from_utf8(&caps[1]).ok().and_then(|i| i.parse().ok()).unwrap_or(0);
+            match images.path(index) {
+                Some(path) => format!(r#"src="../{}""#, path).into_bytes(),
+                None => Vec::new(),
+            }
+        });
+        let chunk = MBP.replace_all(&chunk, &b""[..]);
+        parts.push((part_path(i), xhtml_page(&title, &header.decode(&chunk))));
+    }
+
+    let toc = if ncx.is_empty() {
+        // 没有 NCX 时每个章节一个目录项，标题取第一个标题元素
+        parts.iter().enumerate()
+            .map(|(i, (path, html))| TocItem {
+                label: first_heading(html).unwrap_or_else(|| format!("第 {} 部分", i + 1)),
+                href: path.clone(),
+                depth: 0,
+                parent: None,
+            })
+            .collect()
+    } else {
+        ncx.into_iter()
+            .map(|(label, pos, depth, parent)| TocItem {
+                label,
+                href: format!("{}/{}", TEXT_DIR, href(pos)),
+                depth,
+                parent,
+            })
+            .collect()
+    };
+
+    MobiBook { parts, styles: Vec::new(), toc: build_toc(toc) }
+}
+
+// 在每个目标位置插入 <a id="fileposN"></a>。位置落在标签内部时移到标签结束之后
+fn insert_anchors(text: &[u8], targets: &BTreeSet<usize>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len() + targets.len() * 24);
+    let mut last = 0;
+    let mut in_tag = false;  // `last` 处是否在标签内部
+    for &target in targets.iter().filter(|&&t| t <= text.len()) {
+        let mut at = target.max(last);
+        let segment = &text[last..at];
+        if let Some(p) = segment.iter().rposition(|&b| b == b'<' || b == b'>') {
+            in_tag = segment[p] == b'<';
+        }
+        if in_tag {
+            at = text[at..].iter().position(|&b| b == b'>').map_or(text.len(), |p| at + p + 1);
+            in_tag = false;
+        }
+        out.extend_from_slice(&text[last..at]);
+        out.extend_from_slice(format!(r#"<a id="filepos{}"></a>"#, target).as_bytes());
+        last = at;
+    }
+    out.extend_from_slice(&text[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchors(text: &str, targets: &[usize]) -> String {
+        String::from_utf8(insert_anchors(text.as_bytes(), &targets.iter().copied().collect())).unwrap()
+    }
+
+    #[test]
+    fn inserts_anchors() {
+        assert_eq!(anchors("<p>ab</p>", &[3]), r#"<p><a id="filepos3"></a>ab</p>"#);
+        assert_eq!(anchors("ab", &[0, 2]), r#"<a id="filepos0"></a>ab<a id="filepos2"></a>"#);
+    }
+
+    #[test]
+    fn anchors_inside_tags_move_after_them() {
+        assert_eq!(anchors(r#"<p class="x">a</p>"#, &[4]), r#"<p class="x"><a id="filepos4"></a>a</p>"#);
+        // 同一个标签内的两个位置
+        assert_eq!(
+            anchors(r#"<p class="x">a</p>"#, &[2, 5]),
+            r#"<p class="x"><a id="filepos2"></a><a id="filepos5"></a>a</p>"#,
+        );
+    }
+
+    #[test]
+    fn anchors_past_the_end_are_ignored() {
+        assert_eq!(anchors("ab", &[5]), "ab");
+        // 截断在标签中间的文本
+        assert_eq!(anchors("a<p cla", &[3]), r#"a<p cla<a id="filepos3"></a>"#);
+    }
+}
@@ -0,0 +1,393 @@
+// Kindle MOBI / AZW3 电子书（无 DRM）。
+// 文件是 PalmDB 容器：第 0 条记录是 PalmDOC 头、MOBI 头和 EXTH 元数据，之后是压缩的正文记录、
+// 索引表和图片等资源。旧的 MOBI6 正文是一整段带 filepos 链接的 HTML，
+// KF8（AZW3，或 MOBI 文件中的 KF8 部分）则由骨架和片段重新拼出各个 XHTML 文件。
+mod compression;
+mod index;
+mod kf8;
+mod mobi6;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use crate::book::convert::Converted;
+use crate::book::metadata::PackageMetadata;
+use crate::book::{BookContent, BookError, Contributor, Identifier, NavPoint};
+use compression::{palmdoc_decompress, read_u16, read_u32, HuffReader};
+
+// 索引字段未使用时的取值
+const NULL_INDEX: u32 = 0xFFFF_FFFF;
+
+// 每个正文记录解压后的长度
+const TEXT_RECORD_SIZE: usize = 4096;
+
+// 转换后章节和图片的存放位置
+const TEXT_DIR: &str = "text";
+const IMAGE_DIR: &str = "images";
+
+/// 把 MOBI / AZW3 文件转换为书的结构和内存中的资源。
+/// 文件中同时有 MOBI6 和 KF8 两份内容时使用 KF8。
+pub(crate) fn import_mobi(data: &[u8]) -> Result<Converted, BookError> {
+    let records = pdb_records(data)
+        .ok_or_else(|| BookError::MalformedMobi("不是 PalmDB 格式".to_string()))?;
+    let first = MobiHeader::parse(records.first().copied().unwrap_or_default())
+        .ok_or_else(|| BookError::MalformedMobi("缺少 MOBI 头".to_string()))?;
+    if first.encryption != 0 {
+        return Err(BookError::Encrypted);
+    }
+
+    // KF8 部分紧跟在 BOUNDARY 记录之后，它的索引字段都相对于自己的头记录
+    let kf8_start = if first.version >= 8 {
+        Some(0)
+    } else {
+        first.exth_u32(121)
+            .map(|boundary| boundary as usize)
+            .filter(|&boundary| records.get(boundary.wrapping_sub(1)).is_some_and(|r| r.starts_with(b"BOUNDARY")))
+    };
+    let kf8 = kf8_start.and_then(|start| {
+        let header = MobiHeader::parse(records.get(start)?)?;
+        Some((start, header))
+    });
+
+    // 图片在 MOBI6 和 KF8 之间共用，序号从第一个头记录给出的位置开始
+    let resource_end = match kf8_start {
+        Some(start) if start > 0 => start - 1,
+        _ => records.len(),
+    };
+    let images = extract_images(&records, first.first_image as usize, resource_end);
+
+    let (base, header) = match &kf8 {
+        Some((start, header)) => (*start, header),
+        None => (0, &first),
+    };
+    let text = header.text(&records, base)
+        .ok_or_else(|| BookError::MalformedMobi("无法解压正文".to_string()))?;
+
+    let book = match kf8 {
+        Some(_) => kf8::convert(&text, &records, base, header, &images),
+        None => mobi6::convert(&text, &records, header, &images),
+    };
+    if book.parts.is_empty() {
+        return Err(BookError::MalformedMobi("正文为空".to_string()));
+    }
+
+    let mut resources = HashMap::new();
+    let mut manifest = HashMap::new();
+    let mut spine = Vec::new();
+    for (i, (path, html)) in book.parts.into_iter().enumerate() {
+        let id = format!("part{}", i + 1);
+        manifest.insert(id.clone(), (PathBuf::from(&path), "application/xhtml+xml".to_string()));
+        resources.insert(path, (html.into_bytes(), "application/xhtml+xml".to_string()));
+        spine.push(id);
+    }
+    for (path, data, mime) in book.styles {
+        manifest.insert(path.clone(), (PathBuf::from(&path), mime.clone()));
+        resources.insert(path, (data, mime));
+    }
+    let mut cover_id = None;
+    for (index, (path, mime)) in images.paths.iter() {
+        let id = format!("image{}", index);
+        if header.exth_u32(201).is_some_and(|offset| offset + 1 == *index) {
+            cover_id = Some(id.clone());
+        }
+        manifest.insert(id, (PathBuf::from(path), mime.clone()));
+    }
+    for (path, data, mime) in images.data {
+        resources.insert(path, (data, mime));
+    }
+
+    let (metadata, package) = header.metadata();
+    let mut content = BookContent::from_parts(spine, manifest, book.toc, metadata);
+    content.package_metadata = package;
+    content.cover_id = cover_id;
+    Ok(Converted { content, resources })
+}
+
+// 转换后的正文：各章节（路径, XHTML）、KF8 的样式等其他流以及目录
+struct MobiBook {
+    parts: Vec<(String, String)>,
+    styles: Vec<(String, Vec<u8>, String)>,
+    toc: Vec<NavPoint>,
+}
+
+// PalmDB 容器中的全部记录
+fn pdb_records(data: &[u8]) -> Option<Vec<&[u8]>> {
+    if data.get(60..68)? != b"BOOKMOBI" {
+        return None;
+    }
+    let count = read_u16(data, 76)? as usize;
+    let offsets: Vec<usize> = (0..count)
+        .map(|i| read_u32(data, 78 + i * 8).map(|o| o as usize))
+        .collect::<Option<_>>()?;
+    let mut records = Vec::with_capacity(count);
+    for (i, &start) in offsets.iter().enumerate() {
+        let end = offsets.get(i + 1).copied().unwrap_or(data.len());
+        records.push(data.get(start..end.max(start))?);
+    }
+    Some(records)
+}
+
+// 头记录中用到的字段
+struct MobiHeader {
+    compression: u16,
+    text_length: usize,
+    text_records: usize,
+    encryption: u16,
+    encoding: u32,
+    version: u32,
+    full_name: Vec<u8>,
+    first_image: u32,
+    huff_record: u32,
+    huff_count: u32,
+    extra_flags: u16,
+    ncx_index: u32,
+    fdst_index: u32,
+    skeleton_index: u32,
+    fragment_index: u32,
+    exth: HashMap<u32, Vec<Vec<u8>>>,
+}
+
+impl MobiHeader {
+    fn parse(record: &[u8]) -> Option<Self> {
+        if record.get(16..20)? != b"MOBI" {
+            return None;
+        }
+        let header_len = read_u32(record, 20)? as usize;
+        let field = |offset: usize| {
+            // 旧版本的头比较短，没有的字段视为未使用
+            if offset + 4 <= 16 + header_len { read_u32(record, offset) } else { None }
+        };
+        let name_offset = field(0x54).unwrap_or(0) as usize;
+        let name_len = field(0x58).unwrap_or(0) as usize;
+
+        let mut exth = HashMap::new();
+        if field(0x80).is_some_and(|flags| flags & 0x40 != 0) {
+            let start = 16 + header_len;
+            if record.get(start..start + 4) == Some(b"EXTH") {
+                let count = read_u32(record, start + 8).unwrap_or(0);
+                let mut pos = start + 12;
+                for _ in 0..count {
+                    let (Some(kind), Some(len)) = (read_u32(record, pos), read_u32(record, pos + 4)) else { break };
+                    let len = len as usize;
+                    let Some(value) = record.get(pos + 8..pos + len.max(8)) else { break };
+                    exth.entry(kind).or_insert_with(Vec::new).push(value.to_vec());
+                    pos += len.max(8);
+                }
+            }
+        }
+
+        Some(Self {
+            compression: read_u16(record, 0)?,
+            text_length: read_u32(record, 4)? as usize,
+            text_records: read_u16(record, 8)? as usize,
+            encryption: read_u16(record, 12)?,
+            encoding: field(0x1C).unwrap_or(1252),
+            version: field(0x24).unwrap_or(0),
+            full_name: record.get(name_offset..name_offset + name_len).unwrap_or_default().to_vec(),
+            first_image: field(0x6C).unwrap_or(NULL_INDEX),
+            huff_record: field(0x70).unwrap_or(NULL_INDEX),
+            huff_count: field(0x74).unwrap_or(0),
+            extra_flags: if header_len >= 0xE4 { read_u16(record, 0xF2).unwrap_or(0) } else { 0 },
+            ncx_index: field(0xF4).unwrap_or(NULL_INDEX),
+            fdst_index: field(0xC0).unwrap_or(NULL_INDEX),
+            fragment_index: field(0xF8).unwrap_or(NULL_INDEX),
+            skeleton_index: field(0xFC).unwrap_or(NULL_INDEX),
+            exth,
+        })
+    }
+
+    fn text_encoding(&self) -> &'static Encoding {
+        if self.encoding == 65001 { UTF_8 } else { WINDOWS_1252 }
+    }
+
+    fn decode(&self, data: &[u8]) -> String {
+        self.text_encoding().decode_without_bom_handling(data).0.into_owned()
+    }
+
+    fn exth_u32(&self, kind: u32) -> Option<u32> {
+        self.exth.get(&kind)?.first().and_then(|value| read_u32(value, 0)).filter(|&v| v != NULL_INDEX)
+    }
+
+    fn exth_strings(&self, kind: u32) -> Vec<String> {
+        self.exth.get(&kind)
+            .map(|values| values.iter().map(|v| self.decode(v).trim().to_string()).filter(|v| !v.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    // 解压全部正文记录，`base` 为头记录的序号
+    fn text(&self, records: &[&[u8]], base: usize) -> Option<Vec<u8>> {
+        let mut huff = match self.compression {
+            17480 => {
+                let start = base + self.huff_record as usize;
+                let huff = records.get(start)?;
+                let cdics: Vec<&[u8]> = (1..self.huff_count as usize)
+                    .filter_map(|i| records.get(start + i).copied())
+                    .collect();
+                Some(HuffReader::new(huff, &cdics)?)
+            }
+            1 | 2 => None,
+            _ => return None,
+        };
+
+        // 文件头中的长度不可信，按实际的记录数限制预分配的大小
+        let mut text = Vec::with_capacity(self.text_length.min(records.len() * TEXT_RECORD_SIZE));
+        for record in (base + 1..=base + self.text_records).filter_map(|i| records.get(i)) {
+            let record = &record[..record.len() - trailing_size(record, self.extra_flags)];
+            match (self.compression, huff.as_mut()) {
+                (2, _) => text.extend(palmdoc_decompress(record)),
+                (17480, Some(huff)) => text.extend(huff.decompress(record)),
+                _ => text.extend_from_slice(record),
+            }
+        }
+        text.truncate(self.text_length);
+        Some(text)
+    }
+
+    // EXTH 元数据：标题、作者、出版社、简介、主题、日期、语言和 ISBN
+    fn metadata(&self) -> (HashMap<String, Vec<String>>, PackageMetadata) {
+        let title = self.exth_strings(503).into_iter().next()
+            .or_else(|| Some(self.decode(&self.full_name).trim().to_string()).filter(|t| !t.is_empty()));
+        let authors = self.exth_strings(100);
+
+        let mut metadata = HashMap::new();
+        if let Some(title) = title {
+            metadata.insert("title".to_string(), vec![title]);
+        }
+        if !authors.is_empty() {
+            metadata.insert("creator".to_string(), authors.clone());
+        }
+        if let Some(description) = self.exth_strings(103).into_iter().next() {
+            metadata.insert("description".to_string(), vec![description]);
+        }
+
+        let package = PackageMetadata {
+            creators: authors.into_iter()
+                .map(|name| Contributor { name, role: Some("aut".to_string()), file_as: None })
+                .collect(),
+            contributors: Vec::new(),
+            language: self.exth_strings(524).into_iter().next(),
+            publisher: self.exth_strings(101).into_iter().next(),
+            date: self.exth_strings(106).into_iter().next(),
+            subjects: self.exth_strings(105),
+            identifiers: self.exth_strings(104).into_iter()
+                .map(|value| Identifier { value, scheme: Some("ISBN".to_string()) })
+                .collect(),
+            series: None,
+        };
+        (metadata, package)
+    }
+}
+
+// 正文记录末尾附加的数据（多字节字符的跨记录部分、索引信息等）的总长度，解压前要去掉。
+// `flags` 的第 0 位表示多字节字符，其余每一位对应一段以反向变长整数标明长度的数据
+fn trailing_size(record: &[u8], flags: u16) -> usize {
+    let mut size = 0;
+    let mut bits = flags >> 1;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            let end = record.len().saturating_sub(size);
+            let mut value = 0usize;
+            for &byte in &record[end.saturating_sub(4)..end] {
+                if byte & 0x80 != 0 {
+                    value = 0;
+                }
+                value = (value << 7) | (byte & 0x7F) as usize;
+            }
+            size += value;
+        }
+        bits >>= 1;
+    }
+    if flags & 1 != 0 {
+        if let Some(&byte) = record.len().checked_sub(size + 1).and_then(|i| record.get(i)) {
+            size += (byte & 0x03) as usize + 1;
+        }
+    }
+    size.min(record.len())
+}
+
+// 资源记录中的图片，按资源序号（从 1 开始，即 recindex 和 kindle:embed 的编号）存放
+struct Images {
+    paths: Vec<(u32, (String, String))>,   // 序号 -> (路径, MIME 类型)
+    data: Vec<(String, Vec<u8>, String)>,
+}
+
+impl Images {
+    fn path(&self, index: u32) -> Option<&str> {
+        self.paths.iter().find(|(i, _)| *i == index).map(|(_, (path, _))| path.as_str())
+    }
+}
+
+fn extract_images(records: &[&[u8]], first: usize, end: usize) -> Images {
+    let mut images = Images { paths: Vec::new(), data: Vec::new() };
+    if first as u32 == NULL_INDEX {
+        return images;
+    }
+    for (i, record) in records.iter().enumerate().take(end).skip(first) {
+        let Some((ext, mime)) = image_type(record) else { continue };
+        let index = (i - first + 1) as u32;
+        let path = format!("{}/{:05}.{}", IMAGE_DIR, index, ext);
+        images.paths.push((index, (path.clone(), mime.to_string())));
+        images.data.push((path, record.to_vec(), mime.to_string()));
+    }
+    images
+}
+
+// 按文件头识别图片格式，FLIS、FCIS、FONT 等其他记录返回 None
+fn image_type(data: &[u8]) -> Option<(&'static str, &'static str)> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(("jpg", "image/jpeg"))
+    } else if data.starts_with(b"\x89PNG") {
+        Some(("png", "image/png"))
+    } else if data.starts_with(b"GIF8") {
+        Some(("gif", "image/gif"))
+    } else if data.starts_with(b"BM") && data.len() > 14 {
+        Some(("bmp", "image/bmp"))
+    } else {
+        None
+    }
+}
+
+// 目录中的一项
+struct TocItem {
+    label: String,
+    href: String,
+    depth: u32,
+    parent: Option<usize>,
+}
+
+// 按父项或层级组织成目录树，顺序号按文件中的顺序编排
+fn build_toc(items: Vec<TocItem>) -> Vec<NavPoint> {
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); items.len()];
+    let mut roots = Vec::new();
+    let mut stack: Vec<(u32, usize)> = Vec::new();  // 没有父项信息时按层级推断
+    for (i, item) in items.iter().enumerate() {
+        while stack.last().is_some_and(|(depth, _)| *depth >= item.depth) {
+            stack.pop();
+        }
+        let parent = item.parent.filter(|&p| p < i).or(stack.last().map(|(_, p)| *p));
+        match parent {
+            Some(parent) => children[parent].push(i),
+            None => roots.push(i),
+        }
+        stack.push((item.depth, i));
+    }
+
+    fn node(i: usize, items: &[TocItem], children: &[Vec<usize>]) -> NavPoint {
+        NavPoint {
+            label: items[i].label.clone(),
+            content: PathBuf::from(&items[i].href),
+            children: children[i].iter().map(|&c| node(c, items, children)).collect(),
+            play_order: i + 1,
+        }
+    }
+    roots.iter().map(|&i| node(i, &items, &children)).collect()
+}
+
+// 第 index 个章节的文件名和完整路径
+fn part_file(index: usize) -> String {
+    format!("part{:04}.xhtml", index + 1)
+}
+
+fn part_path(index: usize) -> String {
+    format!("{}/{}", TEXT_DIR, part_file(index))
+}
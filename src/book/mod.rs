@@ -0,0 +1,40 @@
+mod archive;
+mod cache;
+mod cbz;
+mod content;
+mod convert;
+mod cover;
+mod css;
+mod encoding;
+mod encryption;
+mod error;
+mod fb2;
+mod html_processor;
+mod loader;
+mod media_overlay;
+mod metadata;
+mod mobi;
+mod nav;
+mod paths;
+mod rendition;
+mod sanitizer;
+mod txt;
+
+pub use epub::doc::NavPoint;
+pub use archive::{BookArchive, guess_mime};
+pub use cache::{ChapterCache, DEFAULT_CHAPTER_CACHE_BYTES};
+pub use encoding::{decode_text, detect_encoding};
+pub use encryption::{parse_encryption, EncryptedResource, FontObfuscation};
+pub use error::BookError;
+pub use content::{BookContent, BookMetadata, Chapter};
+pub use cover::{thumbnail, THUMBNAIL_SIZE};
+pub use css::{css_imports, is_vertical_writing, rescope_css, scope_css, CONTENT_SCOPE};
+pub use html_processor::{extract_stylesheets, first_heading, html_title, note_content, process_html_content, resource_url, BODY_ATTRIBUTE, LINK_ATTRIBUTE, NOTEREF_ATTRIBUTE, NOTE_ATTRIBUTE, RESOURCE_SCHEME};
+pub use loader::Book;
+pub use media_overlay::{parse_smil, MediaOverlay, OverlayClip, DEFAULT_ACTIVE_CLASS};
+pub use metadata::{Contributor, Identifier, Series};
+pub use nav::{parse_nav, parse_ncx_page_list, Landmark, NavDocument, PageTarget};
+pub use paths::{is_external, normalize_path, path_to_string, resolve_path};
+pub use rendition::{page_viewport, FixedPage, ItemRendition, Layout, PageDirection, PageSpread, Rendition, Spread, SPINE_ATTRIBUTE};
+pub use sanitizer::Removal;
+pub use txt::ChapterPatterns;
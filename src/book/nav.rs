@@ -0,0 +1,323 @@
+use std::path::PathBuf;
+use epub::doc::NavPoint;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use scraper::{ElementRef, Html, Selector};
+use crate::book::resolve_path;
+
+/// 导航文档中的地标，如封面、目录、正文开始
+#[derive(Debug, Clone, PartialEq)]
+pub struct Landmark {
+    pub kind: String,   // epub:type，如 cover、toc、bodymatter
+    pub label: String,
+    pub href: String,   // 完整路径，可带锚点
+}
+
+/// 印刷版页码在书中的位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageTarget {
+    pub label: String,
+    pub href: String,
+}
+
+/// EPUB3 导航文档（nav.xhtml）的内容
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NavDocument {
+    pub toc: Vec<NavPoint>,
+    pub landmarks: Vec<Landmark>,
+    pub page_list: Vec<PageTarget>,
+}
+
+/// 解析导航文档，`nav_path` 是它在压缩包内的完整路径。
+/// 目录项的 `play_order` 按文档顺序从 1 开始编号。
+pub fn parse_nav(html: &str, nav_path: &str) -> NavDocument {
+    let document = Html::parse_document(html);
+    let nav_selector = Selector::parse("nav").unwrap();
+    let mut nav_doc = NavDocument::default();
+
+    for nav in document.select(&nav_selector) {
+        match nav_kind(&nav).as_deref() {
+            Some("toc") if nav_doc.toc.is_empty() => {
+                let mut play_order = 0;
+                if let Some(list) = child_element(&nav, "ol") {
+                    nav_doc.toc = toc_entries(&list, nav_path, &mut play_order);
+                }
+            }
+            Some("landmarks") if nav_doc.landmarks.is_empty() => {
+                nav_doc.landmarks = links(&nav, nav_path)
+                    .map(|(link, label, href)| Landmark {
+                        kind: nav_type(&link).unwrap_or_default(),
+                        label,
+                        href,
+                    })
+                    .collect();
+            }
+            Some("page-list") if nav_doc.page_list.is_empty() => {
+                nav_doc.page_list = links(&nav, nav_path)
+                    .map(|(_, label, href)| PageTarget { label, href })
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+
+    nav_doc
+}
+
+/// 从 OPF 中找出导航文档的完整路径（清单里带 `properties="nav"` 的项），
+/// 以及 EPUB2 `<guide>` 中登记的地标
+pub(crate) fn parse_package_nav(opf: &str, opf_path: &str) -> (Option<String>, Vec<Landmark>) {
+    let mut reader = Reader::from_str(opf);
+    let mut nav_path = None;
+    let mut landmarks = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let attr = |name: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == name)
+                        .and_then(|a| a.unescape_value().ok())
+                        .map(|v| v.into_owned())
+                };
+                match e.local_name().as_ref() {
+                    b"item" if nav_path.is_none() => {
+                        let is_nav = attr(b"properties")
+                            .is_some_and(|p| p.split_whitespace().any(|p| p == "nav"));
+                        if is_nav {
+                            nav_path = attr(b"href").and_then(|href| resolve_path(opf_path, &href));
+                        }
+                    }
+                    b"reference" => {
+                        let href = attr(b"href").and_then(|href| full_href(opf_path, &href));
+                        if let (Some(kind), Some(href)) = (attr(b"type"), href) {
+                            landmarks.push(Landmark {
+                                kind: guide_kind(&kind),
+                                label: attr(b"title").unwrap_or_default(),
+                                href,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    (nav_path, landmarks)
+}
+
+// EPUB2 guide 的类型换成对应的 EPUB3 地标类型
+fn guide_kind(kind: &str) -> String {
+    match kind {
+        "text" | "start" => "bodymatter",
+        "title-page" => "titlepage",
+        "copyright-page" => "copyright-page",
+        other => other,
+    }.to_string()
+}
+
+// `<nav>` 的类型，兼容 epub:type 和 ARIA role
+fn nav_kind(nav: &ElementRef) -> Option<String> {
+    if let Some(kind) = nav_type(nav) {
+        return Some(kind);
+    }
+    match nav.value().attr("role")? {
+        "doc-toc" => Some("toc".to_string()),
+        "doc-pagelist" => Some("page-list".to_string()),
+        "directory" => Some("landmarks".to_string()),
+        _ => None,
+    }
+}
+
+fn nav_type(element: &ElementRef) -> Option<String> {
+    element.value().attr("epub:type")
+        .and_then(|t| t.split_whitespace().next())
+        .map(|t| t.to_string())
+}
+
+// 递归解析 `<ol><li><a>…</a><ol>…</ol></li></ol>`，
+// 没有链接的 `<span>` 标题项指向它第一个子项
+fn toc_entries(list: &ElementRef, nav_path: &str, play_order: &mut usize) -> Vec<NavPoint> {
+    let mut entries = Vec::new();
+    for item in list.children().filter_map(ElementRef::wrap) {
+        if item.value().name() != "li" {
+            continue;
+        }
+        let heading = child_element(&item, "a").or_else(|| child_element(&item, "span"));
+        let Some(heading) = heading else { continue };
+
+        *play_order += 1;
+        let order = *play_order;
+        let children = child_element(&item, "ol")
+            .map(|sub| toc_entries(&sub, nav_path, play_order))
+            .unwrap_or_default();
+        let content = heading.value().attr("href")
+            .and_then(|href| full_href(nav_path, href))
+            .map(PathBuf::from)
+            .or_else(|| children.first().map(|child| child.content.clone()));
+
+        if let Some(content) = content {
+            entries.push(NavPoint {
+                label: label(&heading),
+                content,
+                children,
+                play_order: order,
+            });
+        }
+    }
+    entries
+}
+
+// 导航中的所有链接及其标签和完整地址
+fn links<'a>(
+    nav: &ElementRef<'a>,
+    nav_path: &'a str,
+) -> impl Iterator<Item = (ElementRef<'a>, String, String)> + 'a {
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let links: Vec<ElementRef<'a>> = nav.select(&link_selector).collect();
+    links.into_iter().filter_map(move |link| {
+        let href = full_href(nav_path, link.value().attr("href")?)?;
+        Some((link, label(&link), href))
+    })
+}
+
+fn child_element<'a>(parent: &ElementRef<'a>, name: &str) -> Option<ElementRef<'a>> {
+    parent.children()
+        .filter_map(ElementRef::wrap)
+        .find(|child| child.value().name() == name)
+}
+
+// 链接文字，空白合并；纯图片链接使用 title 属性
+fn label(element: &ElementRef) -> String {
+    let text = element.text().collect::<Vec<_>>().join(" ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        element.value().attr("title").unwrap_or_default().to_string()
+    } else {
+        text
+    }
+}
+
+// 解析为完整路径并保留锚点
+pub(crate) fn full_href(base: &str, href: &str) -> Option<String> {
+    let path = resolve_path(base, href)?;
+    match href.split_once('#') {
+        Some((_, fragment)) if !fragment.is_empty() => Some(format!("{}#{}", path, fragment)),
+        _ => Some(path),
+    }
+}
+
+/// 解析 NCX 中的 `<pageList>`，EPUB2 书用它记录印刷版页码
+pub fn parse_ncx_page_list(ncx: &str, ncx_path: &str) -> Vec<PageTarget> {
+    let mut reader = Reader::from_str(ncx);
+    let mut pages = Vec::new();
+    let mut in_target = false;
+    let mut in_text = false;
+    let mut label = String::new();
+    let mut href = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"pageTarget" => {
+                    in_target = true;
+                    label.clear();
+                    href = None;
+                }
+                b"text" if in_target => in_text = true,
+                b"content" if in_target => {
+                    href = e.attributes()
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == b"src")
+                        .and_then(|a| a.unescape_value().ok())
+                        .and_then(|src| full_href(ncx_path, &src));
+                }
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_text => {
+                if let Ok(text) = t.unescape() {
+                    label.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"text" => in_text = false,
+                b"pageTarget" => {
+                    in_target = false;
+                    if let Some(href) = href.take() {
+                        pages.push(PageTarget { label: label.trim().to_string(), href });
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAV: &str = r##"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops"><body>
+<nav epub:type="toc"><h1>目录</h1><ol>
+  <li><a href="Text/ch1.xhtml">第一章</a></li>
+  <li><span>第二部</span><ol>
+    <li><a href="Text/ch2.xhtml#s1">第二章 <em>开端</em></a></li>
+  </ol></li>
+</ol></nav>
+<nav epub:type="landmarks" hidden=""><ol>
+  <li><a epub:type="cover" href="Text/cover.xhtml">封面</a></li>
+  <li><a epub:type="bodymatter" href="Text/ch1.xhtml">正文</a></li>
+</ol></nav>
+<nav epub:type="page-list" hidden=""><ol>
+  <li><a href="Text/ch1.xhtml#p1">1</a></li>
+  <li><a href="Text/ch2.xhtml#p2">2</a></li>
+</ol></nav>
+</body></html>"##;
+
+    #[test]
+    fn toc() {
+        let nav = parse_nav(NAV, "OEBPS/nav.xhtml");
+        assert_eq!(nav.toc.len(), 2);
+        assert_eq!(nav.toc[0].label, "第一章");
+        assert_eq!(nav.toc[0].content, PathBuf::from("OEBPS/Text/ch1.xhtml"));
+        assert_eq!(nav.toc[0].play_order, 1);
+        assert_eq!(nav.toc[1].label, "第二部");
+        let child = &nav.toc[1].children[0];
+        assert_eq!(child.label, "第二章 开端");
+        assert_eq!(child.content, PathBuf::from("OEBPS/Text/ch2.xhtml#s1"));
+        assert!(child.play_order > nav.toc[0].play_order);
+    }
+
+    #[test]
+    fn landmarks_and_page_list() {
+        let nav = parse_nav(NAV, "OEBPS/nav.xhtml");
+        assert_eq!(nav.landmarks, [
+            Landmark { kind: "cover".into(), label: "封面".into(), href: "OEBPS/Text/cover.xhtml".into() },
+            Landmark { kind: "bodymatter".into(), label: "正文".into(), href: "OEBPS/Text/ch1.xhtml".into() },
+        ]);
+        assert_eq!(nav.page_list, [
+            PageTarget { label: "1".into(), href: "OEBPS/Text/ch1.xhtml#p1".into() },
+            PageTarget { label: "2".into(), href: "OEBPS/Text/ch2.xhtml#p2".into() },
+        ]);
+    }
+
+    #[test]
+    fn ncx_page_list() {
+        let ncx = r#"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/"><pageList>
+            <pageTarget type="normal" value="1"><navLabel><text>i</text></navLabel><content src="Text/ch1.xhtml#p1"/></pageTarget>
+            <pageTarget type="normal" value="2"><navLabel><text> 2 </text></navLabel><content src="../Text/ch2.xhtml"/></pageTarget>
+        </pageList></ncx>"#;
+        assert_eq!(parse_ncx_page_list(ncx, "OEBPS/toc.ncx"), [
+            PageTarget { label: "i".into(), href: "OEBPS/Text/ch1.xhtml#p1".into() },
+            PageTarget { label: "2".into(), href: "Text/ch2.xhtml".into() },
+        ]);
+    }
+}
@@ -0,0 +1,84 @@
+use percent_encoding::percent_decode_str;
+use std::path::Path;
+
+/// 把书中的相对引用解析为压缩包内的完整路径。
+/// `base` 是引用所在文件的完整路径，外部链接、data URI 和纯锚点返回 `None`。
+pub fn resolve_path(base: &str, href: &str) -> Option<String> {
+    let href = href.trim();
+    let href = href.split(['#', '?']).next().unwrap_or("");
+    if href.is_empty() || is_external(href) {
+        return None;
+    }
+    let path = if href.starts_with('/') {
+        href.to_string()
+    } else {
+        // 引用相对于所在文件的目录
+        let dir = base.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        format!("{}/{}", dir, href)
+    };
+    Some(normalize_path(&path))
+}
+
+/// 压缩包内路径的规范形式：解码 %xx 转义，去掉 `.` 和 `..`。
+/// 清单中的路径原样来自 OPF，和 `resolve_path` 的结果比较前要先规范化。
+pub fn normalize_path(path: &str) -> String {
+    let path = percent_decode_str(path).decode_utf8_lossy();
+    let mut parts: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(segment),
+        }
+    }
+    parts.join("/")
+}
+
+/// 带协议的地址（http:、mailto:、data: 等）不是书内资源
+pub fn is_external(href: &str) -> bool {
+    match href.find(':') {
+        Some(colon) => href[..colon].chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')),
+        None => href.starts_with("//"),
+    }
+}
+
+
+/// 压缩包内路径统一使用 '/' 分隔
+pub fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_relative_paths() {
+        assert_eq!(resolve_path("OEBPS/Text/ch1.xhtml", "ch2.xhtml").as_deref(), Some("OEBPS/Text/ch2.xhtml"));
+        assert_eq!(resolve_path("OEBPS/Text/ch1.xhtml", "../Images/a.png").as_deref(), Some("OEBPS/Images/a.png"));
+        assert_eq!(resolve_path("OEBPS/Text/ch1.xhtml", "./ch2.xhtml#s2").as_deref(), Some("OEBPS/Text/ch2.xhtml"));
+        assert_eq!(resolve_path("OEBPS/Text/ch1.xhtml", "/cover.xhtml").as_deref(), Some("cover.xhtml"));
+        assert_eq!(resolve_path("ch1.xhtml", "Images/a%20b.png?v=1").as_deref(), Some("Images/a b.png"));
+    }
+
+    #[test]
+    fn resolve_skips_external_and_fragments() {
+        assert_eq!(resolve_path("OEBPS/ch1.xhtml", "https://example.com/a.png"), None);
+        assert_eq!(resolve_path("OEBPS/ch1.xhtml", "mailto:someone@example.com"), None);
+        assert_eq!(resolve_path("OEBPS/ch1.xhtml", "data:image/png;base64,AAAA"), None);
+        assert_eq!(resolve_path("OEBPS/ch1.xhtml", "//example.com/a.png"), None);
+        assert_eq!(resolve_path("OEBPS/ch1.xhtml", "#note1"), None);
+        assert_eq!(resolve_path("OEBPS/ch1.xhtml", "  "), None);
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_path("OEBPS/Text/../Text/./chapter%201.xhtml"), "OEBPS/Text/chapter 1.xhtml");
+        assert_eq!(normalize_path("/OEBPS//a.xhtml"), "OEBPS/a.xhtml");
+        assert_eq!(normalize_path("../a.xhtml"), "a.xhtml");
+        assert_eq!(normalize_path("OEBPS/%E7%AC%AC%E4%B8%80.xhtml"), "OEBPS/第一.xhtml");
+    }
+}
@@ -0,0 +1,333 @@
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::sync::LazyLock;
+use regex::Regex;
+
+/// 版式：流式排版或固定版式（`rendition:layout`）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Layout {
+    #[default]
+    Reflowable,
+    PrePaginated,
+}
+
+/// 何时把两页并排显示（`rendition:spread`）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Spread {
+    None,
+    Landscape,
+    Both,
+    #[default]
+    Auto,
+}
+
+/// 翻页方向（书脊的 `page-progression-direction`），未声明时为 Default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PageDirection {
+    #[default]
+    Default,
+    Ltr,
+    Rtl,
+}
+
+/// 书脊项在跨页中的位置（`page-spread-left/right/center`）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageSpread {
+    Left,
+    Right,
+    Center,
+}
+
+/// 单个书脊项的版式属性
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ItemRendition {
+    pub layout: Layout,
+    pub page_spread: Option<PageSpread>,
+}
+
+/// 固定版式页面容器上标记书脊序号的属性，每页的样式限定在 `.epub-content[data-spine="N"]` 内
+pub const SPINE_ATTRIBUTE: &str = "data-spine";
+
+/// 固定版式中显示的一页
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedPage {
+    pub spine_index: usize,
+    pub content: String,
+    pub styles: String,  // 只作用于本页的书籍样式
+    pub viewport: Option<(f64, f64)>,  // 页面的设计尺寸，未声明时按容器宽度显示
+}
+
+/// OPF 中的版式设置，`items` 与书脊一一对应
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rendition {
+    pub layout: Layout,
+    pub spread: Spread,
+    pub direction: PageDirection,
+    pub viewport: Option<(f64, f64)>,  // 已废弃的全书视口，页面自己没有声明时使用
+    pub items: Vec<ItemRendition>,
+    pub comic: bool,  // 由图片压缩包生成的漫画，跨页按页面顺序配对
+}
+
+impl Rendition {
+    pub fn item(&self, spine_index: usize) -> ItemRendition {
+        self.items.get(spine_index).copied().unwrap_or(ItemRendition {
+            layout: self.layout,
+            page_spread: None,
+        })
+    }
+
+    pub fn is_fixed_layout(&self, spine_index: usize) -> bool {
+        self.item(spine_index).layout == Layout::PrePaginated
+    }
+
+    /// 在当前窗口方向下是否并排显示两页
+    pub fn shows_spread(&self, landscape: bool) -> bool {
+        match self.spread {
+            Spread::None => false,
+            Spread::Both => true,
+            Spread::Landscape | Spread::Auto => landscape,
+        }
+    }
+
+    /// 书籍声明的翻页方向是否从右到左
+    pub fn is_rtl(&self) -> bool {
+        self.direction == PageDirection::Rtl
+    }
+
+    /// 与 `spine_index` 同属一个跨页的书脊项，按从左到右排列。
+    /// 只配对显式声明了左右页的相邻固定版式页面；从右到左翻页时右页在前。
+    /// 漫画的第一页（封面）单独显示，之后每两页一个跨页。
+    pub fn spread_pages(&self, spine_index: usize, rtl: bool) -> Vec<usize> {
+        if self.comic {
+            let partner = match spine_index {
+                0 => None,
+                idx if idx % 2 == 1 => Some(idx + 1),
+                idx => Some(idx - 1),
+            };
+            return match partner.filter(|&idx| idx < self.items.len()) {
+                Some(partner) => Self::spread_order(spine_index, partner, rtl),
+                None => vec![spine_index],
+            };
+        }
+        let (leading, trailing) = if rtl {
+            (PageSpread::Right, PageSpread::Left)
+        } else {
+            (PageSpread::Left, PageSpread::Right)
+        };
+        let item = self.item(spine_index);
+        let partner = match item.page_spread {
+            Some(side) if side == leading => Some((spine_index + 1, trailing)),
+            Some(side) if side == trailing => spine_index.checked_sub(1).map(|idx| (idx, leading)),
+            _ => None,
+        };
+        let partner = partner.filter(|(idx, side)| {
+            *idx < self.items.len()
+                && self.is_fixed_layout(*idx)
+                && self.item(*idx).page_spread == Some(*side)
+        });
+
+        match partner {
+            Some((partner, _)) => Self::spread_order(spine_index, partner, rtl),
+            None => vec![spine_index],
+        }
+    }
+
+    // 跨页的两页按从左到右排列
+    fn spread_order(spine_index: usize, partner: usize, rtl: bool) -> Vec<usize> {
+        let (first, second) = (spine_index.min(partner), spine_index.max(partner));
+        if rtl { vec![second, first] } else { vec![first, second] }
+    }
+}
+
+/// 解析 OPF 中全书和每个书脊项的版式属性
+pub(crate) fn parse_rendition(opf: &str) -> Rendition {
+    let mut reader = Reader::from_str(opf);
+    let mut rendition = Rendition::default();
+    let mut item_layouts: Vec<(Option<Layout>, Option<PageSpread>)> = Vec::new();
+    let mut property: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let attr = |name: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == name)
+                        .and_then(|a| a.unescape_value().ok())
+                        .map(|v| v.into_owned())
+                };
+                match e.local_name().as_ref() {
+                    b"meta" => property = attr(b"property"),
+                    b"spine" => {
+                        rendition.direction = match attr(b"page-progression-direction").as_deref() {
+                            Some("rtl") => PageDirection::Rtl,
+                            Some("ltr") => PageDirection::Ltr,
+                            _ => PageDirection::Default,
+                        };
+                        property = None;
+                    }
+                    b"itemref" => {
+                        let mut layout = None;
+                        let mut page_spread = None;
+                        for value in attr(b"properties").unwrap_or_default().split_whitespace() {
+                            match value {
+                                "rendition:layout-pre-paginated" => layout = Some(Layout::PrePaginated),
+                                "rendition:layout-reflowable" => layout = Some(Layout::Reflowable),
+                                "page-spread-left" | "rendition:page-spread-left" => page_spread = Some(PageSpread::Left),
+                                "page-spread-right" | "rendition:page-spread-right" => page_spread = Some(PageSpread::Right),
+                                "rendition:page-spread-center" => page_spread = Some(PageSpread::Center),
+                                _ => {}
+                            }
+                        }
+                        item_layouts.push((layout, page_spread));
+                    }
+                    _ => property = None,
+                }
+            }
+            Ok(Event::Text(t)) => {
+                let Ok(text) = t.unescape() else { continue };
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let Some(name) = property.take() else { continue };
+                match (name.as_str(), text.trim()) {
+                    ("rendition:layout", "pre-paginated") => rendition.layout = Layout::PrePaginated,
+                    ("rendition:layout", _) => rendition.layout = Layout::Reflowable,
+                    ("rendition:spread", "none") => rendition.spread = Spread::None,
+                    ("rendition:spread", "landscape") => rendition.spread = Spread::Landscape,
+                    ("rendition:spread", "both") | ("rendition:spread", "portrait") => rendition.spread = Spread::Both,
+                    ("rendition:spread", _) => rendition.spread = Spread::Auto,
+                    ("rendition:viewport", value) => rendition.viewport = parse_viewport_content(value),
+                    _ => {}
+                }
+            }
+            Ok(Event::End(_)) => property = None,
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    rendition.items = item_layouts.into_iter()
+        .map(|(layout, page_spread)| ItemRendition {
+            layout: layout.unwrap_or(rendition.layout),
+            page_spread,
+        })
+        .collect();
+    rendition
+}
+
+/// 固定版式页面的尺寸：XHTML 取 `<meta name="viewport">`，SVG 取根元素的 viewBox 或宽高
+pub fn page_viewport(html: &str) -> Option<(f64, f64)> {
+    static META_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?is)<meta\b[^>]*\bname\s*=\s*["']viewport["'][^>]*>"#).unwrap());
+    static CONTENT_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\bcontent\s*=\s*["']([^"']*)["']"#).unwrap());
+    if let Some(viewport) = META_REGEX.find(html)
+        .and_then(|meta| CONTENT_REGEX.captures(meta.as_str()))
+        .and_then(|caps| parse_viewport_content(&caps[1]))
+    {
+        return Some(viewport);
+    }
+
+    static SVG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?is)<svg\b[^>]*>"#).unwrap());
+    let svg = SVG_REGEX.find(html)?.as_str();
+    static VIEW_BOX_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\bviewBox\s*=\s*["']\s*[-\d.]+[\s,]+[-\d.]+[\s,]+([\d.]+)[\s,]+([\d.]+)"#).unwrap());
+    if let Some(caps) = VIEW_BOX_REGEX.captures(svg) {
+        return Some((caps[1].parse().ok()?, caps[2].parse().ok()?));
+    }
+    static SIZE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\b(width|height)\s*=\s*["']\s*([\d.]+)(?:px)?\s*["']"#).unwrap());
+    let mut width = None;
+    let mut height = None;
+    for caps in SIZE_REGEX.captures_iter(svg) {
+        let value = caps[2].parse::<f64>().ok();
+        if caps[1].eq_ignore_ascii_case("width") { width = value } else { height = value }
+    }
+    Some((width?, height?))
+}
+
+// `width=1200, height=1600` 形式的视口声明
+fn parse_viewport_content(content: &str) -> Option<(f64, f64)> {
+    let mut width = None;
+    let mut height = None;
+    for part in content.split([',', ';']) {
+        let Some((key, value)) = part.split_once('=') else { continue };
+        let value = value.trim().trim_end_matches("px").parse::<f64>().ok();
+        match key.trim() {
+            "width" => width = value,
+            "height" => height = value,
+            _ => {}
+        }
+    }
+    Some((width?, height?)).filter(|(w, h)| *w > 0.0 && *h > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPF: &str = r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata>
+    <meta property="rendition:layout">pre-paginated</meta>
+    <meta property="rendition:spread">landscape</meta>
+    <meta property="rendition:viewport">width=1200, height=1600</meta>
+  </metadata>
+  <spine page-progression-direction="rtl">
+    <itemref idref="cover" properties="rendition:page-spread-center"/>
+    <itemref idref="p1" properties="page-spread-right"/>
+    <itemref idref="p2" properties="page-spread-left"/>
+    <itemref idref="p3" properties="page-spread-right"/>
+    <itemref idref="notes" properties="rendition:layout-reflowable page-spread-left"/>
+  </spine>
+</package>"#;
+
+    #[test]
+    fn parses_package_rendition() {
+        let rendition = parse_rendition(OPF);
+        assert_eq!(rendition.layout, Layout::PrePaginated);
+        assert_eq!(rendition.spread, Spread::Landscape);
+        assert_eq!(rendition.viewport, Some((1200.0, 1600.0)));
+        assert!(rendition.is_rtl());
+        assert_eq!(rendition.items.len(), 5);
+        assert_eq!(rendition.item(0).page_spread, Some(PageSpread::Center));
+        assert!(rendition.is_fixed_layout(3));
+        assert!(!rendition.is_fixed_layout(4));
+        assert!(rendition.shows_spread(true));
+        assert!(!rendition.shows_spread(false));
+    }
+
+    #[test]
+    fn pairs_spread_pages() {
+        let rendition = parse_rendition(OPF);
+        // 从右到左：右页在先，显示时排在右边
+        assert_eq!(rendition.spread_pages(1, true), [2, 1]);
+        assert_eq!(rendition.spread_pages(2, true), [2, 1]);
+        assert_eq!(rendition.spread_pages(0, true), [0]);
+        // 下一页是流式排版，不配对
+        assert_eq!(rendition.spread_pages(3, true), [3]);
+        // 从左到右时右页 1 的前一页是封面，左页 2 与右页 3 配对
+        assert_eq!(rendition.spread_pages(1, false), [1]);
+        assert_eq!(rendition.spread_pages(2, false), [2, 3]);
+    }
+
+    #[test]
+    fn pairs_comic_pages_after_the_cover() {
+        let rendition = Rendition {
+            layout: Layout::PrePaginated,
+            items: vec![ItemRendition { layout: Layout::PrePaginated, page_spread: None }; 4],
+            comic: true,
+            ..Rendition::default()
+        };
+        assert_eq!(rendition.spread_pages(0, false), [0]);
+        assert_eq!(rendition.spread_pages(1, false), [1, 2]);
+        assert_eq!(rendition.spread_pages(2, true), [2, 1]);
+        assert_eq!(rendition.spread_pages(3, false), [3]);
+    }
+
+    #[test]
+    fn reads_page_viewport() {
+        let html = r#"<html><head><meta content="width=768px; height=1024" name="viewport"/></head><body></body></html>"#;
+        assert_eq!(page_viewport(html), Some((768.0, 1024.0)));
+        assert_eq!(page_viewport(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 600 800" width="100%">"#), Some((600.0, 800.0)));
+        assert_eq!(page_viewport(r#"<svg width="600px" height="800">"#), Some((600.0, 800.0)));
+        assert_eq!(page_viewport(r#"<meta name="viewport" content="width=device-width, initial-scale=1">"#), None);
+        assert_eq!(page_viewport("<p>no viewport</p>"), None);
+    }
+}
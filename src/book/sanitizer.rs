@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+use std::fmt;
+use html5ever::{local_name, namespace_url, ns, QualName};
+use scraper::node::Element;
+use scraper::{ElementRef, Html, Node};
+
+/// 清理章节时删除的一项内容，在诊断信息中列出
+#[derive(Debug, Clone, PartialEq)]
+pub enum Removal {
+    /// 整个元素连同内容，如 `<script>`、`<iframe>`
+    Element(String),
+    /// 不在白名单中的元素，只去掉标签，保留其中的内容
+    Tag(String),
+    /// 事件处理、`javascript:` 地址等属性
+    Attribute { element: String, name: String, value: String },
+}
+
+impl fmt::Display for Removal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Element(name) => write!(f, "删除元素 <{}>", name),
+            Self::Tag(name) => write!(f, "去掉不支持的标签 <{}>，保留内容", name),
+            Self::Attribute { element, name, value } => {
+                write!(f, "删除 <{}> 的属性 {}=\"{}\"", element, name, value)
+            }
+        }
+    }
+}
+
+// 连同内容一起删除的元素：脚本、内嵌页面，以及可以改写链接地址的 SVG 动画
+const DROPPED_ELEMENTS: &[&str] = &[
+    "script", "noscript", "iframe", "frame", "frameset", "embed", "applet", "base",
+    "template", "portal", "set", "animate", "animateMotion", "animateTransform", "handler",
+];
+
+// EPUB 正文中常见的 HTML 元素，style 和 link 由后续步骤提取为阅读器样式
+const HTML_ELEMENTS: &[&str] = &[
+    "html", "head", "title", "meta", "style", "link", "body",
+    "a", "abbr", "address", "area", "article", "aside", "audio", "b", "bdi", "bdo", "big",
+    "blockquote", "br", "caption", "center", "cite", "code", "col", "colgroup", "dd", "del",
+    "details", "dfn", "div", "dl", "dt", "em", "figcaption", "figure", "font", "footer",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "hgroup", "hr", "i", "img", "ins", "kbd",
+    "label", "legend", "li", "main", "map", "mark", "nav", "ol", "p", "picture", "pre", "q",
+    "rb", "rp", "rt", "rtc", "ruby", "s", "samp", "section", "small", "source", "span",
+    "strike", "strong", "sub", "summary", "sup", "table", "tbody", "td", "tfoot", "th",
+    "thead", "time", "tr", "track", "tt", "u", "ul", "var", "video", "wbr",
+];
+
+const SVG_ELEMENTS: &[&str] = &[
+    "svg", "g", "defs", "symbol", "use", "image", "switch", "a", "title", "desc", "metadata",
+    "path", "rect", "circle", "ellipse", "line", "polyline", "polygon", "text", "tspan",
+    "textPath", "linearGradient", "radialGradient", "stop", "clipPath", "mask", "pattern",
+    "marker", "filter", "style", "foreignObject",
+    "feBlend", "feColorMatrix", "feComponentTransfer", "feComposite", "feConvolveMatrix",
+    "feDiffuseLighting", "feDisplacementMap", "feDistantLight", "feFlood", "feFuncA",
+    "feFuncB", "feFuncG", "feFuncR", "feGaussianBlur", "feImage", "feMerge", "feMergeNode",
+    "feMorphology", "feOffset", "fePointLight", "feSpecularLighting", "feSpotLight",
+    "feTile", "feTurbulence",
+];
+
+const MATHML_ELEMENTS: &[&str] = &[
+    "math", "annotation", "maction", "maligngroup", "malignmark", "menclose", "merror",
+    "mfenced", "mfrac", "mglyph", "mi", "mlabeledtr", "mlongdiv", "mmultiscripts", "mn",
+    "mo", "mover", "mpadded", "mphantom", "mprescripts", "mroot", "mrow", "ms", "mscarries",
+    "mscarry", "msgroup", "msline", "mspace", "msqrt", "msrow", "mstack", "mstyle", "msub",
+    "msubsup", "msup", "mtable", "mtd", "mtext", "mtr", "munder", "munderover", "none",
+    "semantics",
+];
+
+// HTML 元素允许的属性。data-*、aria-* 以及 epub:、xml:、xmlns 前缀的属性另外放行
+const HTML_ATTRIBUTES: &[&str] = &[
+    "id", "class", "title", "lang", "dir", "style", "hidden", "role", "translate",
+    "alt", "src", "srcset", "sizes", "width", "height", "href", "hreflang", "name", "rel",
+    "type", "media", "data", "charset", "content",
+    "colspan", "rowspan", "headers", "scope", "span", "align", "valign", "border",
+    "cellpadding", "cellspacing", "summary", "char", "charoff", "frame", "rules", "bgcolor",
+    "color", "face", "size", "nowrap", "clear", "compact", "noshade",
+    "start", "reversed", "value", "cite", "datetime", "open",
+    "controls", "autoplay", "loop", "muted", "preload", "poster",
+    "kind", "srclang", "label", "default", "usemap", "ismap", "shape", "coords",
+];
+
+// 可能带地址的属性，地址为脚本时删除
+const URL_ATTRIBUTES: &[&str] = &[
+    "href", "src", "srcset", "action", "formaction", "data", "poster", "cite",
+    "background", "lowsrc", "dynsrc", "ping", "codebase",
+];
+
+/// 按白名单清理正文：删除脚本、内嵌页面和不支持的元素，
+/// 去掉事件处理属性和 `javascript:` 等脚本地址，返回删除的内容
+pub(crate) fn sanitize(document: &mut Html) -> Vec<Removal> {
+    let mut removals = Vec::new();
+    let mut dropped = HashSet::new();
+    let mut unwrapped = Vec::new();
+    let mut attributes = Vec::new();
+    let mut images = Vec::new();
+
+    for element in document.root_element().descendants().filter_map(ElementRef::wrap) {
+        // 已经整体删除的元素里面的内容不再单独记录
+        if element.ancestors().any(|ancestor| dropped.contains(&ancestor.id())) {
+            continue;
+        }
+        let value = element.value();
+        let name = value.name.local.to_string();
+        match element_policy(value) {
+            Policy::Drop => {
+                dropped.insert(element.id());
+                removals.push(Removal::Element(name));
+                continue;
+            }
+            Policy::Unwrap => {
+                unwrapped.push(element.id());
+                removals.push(Removal::Tag(name));
+                continue;
+            }
+            Policy::Image => images.push(element.id()),
+            Policy::Keep => {}
+        }
+
+        let removed: Vec<QualName> = value.attrs.iter()
+            .filter(|(key, value)| !attribute_allowed(&attribute_name(key), value, &element))
+            .map(|(key, attr_value)| {
+                removals.push(Removal::Attribute {
+                    element: name.clone(),
+                    name: attribute_name(key),
+                    value: attr_value.chars().take(80).collect(),
+                });
+                key.clone()
+            })
+            .collect();
+        if !removed.is_empty() {
+            attributes.push((element.id(), removed));
+        }
+    }
+
+    for (id, removed) in attributes {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            if let Node::Element(element) = node.value() {
+                element.attrs.retain(|(key, _)| !removed.contains(key));
+            }
+        }
+    }
+    for id in dropped {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+    // <object data> 改成 <img src>，替代内容不再需要
+    for id in images {
+        let Some(mut node) = document.tree.get_mut(id) else { continue };
+        if let Node::Element(element) = node.value() {
+            element.name = QualName::new(None, ns!(html), local_name!("img"));
+            for (key, _) in element.attrs.iter_mut() {
+                if key.local == local_name!("data") {
+                    *key = QualName::new(None, ns!(), local_name!("src"));
+                }
+            }
+            element.attrs.retain(|(key, _)| key.local != local_name!("type"));
+            element.attrs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        }
+        let children: Vec<_> = node.tree().get(id)
+            .map(|node| node.children().map(|child| child.id()).collect())
+            .unwrap_or_default();
+        for child in children {
+            if let Some(mut child) = node.tree().get_mut(child) {
+                child.detach();
+            }
+        }
+    }
+    // 子节点依次移到元素前面，再删除元素本身
+    for id in unwrapped {
+        let children: Vec<_> = match document.tree.get(id) {
+            Some(node) => node.children().map(|child| child.id()).collect(),
+            None => continue,
+        };
+        if let Some(mut node) = document.tree.get_mut(id) {
+            for child in children {
+                node.insert_id_before(child);
+            }
+            node.detach();
+        }
+    }
+
+    removals
+}
+
+enum Policy {
+    Keep,
+    Drop,
+    Unwrap,
+    Image,
+}
+
+fn element_policy(element: &Element) -> Policy {
+    let name = &*element.name.local;
+    if DROPPED_ELEMENTS.contains(&name) {
+        return Policy::Drop;
+    }
+    // <object> 按实际内容显示，可能嵌入网页或带脚本的 SVG。
+    // 声明为图片的改成 <img>（图片方式加载的 SVG 不会执行脚本），其余改用它内部的替代内容
+    if name == "object" {
+        let image = element.attr("type").is_some_and(|t| t.trim().starts_with("image/"))
+            && element.attr("data").is_some();
+        return if image { Policy::Image } else { Policy::Unwrap };
+    }
+    let allowed = match element.name.ns {
+        ns!(html) => HTML_ELEMENTS.contains(&name),
+        ns!(svg) => SVG_ELEMENTS.contains(&name),
+        ns!(mathml) => MATHML_ELEMENTS.contains(&name),
+        _ => false,
+    };
+    if allowed { Policy::Keep } else { Policy::Unwrap }
+}
+
+// 带前缀的属性名，如 xlink:href、xml:lang
+fn attribute_name(key: &QualName) -> String {
+    match &key.prefix {
+        Some(prefix) => format!("{}:{}", prefix, key.local),
+        None => key.local.to_string(),
+    }
+}
+
+fn attribute_allowed(name: &str, value: &str, element: &ElementRef) -> bool {
+    let lower = name.to_ascii_lowercase();
+    if lower.starts_with("on") {
+        return false;
+    }
+    let local = lower.rsplit(':').next().unwrap_or(&lower);
+    if URL_ATTRIBUTES.contains(&local) && is_script_url(value) {
+        return false;
+    }
+    // SVG 和 MathML 的表现属性很多，去掉事件和脚本地址后都放行
+    if element.value().name.ns != ns!(html) {
+        return true;
+    }
+    HTML_ATTRIBUTES.contains(&lower.as_str())
+        || lower.starts_with("data-")
+        || lower.starts_with("aria-")
+        || lower.starts_with("epub:")
+        || lower.starts_with("xml:")
+        || lower.starts_with("xmlns")
+}
+
+// javascript:、vbscript: 以及图片以外的 data: 地址，srcset 中的每个候选地址都检查。
+// 浏览器解析地址时忽略空白和控制字符，比较前同样去掉。
+fn is_script_url(value: &str) -> bool {
+    let url = value.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    url.split(',').any(|candidate| {
+        candidate.starts_with("javascript:")
+            || candidate.starts_with("vbscript:")
+            || (candidate.starts_with("data:") && !candidate.starts_with("data:image/"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(html: &str) -> (String, Vec<Removal>) {
+        let mut document = Html::parse_document(html);
+        let removals = sanitize(&mut document);
+        (document.root_element().html(), removals)
+    }
+
+    #[test]
+    fn drops_scripts_and_frames() {
+        let (html, removals) = clean("<p>正文<script>alert(1)</script></p><iframe src=\"a.html\">内嵌</iframe>");
+        assert!(html.contains("<p>正文</p>"));
+        assert!(!html.contains("alert") && !html.contains("内嵌"));
+        assert_eq!(removals, [Removal::Element("script".into()), Removal::Element("iframe".into())]);
+    }
+
+    #[test]
+    fn removes_event_handlers_and_script_urls() {
+        let (html, removals) = clean("<p onclick=\"run()\" class=\"a\"><a href=\" JavaScript:run()\">链接</a><a href=\"ch2.xhtml\">下一章</a></p>");
+        assert!(!html.contains("onclick") && !html.contains("run()"));
+        assert!(html.contains("class=\"a\"") && html.contains("href=\"ch2.xhtml\""));
+        assert_eq!(removals, [
+            Removal::Attribute { element: "p".into(), name: "onclick".into(), value: "run()".into() },
+            Removal::Attribute { element: "a".into(), name: "href".into(), value: " JavaScript:run()".into() },
+        ]);
+    }
+
+    #[test]
+    fn unwraps_unknown_elements() {
+        let (html, removals) = clean("<p><marquee>滚动的字</marquee></p>");
+        assert!(html.contains("<p>滚动的字</p>"));
+        assert_eq!(removals, [Removal::Tag("marquee".into())]);
+    }
+
+    #[test]
+    fn keeps_svg_and_mathml() {
+        let html = "<p><svg xmlns=\"http://www.w3.org/2000/svg\"><rect width=\"1\"></rect></svg><math><mi>x</mi></math></p>";
+        let (cleaned, removals) = clean(html);
+        assert!(removals.is_empty(), "{:?}", removals);
+        assert!(cleaned.contains("<rect") && cleaned.contains("<mi>x</mi>"));
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use regex::Regex;
+use crate::book::convert::{escape_text, xhtml_page, Converted};
+use crate::book::{BookContent, NavPoint};
+
+// 中文数字和全角数字也算章节序号
+const NUMERAL: &str = "[0-9０-９零〇一二两三四五六七八九十百千万]+";
+
+/// TXT 小说的章节标题规则，每条是一个按整行匹配的正则表达式
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterPatterns {
+    pub volumes: Vec<String>,   // 卷、部、篇，在目录中作为章节的上一级
+    pub chapters: Vec<String>,
+}
+
+impl Default for ChapterPatterns {
+    fn default() -> Self {
+        Self {
+            volumes: vec![
+                format!(r"^第{}[卷部集篇](?:[\s:：·.、].*)?$", NUMERAL),
+            ],
+            chapters: vec![
+                format!(r"^第{}[章回节话].*$", NUMERAL),
+                r"^(?i:chapter|chap\.)\s*(?:[0-9]+|[ivxlcdm]+)\b.*$".to_string(),
+                r"^(?:序章|序言|楔子|引子|前言|尾声|后记|终章|番外)(?:[\s:：·.、].*)?$".to_string(),
+            ],
+        }
+    }
+}
+
+// 标题行不会太长，也不会以句子的标点结尾
+const MAX_HEADING_CHARS: usize = 40;
+
+// 整本书找不到章节标题时，按这个字数切分，避免一章过大
+const PART_CHARS: usize = 20_000;
+
+// 转换后章节的样式：段首缩进，标题居中
+const TXT_CSS: &str = "p { text-indent: 2em; margin: 0.4em 0; } h1, h2 { text-align: center; }";
+
+struct Section<'a> {
+    title: Option<String>,
+    volume: bool,
+    lines: Vec<&'a str>,
+}
+
+impl Section<'_> {
+    fn is_empty(&self) -> bool {
+        self.lines.iter().all(|line| line.trim().is_empty())
+    }
+}
+
+/// 把 TXT 小说按卷和章节切分为 XHTML 章节。`title` 为书名，通常取文件名。
+/// 文本中没有任何内容时返回 `None`。
+pub(crate) fn import_txt(text: &str, title: &str, patterns: &ChapterPatterns) -> Option<Converted> {
+    let compile = |patterns: &[String]| -> Vec<Regex> {
+        // 用户配置的规则写错时跳过该条
+        patterns.iter().filter_map(|p| Regex::new(p).ok()).collect()
+    };
+    let volumes = compile(&patterns.volumes);
+    let chapters = compile(&patterns.chapters);
+
+    let mut sections = vec![Section { title: None, volume: false, lines: Vec::new() }];
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let heading = trimmed.chars().count() <= MAX_HEADING_CHARS
+            && !trimmed.ends_with(['。', '！', '？', '…', '，', ',', '"', '”']);
+        if heading && volumes.iter().any(|re| re.is_match(trimmed)) {
+            sections.push(Section { title: Some(trimmed.to_string()), volume: true, lines: Vec::new() });
+        } else if heading && chapters.iter().any(|re| re.is_match(trimmed)) {
+            sections.push(Section { title: Some(trimmed.to_string()), volume: false, lines: Vec::new() });
+        } else if let Some(section) = sections.last_mut() {
+            section.lines.push(line);
+        }
+    }
+
+    // 开头的书名、作者、简介等放在第一页，标题用书名
+    let preamble = sections.remove(0);
+    let author = preamble.lines.iter()
+        .take(20)
+        .find_map(|line| {
+            let line = line.trim();
+            line.strip_prefix("作者").map(|rest| rest.trim_start_matches([':', '：', ' ', '\u{3000}']).trim())
+        })
+        .filter(|author| !author.is_empty())
+        .map(str::to_string);
+    if sections.is_empty() {
+        if preamble.is_empty() {
+            return None;
+        }
+        sections = split_parts(preamble.lines);
+    } else if !preamble.is_empty() {
+        sections.insert(0, Section { title: Some(title.to_string()), volume: false, lines: preamble.lines });
+    }
+
+    let mut spine = Vec::new();
+    let mut manifest = HashMap::new();
+    let mut resources = HashMap::new();
+    let mut toc: Vec<NavPoint> = Vec::new();
+    let mut current_volume: Option<usize> = None;
+    let mut pending_volumes: Vec<usize> = Vec::new();  // 没有正文的卷，指向下一个章节
+
+    for (i, section) in sections.into_iter().enumerate() {
+        let label = section.title.clone().unwrap_or_default();
+        let play_order = i + 1;
+        let mut nav = NavPoint {
+            label: label.clone(),
+            content: PathBuf::new(),
+            children: Vec::new(),
+            play_order,
+        };
+
+        if !(section.volume && section.is_empty()) {
+            let id = format!("c{}", spine.len() + 1);
+            let path = format!("text/{:04}.xhtml", spine.len() + 1);
+            let heading = if section.volume { "h1" } else { "h2" };
+            let mut body = String::new();
+            if !label.is_empty() {
+                body.push_str(&format!("<{0}>{1}</{0}>", heading, escape_text(&label)));
+            }
+            for line in section.lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+                body.push_str(&format!("<p>{}</p>", escape_text(line)));
+            }
+            let page = xhtml_page(if label.is_empty() { title } else { &label }, &body);
+
+            for volume in pending_volumes.drain(..) {
+                toc[volume].content = PathBuf::from(&path);
+            }
+            nav.content = PathBuf::from(&path);
+            resources.insert(path.clone(), (page.into_bytes(), "application/xhtml+xml".to_string()));
+            manifest.insert(id.clone(), (PathBuf::from(path), "application/xhtml+xml".to_string()));
+            spine.push(id);
+        }
+
+        if section.volume {
+            if nav.content.as_os_str().is_empty() {
+                pending_volumes.push(toc.len());
+            }
+            current_volume = Some(toc.len());
+            toc.push(nav);
+        } else {
+            match current_volume {
+                Some(volume) => toc[volume].children.push(nav),
+                None => toc.push(nav),
+            }
+        }
+    }
+    // 末尾没有任何章节的卷不放进目录
+    toc.retain(|nav| !nav.content.as_os_str().is_empty());
+    if spine.is_empty() {
+        return None;
+    }
+
+    let mut metadata = HashMap::new();
+    metadata.insert("title".to_string(), vec![title.to_string()]);
+    if let Some(author) = author {
+        metadata.insert("creator".to_string(), vec![author]);
+    }
+    let mut content = BookContent::from_parts(spine, manifest, toc, metadata);
+    content.extra_css.push(TXT_CSS.to_string());
+    Some(Converted { content, resources })
+}
+
+// 没有章节标题的文本按字数在行尾切分为若干部分
+fn split_parts(lines: Vec<&str>) -> Vec<Section<'_>> {
+    let mut parts: Vec<Section> = Vec::new();
+    let mut chars = 0;
+    for line in lines {
+        if parts.is_empty() || chars >= PART_CHARS {
+            parts.push(Section {
+                title: Some(format!("第 {} 部分", parts.len() + 1)),
+                volume: false,
+                lines: Vec::new(),
+            });
+            chars = 0;
+        }
+        chars += line.chars().count();
+        if let Some(part) = parts.last_mut() {
+            part.lines.push(line);
+        }
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(text: &str) -> Option<Converted> {
+        import_txt(text, "小说", &ChapterPatterns::default())
+    }
+
+    fn labels(toc: &[NavPoint]) -> Vec<&str> {
+        toc.iter().map(|nav| nav.label.as_str()).collect()
+    }
+
+    #[test]
+    fn volumes_and_chapters() {
+        let text = "小说\n作者：某人\n简介\n第一卷 启程\n第一章 出发\n　　天亮了。\n第二章 路上\n走了很久。\n第二卷\n第三章 到达\n到了。\n";
+        let converted = import(text).unwrap();
+        let content = &converted.content;
+        assert_eq!(labels(&content.toc), ["小说", "第一卷 启程", "第二卷"]);
+        assert_eq!(labels(&content.toc[1].children), ["第一章 出发", "第二章 路上"]);
+        assert_eq!(labels(&content.toc[2].children), ["第三章 到达"]);
+        // 没有正文的卷指向它的第一章
+        assert_eq!(content.toc[1].content, content.toc[1].children[0].content);
+        assert_eq!(content.spine.len(), 4);
+
+        let (page, mime) = &converted.resources["text/0002.xhtml"];
+        let page = String::from_utf8_lossy(page);
+        assert_eq!(mime, "application/xhtml+xml");
+        assert!(page.contains("<h2>第一章 出发</h2><p>天亮了。</p>"));
+        assert_eq!(content.metadata.get("creator"), Some(&vec!["某人".to_string()]));
+    }
+
+    #[test]
+    fn sentences_are_not_headings() {
+        let converted = import("第一章 开始\n第一章就这样结束了。\n").unwrap();
+        assert_eq!(labels(&converted.content.toc), ["第一章 开始"]);
+        assert_eq!(converted.content.spine.len(), 1);
+    }
+
+    #[test]
+    fn text_without_headings() {
+        let line = "一".repeat(PART_CHARS / 2);
+        let text = [line.as_str(); 3].join("\n");
+        let converted = import(&text).unwrap();
+        assert_eq!(labels(&converted.content.toc), ["第 1 部分", "第 2 部分"]);
+    }
+
+    #[test]
+    fn empty_text() {
+        assert!(import("").is_none());
+        assert!(import("\n　　\n").is_none());
+    }
+
+    #[test]
+    fn invalid_patterns_are_skipped() {
+        let patterns = ChapterPatterns { volumes: vec!["(".to_string()], chapters: vec![r"^Part \d+$".to_string()] };
+        let converted = import_txt("Part 1\ntext\nPart 2\nmore", "book", &patterns).unwrap();
+        assert_eq!(labels(&converted.content.toc), ["Part 1", "Part 2"]);
+    }
+}
@@ -0,0 +1,114 @@
+use dioxus::prelude::*;
+use fast_epub::book::{BookMetadata, Contributor};
+
+#[derive(Props, PartialEq, Clone)]
+pub struct BookDetailsProps {
+    metadata: BookMetadata,
+    on_close: EventHandler<()>,
+}
+
+// 常见 MARC 角色代码的中文名称
+fn role_name(role: Option<&str>) -> &str {
+    match role {
+        Some("aut") | None => "作者",
+        Some("trl") => "译者",
+        Some("edt") => "编者",
+        Some("ill") => "插图",
+        Some("nrt") => "朗读",
+        Some("aui") => "导言",
+        Some("aft") => "后记",
+        Some("ann") => "注释",
+        Some("com") => "编纂",
+        Some("pbl") => "出版",
+        Some("bkp") => "制作",
+        Some("cov") => "封面设计",
+        Some("pht") => "摄影",
+        Some(other) => other,
+    }
+}
+
+fn contributor_line(contributor: &Contributor) -> String {
+    match contributor.file_as.as_deref() {
+        Some(file_as) if file_as != contributor.name => {
+            format!("{}（{}）", contributor.name, file_as)
+        }
+        _ => contributor.name.clone(),
+    }
+}
+
+#[component]
+fn DetailRow(label: String, value: String) -> Element {
+    rsx! {
+        div { class: "flex py-1",
+            div { class: "w-20 shrink-0 text-gray-500", "{label}" }
+            div { class: "flex-1 break-words", "{value}" }
+        }
+    }
+}
+
+/// 书籍详情：书名、参与者、丛书、出版信息和标识符
+#[component]
+pub fn BookDetails(props: BookDetailsProps) -> Element {
+    let metadata = &props.metadata;
+    let on_close = props.on_close;
+    let people: Vec<(String, String)> = metadata.creators.iter()
+        .chain(metadata.contributors.iter())
+        .map(|c| (role_name(c.role.as_deref()).to_string(), contributor_line(c)))
+        .collect();
+    let series = metadata.series.as_ref().map(|series| match series.index {
+        Some(index) => format!("{} 第 {} 册", series.name, index),
+        None => series.name.clone(),
+    });
+
+    rsx! {
+        // 遮罩层
+        div {
+            class: "fixed inset-0 bg-black bg-opacity-30 flex items-center justify-center z-50",
+            onclick: move |_| on_close.call(()),
+            div {
+                class: "bg-white rounded-lg shadow-lg p-6 w-[28rem] max-h-[80vh] overflow-y-auto text-sm text-gray-800",
+                onclick: move |evt| evt.stop_propagation(),
+                div { class: "flex justify-between items-start mb-4",
+                    h2 { class: "text-xl font-bold",
+                        "{metadata.title.as_deref().unwrap_or(\"未知标题\")}"
+                    }
+                    button {
+                        class: "p-1 text-gray-500 hover:text-gray-800",
+                        onclick: move |_| on_close.call(()),
+                        "✕"
+                    }
+                }
+                for (idx, (role, name)) in people.into_iter().enumerate() {
+                    DetailRow { key: "{idx}", label: role, value: name }
+                }
+                if let Some(series) = series {
+                    DetailRow { label: "丛书", value: series }
+                }
+                if let Some(publisher) = metadata.publisher.clone() {
+                    DetailRow { label: "出版社", value: publisher }
+                }
+                if let Some(date) = metadata.date.clone() {
+                    DetailRow { label: "出版日期", value: date }
+                }
+                if let Some(language) = metadata.language.clone() {
+                    DetailRow { label: "语言", value: language }
+                }
+                if !metadata.subjects.is_empty() {
+                    DetailRow { label: "主题", value: metadata.subjects.join("、") }
+                }
+                for (idx, identifier) in metadata.identifiers.iter().enumerate() {
+                    DetailRow {
+                        key: "id-{idx}",
+                        label: identifier.scheme.clone().unwrap_or_else(|| "标识符".to_string()),
+                        value: identifier.value.clone(),
+                    }
+                }
+                if let Some(description) = metadata.description.clone() {
+                    div { class: "mt-4 pt-3 border-t border-gray-200 text-gray-600 whitespace-pre-line",
+                        "{description}"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::PathBuf;
+use fast_epub::book::{thumbnail, RESOURCE_SCHEME};
+use crate::components::config_dir;
+
+const COVER_DIR: &str = "covers";
+
+fn cover_path(key: &str) -> PathBuf {
+    config_dir().join(COVER_DIR).join(format!("{}.jpg", key))
+}
+
+/// 已缓存的封面缩略图的键，还没有缓存时返回 None
+pub fn cached_cover(key: &str) -> Option<String> {
+    cover_path(key).is_file().then(|| key.to_string())
+}
+
+/// 生成并缓存封面缩略图，返回缓存的键。大图的解码和缩放较慢，放在后台线程中进行。
+pub async fn cache_cover(key: String, data: Vec<u8>) -> Option<String> {
+    async_std::task::spawn_blocking(move || {
+        let path = cover_path(&key);
+        let thumbnail = thumbnail(&data)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).ok()?;
+        }
+        fs::write(&path, thumbnail).ok()?;
+        Some(key)
+    }).await
+}
+
+/// 读取缓存的封面缩略图，键只允许十六进制字符，防止读到缓存目录以外的文件
+pub fn read_cover(key: &str) -> Option<Vec<u8>> {
+    let key = key.strip_suffix(".jpg").unwrap_or(key);
+    if key.is_empty() || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    fs::read(cover_path(key)).ok()
+}
+
+/// 封面缩略图在 webview 中的地址，与书中资源共用同一个自定义协议
+pub fn cover_url(key: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.cover/{}.jpg", RESOURCE_SCHEME, key)
+    } else {
+        format!("{}://cover/{}.jpg", RESOURCE_SCHEME, key)
+    }
+}
@@ -0,0 +1,50 @@
+use dioxus::prelude::*;
+use fast_epub::book::Removal;
+
+#[derive(Props, PartialEq, Clone)]
+pub struct DiagnosticsProps {
+    entries: Vec<(String, Vec<Removal>)>,  // 章节路径及清理时删除的内容
+    on_close: EventHandler<()>,
+}
+
+/// 诊断信息：列出各章节在安全清理时删除的脚本、事件属性等内容
+#[component]
+pub fn Diagnostics(props: DiagnosticsProps) -> Element {
+    let on_close = props.on_close;
+
+    rsx! {
+        // 遮罩层
+        div {
+            class: "fixed inset-0 bg-black bg-opacity-30 flex items-center justify-center z-50",
+            onclick: move |_| on_close.call(()),
+            div {
+                class: "bg-white rounded-lg shadow-lg p-6 w-[36rem] max-h-[80vh] overflow-y-auto text-sm text-gray-800",
+                onclick: move |evt| evt.stop_propagation(),
+                div { class: "flex justify-between items-start mb-2",
+                    h2 { class: "text-xl font-bold", "诊断信息" }
+                    button {
+                        class: "p-1 text-gray-500 hover:text-gray-800",
+                        onclick: move |_| on_close.call(()),
+                        "✕"
+                    }
+                }
+                p { class: "text-gray-500 mb-4",
+                    "为安全起见，正文显示前会删除脚本、事件处理属性和内嵌页面。只包含已经打开过的章节。"
+                }
+                if props.entries.is_empty() {
+                    p { "没有删除任何内容。" }
+                }
+                for (path, removals) in props.entries.iter() {
+                    div { key: "{path}", class: "mb-3",
+                        div { class: "font-medium break-all", "{path}" }
+                        ul { class: "list-disc pl-5 text-gray-600",
+                            for (idx, removal) in removals.iter().enumerate() {
+                                li { key: "{idx}", class: "break-all", "{removal}" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,13 +1,31 @@
 use dioxus::prelude::*;
 use std::path::PathBuf;
 use std::collections::HashMap;
-use crate::components::{TableOfContents, BookMetadata, BookState, load_epub, AppState, process_html_content};
+use crate::components::{TableOfContents, Landmarks, BookState, load_epub, AppState, LoadErrorView, GoToPageDialog, BookDetails, Diagnostics, ReadingDirection, ComicFit, cache_cover, cached_cover};
+use crate::components::{load_overlay, overlay_script, set_overlay_playing, set_overlay_rate, stop_overlay, ReadAlongControls};
+use crate::components::{link_script, open_external_link, scroll_to_anchor, scroll_tracker_script, show_footnote, vertical_wheel_script, LinkClick, ScrollPosition, CONTENT_VIEW_ID};
+use fast_epub::book::{is_vertical_writing, BookError, FixedPage};
 
 #[derive(Props, PartialEq, Clone)]
 pub struct ContentViewProps {
     pub content: String,
+    pub styles: String,
+    pub publisher_styles: bool,  // false 时改用阅读器自己的排版样式
+    pub vertical: bool,  // 竖排，正文横向滚动
+    pub toc_anchors: String,  // 当前章节目录锚点的 JSON
+    pub page_anchors: String,  // 当前章节印刷页码锚点的 JSON
 }
 
+#[derive(Props, PartialEq, Clone)]
+pub struct FixedLayoutViewProps {
+    pub pages: Vec<FixedPage>,
+    pub pane: (f64, f64),  // 内容区域的宽高
+    pub fit_width: bool,  // 按宽度缩放，页面超出高度时上下滚动
+}
+
+// 固定版式页面下方导航按钮占用的高度
+const NAV_BAR_HEIGHT: f64 = 56.0;
+
 #[component]
 pub fn EpubReader() -> Element {
     let mut app_state = use_context::<Signal<AppState>>();
@@ -16,6 +34,10 @@ pub fn EpubReader() -> Element {
     let mut current_chapter = use_context_provider(|| Signal::new(0));
     let mut spine_index = use_signal(|| 0); // 改为use_signal
     let mut chapter_content = use_signal(|| String::new());
+    let mut chapter_styles = use_signal(|| String::new());
+    // 章节内容更新后要滚动到的锚点，scroll_request 每次跳转递增以便同一章内重复定位
+    let mut pending_anchor = use_signal(|| None::<String>);
+    let mut scroll_request = use_signal(|| 0u64);
 
     // 将 goto_chapter 定义为闭包
     let mut goto_chapter = move |new_chapter: usize| {
@@ -23,57 +45,176 @@ pub fn EpubReader() -> Element {
         current_chapter.set(new_chapter);
         
         // 更新spine_index
-        if let Some(idx) = book_state.read().content.get_spine_index(new_chapter) {
+        if let Some(idx) = book_state.read().get_spine_index(new_chapter) {
             spine_index.set(idx);  // 使用set方法更新值
         }
         
-        state.update_progress(current_file.read().to_string(), new_chapter);
-        let content = book_state.write().get_chapter(new_chapter).content;
-        chapter_content.set(content);
+        state.update_progress(current_file.read().to_string(), new_chapter, *spine_index.peek());
+        if let Some(chapter) = book_state.write().get_chapter(new_chapter) {
+            chapter_content.set(chapter.content);
+            chapter_styles.set(chapter.styles);
+        }
+
+        // 多个目录项可能指向同一文件的不同锚点
+        pending_anchor.set(book_state.read().anchor(new_chapter));
+        scroll_request += 1;
     };
 
     // 将 set_chapter_by_spine 定义为闭包
     let mut set_chapter_by_spine = move |idx: usize| {
         spine_index.set(idx);  // 使用set方法更新值
-        let image_cache = book_state.read().image_cache.clone();
+        pending_anchor.set(None);
         let mut st = book_state.write();
-        if let Some(ref mut doc) = st.doc {
-            let spine_id = doc.spine.get(idx).cloned();
-            if let Some(spine_id) = spine_id {
-                if let Some((raw_content, _)) = doc.get_resource(&spine_id) {
-                    let processed = process_html_content(
-                        &String::from_utf8_lossy(&raw_content),
-                        &doc.resources,
-                        &image_cache
-                    );
-                    chapter_content.set(processed);
-                }
-                if let Some(&play_order) = st.content.spine_to_order.get(&idx) {
-                    current_chapter.set(play_order);
-                }
+        if let Some(chapter) = st.get_chapter_by_spine(idx) {
+            chapter_content.set(chapter.content);
+            chapter_styles.set(chapter.styles);
+        }
+        if let Some(play_order) = st.spine_to_order(idx) {
+            current_chapter.set(play_order);
+        }
+        // 漫画等书籍的目录只指向章节开头，按书脊记录具体翻到的页面
+        let chapter = *current_chapter.peek();
+        app_state.write().update_progress(current_file.peek().to_string(), chapter, idx);
+    };
+
+    // 跳到书内地址（完整路径，可带锚点），用于书内链接和地标
+    let mut goto_href = move |href: &str| {
+        let target = book_state.read().locate(href);
+        if let Some((idx, anchor)) = target {
+            if idx != *spine_index.peek() {
+                set_chapter_by_spine(idx);
             }
+            pending_anchor.set(anchor);
+            scroll_request += 1;
         }
     };
 
+    // 正文渲染后定位到锚点或回到顶部
+    use_effect(move || {
+        let _ = chapter_content.read();
+        let _ = scroll_request.read();
+        scroll_to_anchor(pending_anchor.peek().as_deref());
+    });
+
+    // 视口顶部所在的印刷页码，书中没有页码表时为空
+    let mut current_page = use_signal(|| None::<String>);
+    let mut show_page_dialog = use_signal(|| false);
+    let mut show_details = use_signal(|| false);
+    let mut show_diagnostics = use_signal(|| false);
+
+    // 正文滚动经过目录锚点和页码锚点时同步当前目录项、阅读进度和页码
+    use_future(move || async move {
+        let mut eval = document::eval(&scroll_tracker_script());
+        while let Ok(position) = eval.recv::<ScrollPosition>().await {
+            if *current_page.peek() != position.page {
+                current_page.set(position.page);
+            }
+            let Some(play_order) = position.order else { continue };
+            // 忽略切换章节过程中来自上一章的滚动事件
+            let in_current = book_state.read().get_spine_index(play_order) == Some(*spine_index.peek());
+            if in_current && play_order != *current_chapter.peek() {
+                current_chapter.set(play_order);
+                app_state.write().update_progress(current_file.peek().to_string(), play_order, *spine_index.peek());
+            }
+        }
+    });
+
+    // 当前章节的目录锚点和页码锚点，供滚动跟踪脚本使用
+    let toc_anchors = use_memo(move || {
+        let anchors = book_state.read().toc_anchors(*spine_index.read());
+        serde_json::to_string(&anchors).unwrap_or_default()
+    });
+    let page_anchors = use_memo(move || {
+        let anchors = book_state.read().page_anchors(*spine_index.read());
+        serde_json::to_string(&anchors).unwrap_or_default()
+    });
+    let has_pages = use_memo(move || !book_state.read().page_list().is_empty());
+
+    // 拦截正文中的链接：书内链接跳到对应章节和锚点，注释引用弹出注释，外部链接确认后用浏览器打开
+    use_future(move || async move {
+        let mut eval = document::eval(&link_script());
+        while let Ok(click) = eval.recv::<LinkClick>().await {
+            if !click.internal {
+                open_external_link(&click.href);
+                continue;
+            }
+            // 注释引用就地弹出注释，找不到注释内容时按普通链接跳转
+            if click.noteref {
+                let note = book_state.write().footnote(&click.href);
+                if let Some(note) = note {
+                    show_footnote(&note);
+                    continue;
+                }
+            }
+            goto_href(&click.href);
+        }
+    });
+
     let mut loaded_file = use_signal(|| String::new());
-    let mut load_error = use_signal(|| None::<String>);
+    let mut load_error = use_signal(|| None::<BookError>);
     let mut sidebar_width = use_signal(|| 192.0);
     let mut is_resizing = use_signal(|| false);
     let mut preview_width = use_signal(|| 192.0);
     let mut show_preview = use_signal(|| false);
 
+    // 内容区域的尺寸，固定版式按它缩放页面并决定是否并排显示跨页
+    let mut pane_size = use_signal(|| (0.0, 0.0));
+    // 阅读器整体宽度，从右到左时侧边栏在右侧，按它换算拖动位置
+    let mut reader_width = use_signal(|| 0.0);
+    let landscape = use_memo(move || {
+        let (width, height) = *pane_size.read();
+        width > height
+    });
+
+    // 实际使用的翻页方向：用户为这本书指定的优先，否则按书籍声明
+    let rtl = use_memo(move || {
+        let _ = loaded_file.read();
+        match app_state.read().direction(&current_file.read()) {
+            ReadingDirection::Rtl => true,
+            ReadingDirection::Ltr => false,
+            ReadingDirection::Auto => book_state.read().is_rtl(),
+        }
+    });
+    // 书籍样式声明了竖排的章节
+    let vertical = use_memo(move || is_vertical_writing(&chapter_styles.read()));
+
+    // 漫画按菜单中的显示方式排版：双页时并排显示跨页，否则单页
+    let is_comic = use_memo(move || {
+        let _ = loaded_file.read();
+        book_state.read().is_comic()
+    });
+    let comic_fit = use_memo(move || app_state.read().settings.comic_fit);
+
+    let mut fixed_pages = use_signal(Vec::<FixedPage>::new);
+    use_effect(move || {
+        let idx = *spine_index.read();
+        let landscape = if is_comic() {
+            comic_fit() == ComicFit::TwoPage
+        } else {
+            *landscape.read()
+        };
+        let rtl = *rtl.read();
+        let _ = loaded_file.read();
+        let pages = book_state.write().fixed_pages(idx, landscape, rtl);
+        fixed_pages.set(pages);
+    });
+
     // 缓存当前文件路径
     let current = use_memo(move || current_file.read().to_string());
 
+    let sidebar_width_at = move |x: f64| {
+        if *rtl.peek() { *reader_width.peek() - x } else { x }
+    };
+
     let on_mouse_down = move |e: Event<MouseData>| {
         is_resizing.set(true);
         show_preview.set(true);
-        preview_width.set(e.client_coordinates().x as f64);
+        preview_width.set(sidebar_width_at(e.client_coordinates().x));
     };
 
     let on_mouse_move = move |e: Event<MouseData>| {
         if *is_resizing.read() {
-            let new_width = e.client_coordinates().x as f64;
+            let new_width = sidebar_width_at(e.client_coordinates().x);
             if new_width >= 100.0 && new_width <= 400.0 {
                 preview_width.set(new_width);
             }
@@ -95,13 +236,19 @@ pub fn EpubReader() -> Element {
            *loaded_file.read() != file_path) {
             
             let saved_chapter = app_state.read().get_progress(&file_path);
+            let saved_spine = app_state.read().get_spine_progress(&file_path);
+            let settings = app_state.read().settings.clone();
             
-            match load_epub(&file_path) {
-                Ok(_) => {
+            match load_epub(&file_path, &settings) {
+                Ok(state) => {
+                    book_state.set(state);
                     load_error.set(None);
                     loaded_file.set(file_path.clone());
                     
-                    let chapter = saved_chapter.unwrap_or(0);
+                    // 没有阅读记录时从书脊最前面的目录项开始
+                    let chapter = saved_chapter
+                        .or_else(|| book_state.read().start_order())
+                        .unwrap_or(0);
                     
                     // 先设置spine_index为0，防止未初始化状态
                     spine_index.set(0);  // 使用set方法设置初始值
@@ -109,34 +256,50 @@ pub fn EpubReader() -> Element {
                     current_chapter.set(chapter);
                     
                     // 获取内容并更新spine_index
-                    let content = book_state.write().get_chapter(chapter).content;
-                    chapter_content.set(content);
+                    if let Some(loaded) = book_state.write().get_chapter(chapter) {
+                        chapter_content.set(loaded.content);
+                        chapter_styles.set(loaded.styles);
+                    }
+                    pending_anchor.set(book_state.read().anchor(chapter));
                     
                     // 更新spine_index (如果找到对应的索引)
-                    if let Some(idx) = book_state.read().content.get_spine_index(chapter) {
+                    if let Some(idx) = book_state.read().get_spine_index(chapter) {
                         spine_index.set(idx);  // 使用set方法更新值
                     }
+
+                    // 首次打开时跳过封面、扉页，从正文开始的地标处阅读
+                    let start = book_state.read().start_location();
+                    if let (None, Some((idx, anchor))) = (saved_chapter, start) {
+                        set_chapter_by_spine(idx);
+                        pending_anchor.set(anchor);
+                    }
+                    // 回到上次所在的页面，它可能在目录项指向的位置之后
+                    let spine_len = book_state.read().spine_len();
+                    if let Some(idx) = saved_spine.filter(|&idx| idx < spine_len && idx != *spine_index.peek()) {
+                        set_chapter_by_spine(idx);
+                    }
+                    let chapter = *current_chapter.peek();
                     
-                    // 更新书库
-                    let (title, author) = {
-                        let state = book_state.read();
-                        (
-                            state.metadata.title.clone().unwrap_or_else(|| "未知标题".to_string()),
-                            state.metadata.author.clone().unwrap_or_else(|| "未知作者".to_string())
-                        )
+                    // 更新书库，已有的书同时刷新元数据。封面缩略图没有缓存时在后台生成，完成后再更新
+                    let metadata = book_state.read().metadata.clone();
+                    let key = book_state.read().book.as_ref().map(|book| book.identity());
+                    let cover = key.as_deref().and_then(cached_cover);
+                    app_state.write().add_to_library(file_path.clone(), &metadata, cover.clone(), chapter);
+                    let data = match (cover, key) {
+                        (None, Some(key)) => book_state.read().book.as_ref()
+                            .and_then(|book| book.cover())
+                            .map(|(data, _)| (key, data)),
+                        _ => None,
                     };
-                    
-                    let mut state = app_state.write();
-                    if (!state.library.iter().any(|b| b.path == file_path)) {
-                        state.add_to_library(
-                            file_path.clone(),
-                            title,
-                            author,
-                            chapter
-                        );
+                    if let Some((key, data)) = data {
+                        spawn(async move {
+                            if let Some(cover) = cache_cover(key, data).await {
+                                app_state.write().add_to_library(file_path, &metadata, Some(cover), chapter);
+                            }
+                        });
                     }
                 }
-                Err(e) => load_error.set(Some(e.to_string())),
+                Err(e) => load_error.set(Some(e)),
             }
         }
     });
@@ -147,9 +310,13 @@ pub fn EpubReader() -> Element {
     });
 
     // 修改go_next和go_prev以添加更多安全检查
+    // 固定版式的跨页作为一个整体翻页
     let go_next = move |_| {
-        let current = *spine_index.read();
-        let max_spine = book_state.read().content.spine.len();
+        let current = fixed_pages.read().iter()
+            .map(|page| page.spine_index)
+            .max()
+            .unwrap_or(*spine_index.read());
+        let max_spine = book_state.read().spine_len();
         
         if current < max_spine.saturating_sub(1) {
             set_chapter_by_spine(current + 1);
@@ -157,18 +324,65 @@ pub fn EpubReader() -> Element {
     };
 
     let go_prev = move |_| {
-        let current = *spine_index.read();
+        let current = fixed_pages.read().iter()
+            .map(|page| page.spine_index)
+            .min()
+            .unwrap_or(*spine_index.read());
         let new_spine = current.saturating_sub(1);
         if new_spine < current {
             set_chapter_by_spine(new_spine);
         }
     };
 
+    // 媒体叠加朗读：切换章节时载入新章节的片段，正在朗读时接着读
+    let has_overlays = use_memo(move || book_state.read().has_media_overlays());
+    let overlay = use_memo(move || book_state.read().media_overlay(*spine_index.read()));
+    let mut overlay_playing = use_signal(|| false);
+    let overlay_rate = use_signal(|| 1.0);
+    use_effect(move || {
+        let _ = chapter_content.read();
+        load_overlay(overlay.read().as_ref(), *overlay_playing.peek(), *overlay_rate.peek());
+    });
+    use_effect(move || set_overlay_playing(*overlay_playing.read()));
+    use_effect(move || set_overlay_rate(*overlay_rate.read()));
+    // 当前章节读完后翻到下一个书脊项，读到最后一项时停止
+    use_future(move || async move {
+        let mut eval = document::eval(&overlay_script());
+        while let Ok(event) = eval.recv::<String>().await {
+            if event != "ended" || !*overlay_playing.peek() {
+                continue;
+            }
+            let next = *spine_index.peek() + 1;
+            if next < book_state.read().spine_len() {
+                set_chapter_by_spine(next);
+            } else {
+                overlay_playing.set(false);
+            }
+        }
+    });
+    // 切换到书库等界面时阅读器卸载，朗读随之停止
+    use_drop(stop_overlay);
+
+    // 竖排正文用滚轮横向翻动
+    use_future(move || async move {
+        let _ = document::eval(&vertical_wheel_script()).await;
+    });
+
+    // 从右到左阅读时侧边栏、拖动条和导航按钮整体镜像
+    let mirror = if rtl() { "flex-row-reverse" } else { "" };
+    let preview_side = if rtl() { "right" } else { "left" };
+    let preview_border = if rtl() { "border-l-4" } else { "border-r-4" };
+
     rsx! {
         div {
             onmousemove: on_mouse_move,
             onmouseup: on_mouse_up,
-            class: "flex flex-1 overflow-hidden h-[calc(100vh-48px)] relative",
+            onresize: move |evt| {
+                if let Ok(size) = evt.get_content_box_size() {
+                    reader_width.set(size.width);
+                }
+            },
+            class: "flex {mirror} flex-1 overflow-hidden h-[calc(100vh-48px)] relative",
             
             // 拖动时的全屏遮罩层
             if *is_resizing.read() {
@@ -190,7 +404,20 @@ pub fn EpubReader() -> Element {
                     p { class: "text-sm",
                         "作者: {book_state.read().metadata.author.as_deref().unwrap_or(\"未知作者\")}"
                     }
+                    button {
+                        class: "text-xs text-blue-600 hover:underline",
+                        onclick: move |_| show_details.set(true),
+                        "书籍详情"
+                    }
+                    button {
+                        class: "text-xs text-blue-600 hover:underline ml-2",
+                        onclick: move |_| show_diagnostics.set(true),
+                        "诊断信息"
+                    }
                 },
+                Landmarks {
+                    on_select: move |href: String| goto_href(&href)
+                }
                 // 使用新的目录组件
                 TableOfContents {
                     on_select: move |chapter| goto_chapter(chapter)
@@ -207,8 +434,8 @@ pub fn EpubReader() -> Element {
             // 预览层
             if *show_preview.read() {
                 div {
-                    class: "absolute top-0 bottom-0 border-r-4 border-gray-400 pointer-events-none",
-                    style: "left: {preview_width}px; z-index: 9999",
+                    class: "absolute top-0 bottom-0 {preview_border} border-gray-400 pointer-events-none",
+                    style: "{preview_side}: {preview_width}px; z-index: 9999",
                 }
             },
 
@@ -216,39 +443,160 @@ pub fn EpubReader() -> Element {
             div { 
                 class: "flex-1 p-8 overflow-y-auto bg-white text-gray-800 h-full relative",
                 style: "z-index: 1",
+                onresize: move |evt| {
+                    if let Ok(size) = evt.get_content_box_size() {
+                        pane_size.set((size.width, size.height));
+                    }
+                },
                 if let Some(error) = load_error.read().as_ref() {
-                    div { class: "text-red-500", "{error}" }
+                    LoadErrorView { error: error.clone() }
+                } else if !fixed_pages.read().is_empty() {
+                    fixed_layout_view {
+                        pages: fixed_pages.read().clone(),
+                        pane: *pane_size.read(),
+                        fit_width: is_comic() && comic_fit() == ComicFit::FitWidth,
+                    }
                 } else {
                     content_view {
                         content: chapter_content.read().clone(),
+                        styles: chapter_styles.read().clone(),
+                        publisher_styles: app_state.read().settings.publisher_styles,
+                        vertical: vertical(),
+                        toc_anchors: toc_anchors(),
+                        page_anchors: page_anchors(),
                     }
                 }
-                // 导航按钮
-                div { class: "flex justify-center space-x-4",
+                // 导航按钮，从右到左时“下一章”在左侧
+                div { class: "flex {mirror} justify-center gap-4",
                     button {
                         class: "px-4 py-2 bg-gray-300 rounded disabled:opacity-50",
                         disabled: *spine_index.read() == 0,
                         onclick: go_prev,
                         "上一章"
                     }
+                    // 有页码表时显示当前印刷页码，点击跳到指定页
+                    if has_pages() {
+                        button {
+                            class: "px-4 py-2 bg-gray-100 rounded hover:bg-gray-200",
+                            title: "跳到页码",
+                            onclick: move |_| show_page_dialog.set(true),
+                            {match current_page.read().as_deref() {
+                                Some(page) => format!("第 {} 页", page),
+                                None => "页码".to_string(),
+                            }}
+                        }
+                    }
+                    if has_overlays() {
+                        ReadAlongControls { playing: overlay_playing, rate: overlay_rate }
+                    }
                     button {
                         class: "px-4 py-2 bg-gray-300 rounded disabled:opacity-50",
-                        disabled: *spine_index.read() >= book_state.read().content.spine.len().saturating_sub(1),
+                        disabled: *spine_index.read() >= book_state.read().spine_len().saturating_sub(1),
                         onclick: go_next,
                         "下一章"
                     }
                 }
+                if *show_details.read() {
+                    BookDetails {
+                        metadata: book_state.read().metadata.clone(),
+                        on_close: move |_| show_details.set(false),
+                    }
+                }
+                if *show_diagnostics.read() {
+                    Diagnostics {
+                        entries: book_state.read().removals(),
+                        on_close: move |_| show_diagnostics.set(false),
+                    }
+                }
+                if *show_page_dialog.read() {
+                    GoToPageDialog {
+                        current_page: current_page.read().clone(),
+                        on_submit: move |page: String| {
+                            let href = book_state.read().page_href(&page);
+                            match href {
+                                Some(href) => {
+                                    show_page_dialog.set(false);
+                                    goto_href(&href);
+                                }
+                                None => {
+                                    rfd::MessageDialog::new()
+                                        .set_title("跳到页码")
+                                        .set_description(format!("书中没有第 {} 页", page))
+                                        .show();
+                                }
+                            }
+                        },
+                        on_close: move |_| show_page_dialog.set(false),
+                    }
+                }
             }
         }
     }
 }
 #[component]
 pub fn content_view(props: ContentViewProps) -> Element {
+    let style_class = if props.publisher_styles { "" } else { "reading-styles" };
+    let vertical_class = if props.vertical { "vertical" } else { "" };
+
     rsx! {
         div {
-            class: "flex-1 p-8 overflow-y-auto bg-white text-gray-800 h-full relative",
-            dangerous_inner_html: "{props.content}",
-            style: "img {{ max-width: 100%; height: auto; display: block; margin: 1em auto; }}"
+            id: CONTENT_VIEW_ID,
+            "data-toc-anchors": "{props.toc_anchors}",
+            "data-page-anchors": "{props.page_anchors}",
+            class: "epub-content {style_class} {vertical_class} flex-1 p-8 overflow-y-auto bg-white text-gray-800 h-full relative",
+            // 书籍样式已限定在 .epub-content 内，不会影响界面其他部分
+            if props.publisher_styles {
+                style { "{props.styles}" }
+            }
+            div {
+                dangerous_inner_html: "{props.content}",
+            }
+        }
+    }
+}
+
+/// 固定版式页面按设计尺寸排版后整体缩放到内容区域，跨页时两页并排
+#[component]
+pub fn fixed_layout_view(props: FixedLayoutViewProps) -> Element {
+    let (pane_width, pane_height) = props.pane;
+    let pane_height = (pane_height - NAV_BAR_HEIGHT).max(1.0);
+    // 没有声明尺寸的页面占满各自那一份区域
+    let fallback = (pane_width / props.pages.len().max(1) as f64, pane_height);
+    let sizes: Vec<(f64, f64)> = props.pages.iter()
+        .map(|page| page.viewport.unwrap_or(fallback))
+        .collect();
+    let total_width: f64 = sizes.iter().map(|(width, _)| width).sum();
+    let max_height = sizes.iter().map(|(_, height)| *height).fold(0.0, f64::max);
+    let scale = if props.fit_width && total_width > 0.0 {
+        pane_width / total_width
+    } else if total_width > 0.0 && max_height > 0.0 {
+        (pane_width / total_width).min(pane_height / max_height)
+    } else {
+        1.0
+    };
+
+    rsx! {
+        div {
+            id: CONTENT_VIEW_ID,
+            class: "flex justify-center items-start mb-4",
+            for (page, (width, height)) in props.pages.iter().zip(sizes) {
+                div {
+                    key: "{page.spine_index}",
+                    class: "overflow-hidden shadow",
+                    style: "width: {width * scale}px; height: {height * scale}px",
+                    div {
+                        class: "epub-content fixed-page",
+                        // 与 SPINE_ATTRIBUTE 对应，本页的样式只匹配这个容器
+                        "data-spine": "{page.spine_index}",
+                        style: "width: {width}px; height: {height}px; transform: scale({scale}); transform-origin: 0 0",
+                        // 固定版式依赖书籍自带的样式定位，始终使用出版社样式
+                        style { "{page.styles}" }
+                        div {
+                            dangerous_inner_html: "{page.content}",
+                        }
+                    }
+                }
+            }
         }
     }
 }
@@ -0,0 +1,71 @@
+use dioxus::prelude::*;
+use crate::components::epub_loader::BookState;
+
+#[derive(Props, PartialEq, Clone)]
+pub struct LandmarksProps {
+    on_select: EventHandler<String>,  // 地标指向的书内地址
+}
+
+// 没有标签的地标按类型显示
+fn landmark_name(kind: &str) -> &str {
+    match kind {
+        "cover" => "封面",
+        "titlepage" => "扉页",
+        "toc" => "目录",
+        "bodymatter" => "正文开始",
+        "frontmatter" => "前言",
+        "backmatter" => "附录",
+        "copyright-page" => "版权页",
+        "preface" => "序",
+        "index" => "索引",
+        "bibliography" => "参考文献",
+        "glossary" => "术语表",
+        "loi" => "插图列表",
+        "lot" => "表格列表",
+        other => other,
+    }
+}
+
+/// 导航文档中的地标菜单（封面、目录、正文开始等），显示在目录上方
+#[component]
+pub fn Landmarks(props: LandmarksProps) -> Element {
+    let book_state = use_context::<Signal<BookState>>();
+    let mut expanded = use_signal(|| false);
+    let landmarks = use_memo(move || {
+        book_state.read().landmarks().into_iter()
+            .map(|landmark| {
+                let label = if landmark.label.is_empty() {
+                    landmark_name(&landmark.kind).to_string()
+                } else {
+                    landmark.label
+                };
+                (label, landmark.href)
+            })
+            .collect::<Vec<_>>()
+    });
+
+    if landmarks.read().is_empty() {
+        return rsx! {};
+    }
+
+    rsx! {
+        div {
+            class: "flex flex-col px-2 pt-2 pb-1 border-b border-gray-300 select-none",
+            button {
+                class: "text-left text-sm font-semibold text-gray-600 hover:text-gray-900 focus:outline-none",
+                onclick: move |_| expanded.toggle(),
+                if *expanded.read() { "▼ 地标" } else { "▶ 地标" }
+            }
+            if *expanded.read() {
+                for (idx, (label, href)) in landmarks.read().iter().cloned().enumerate() {
+                    div {
+                        key: "{idx}",
+                        class: "cursor-pointer text-left py-1 pl-5 text-gray-700 hover:text-gray-900 hover:bg-gray-300 rounded",
+                        onclick: move |_| props.on_select.call(href.clone()),
+                        "{label}"
+                    }
+                }
+            }
+        }
+    }
+}
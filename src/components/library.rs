@@ -1,5 +1,5 @@
 use dioxus::prelude::*;
-use crate::components::{AppState, BookInfo};
+use crate::components::{cover_url, AppState, BookInfo};
 use std::cmp::PartialEq;
 use std::path::PathBuf;
 
@@ -31,12 +31,20 @@ fn BookCard(
             div {
                 class: "absolute left-0 top-0 bottom-0 w-1 bg-blue-500 rounded-l-lg"
             }
-            // 封面区域
+            // 封面区域，没有缓存的封面时显示占位图标
             div {
-                class: "h-32 bg-gray-100 dark:bg-gray-700 rounded-t-lg flex items-center justify-center",
-                span { 
-                    class: "text-4xl text-gray-400 dark:text-gray-500",
-                    "📚" 
+                class: "h-32 bg-gray-100 dark:bg-gray-700 rounded-t-lg flex items-center justify-center overflow-hidden",
+                if let Some(cover) = book.cover.as_ref() {
+                    img {
+                        class: "h-full w-full object-cover",
+                        src: "{cover_url(cover)}",
+                        alt: "{book.title}",
+                    }
+                } else {
+                    span { 
+                        class: "text-4xl text-gray-400 dark:text-gray-500",
+                        "📚" 
+                    }
                 }
             }
             // 信息区域
@@ -52,6 +60,16 @@ fn BookCard(
                     title: "{book.author}",
                     "{book.author}" 
                 }
+                if let Some(series) = book.series.as_ref() {
+                    div {
+                        class: "text-gray-500 dark:text-gray-400 text-[10px] truncate",
+                        title: "{series}",
+                        {match book.series_index {
+                            Some(index) => format!("{} #{}", series, index),
+                            None => series.clone(),
+                        }}
+                    }
+                }
                 div { 
                     class: "mt-auto pt-1 space-y-0.5 text-[10px]",
                     div {
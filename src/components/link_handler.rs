@@ -0,0 +1,206 @@
+use dioxus::prelude::*;
+use rfd::{MessageButtons, MessageDialog, MessageDialogResult};
+use serde::Deserialize;
+use fast_epub::book::{LINK_ATTRIBUTE, NOTEREF_ATTRIBUTE};
+
+/// 正文容器的元素 id
+pub const CONTENT_VIEW_ID: &str = "epub-content";
+
+/// 注释弹窗的元素 id
+const FOOTNOTE_POPOVER_ID: &str = "epub-footnote";
+
+/// 正文中被点击的链接
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkClick {
+    pub href: String,
+    pub internal: bool,
+    #[serde(default)]
+    pub noteref: bool,  // 注释引用，应弹出注释而不是跳转
+}
+
+// 脚本注册在文档上的监听器都挂在 `window.<key>` 保存的 AbortController 上。
+// 阅读器每次重新挂载都会再执行一遍脚本，先取消上一次注册的监听器，避免重复触发
+fn replace_listeners(key: &str) -> String {
+    format!(r#"
+        window.{key}?.abort();
+        window.{key} = new AbortController();
+        const signal = window.{key}.signal;
+    "#)
+}
+
+/// 在文档上委托监听正文里的链接点击，阻止 webview 默认导航，改为发给 Rust 处理
+pub fn link_script() -> String {
+    format!(r#"
+        {guard}
+        document.addEventListener('click', (event) => {{
+            // 点击注释弹窗以外的地方时关闭弹窗
+            const popover = document.getElementById('{popover}');
+            if (popover && !popover.contains(event.target)) popover.remove();
+
+            const link = event.target.closest('.epub-content a[href]');
+            if (!link) return;
+            event.preventDefault();
+            const internal = link.getAttribute('{attr}');
+            const noteref = link.hasAttribute('{noteref}');
+            if (noteref) window.__epubNoteref = link;
+            if (internal !== null) {{
+                dioxus.send({{ href: internal, internal: true, noteref }});
+            }} else if (/^(https?|mailto):/i.test(link.getAttribute('href'))) {{
+                dioxus.send({{ href: link.href, internal: false }});
+            }}
+        }}, {{ capture: true, signal }});
+        document.addEventListener('keydown', (event) => {{
+            if (event.key === 'Escape') document.getElementById('{popover}')?.remove();
+        }}, {{ signal }});
+        await new Promise(() => {{}});
+    "#,
+        guard = replace_listeners("__fastEpubLinks"),
+        attr = LINK_ATTRIBUTE,
+        noteref = NOTEREF_ATTRIBUTE,
+        popover = FOOTNOTE_POPOVER_ID,
+    )
+}
+
+/// 在最近点击的注释引用旁弹出注释内容。
+/// 弹窗带 epub-content 类，书籍样式和链接拦截对其中内容同样生效。
+pub fn show_footnote(html: &str) {
+    let html = serde_json::to_string(html).unwrap_or_else(|_| "\"\"".to_string());
+    document::eval(&format!(r#"
+        document.getElementById('{popover}')?.remove();
+        const link = window.__epubNoteref;
+        if (link && document.body.contains(link)) {{
+            const popover = document.createElement('div');
+            popover.id = '{popover}';
+            popover.className = 'epub-content epub-footnote';
+            popover.innerHTML = {html};
+            document.body.appendChild(popover);
+
+            // 默认显示在引用下方，空间不够时放到上方，并保持在窗口内
+            const rect = link.getBoundingClientRect();
+            const width = popover.offsetWidth;
+            const height = popover.offsetHeight;
+            const left = Math.max(8, Math.min(rect.left, window.innerWidth - width - 8));
+            const below = rect.bottom + 6;
+            const top = below + height > window.innerHeight - 8
+                ? Math.max(8, rect.top - height - 6)
+                : below;
+            popover.style.left = left + 'px';
+            popover.style.top = top + 'px';
+        }}
+    "#, popover = FOOTNOTE_POPOVER_ID));
+}
+
+/// 正文滚动时视口顶部所在的目录项和印刷页码
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScrollPosition {
+    pub order: Option<usize>,
+    pub page: Option<String>,
+}
+
+/// 正文滚动时找出视口顶部所在的目录项和印刷页码并发给 Rust。
+/// 当前章节的目录锚点和页码锚点由阅读器写在正文容器的
+/// data-toc-anchors、data-page-anchors 属性里，锚点为空表示章节开头。
+pub fn scroll_tracker_script() -> String {
+    format!(r#"
+        {guard}
+        let current = null;
+        const active = (view, anchors) => {{
+            // 竖排时按行进方向（vertical-rl 从右往左）计算锚点离视口起始边的距离
+            const mode = getComputedStyle(view).writingMode;
+            const viewRect = view.getBoundingClientRect();
+            const offset = rect => mode === 'vertical-rl' ? viewRect.right - rect.right
+                : mode === 'vertical-lr' ? rect.left - viewRect.left
+                : rect.top - viewRect.top;
+            let found = anchors.length > 0 ? anchors[0][0] : null;
+            let foundTop = -Infinity;
+            for (const [key, anchor] of anchors) {{
+                if (!anchor) continue;
+                const target = document.getElementById(anchor);
+                if (!target || !view.contains(target)) continue;
+                const top = offset(target.getBoundingClientRect());
+                if (top <= 16 && top >= foundTop) {{
+                    found = key;
+                    foundTop = top;
+                }}
+            }}
+            return found;
+        }};
+        const update = () => {{
+            const view = document.getElementById('{view}');
+            if (!view) return;
+            const position = {{
+                order: active(view, JSON.parse(view.dataset.tocAnchors || '[]')),
+                page: active(view, JSON.parse(view.dataset.pageAnchors || '[]')),
+            }};
+            const key = JSON.stringify(position);
+            if (key !== current) {{
+                current = key;
+                dioxus.send(position);
+            }}
+        }};
+        document.addEventListener('scroll', update, {{ capture: true, signal }});
+        // 切换章节后锚点属性会变化，不滚动也要更新
+        const observer = new MutationObserver(update);
+        observer.observe(document.body, {{
+            subtree: true,
+            attributes: true,
+            attributeFilter: ['data-toc-anchors', 'data-page-anchors'],
+        }});
+        signal.addEventListener('abort', () => observer.disconnect());
+        await new Promise(() => {{}});
+    "#, guard = replace_listeners("__fastEpubScroll"), view = CONTENT_VIEW_ID)
+}
+
+/// 竖排正文横向滚动，把鼠标滚轮的纵向滚动转成沿行进方向的横向滚动
+pub fn vertical_wheel_script() -> String {
+    format!(r#"
+        {guard}
+        document.addEventListener('wheel', event => {{
+            const view = document.getElementById('{view}');
+            if (!view || !view.contains(event.target) || event.deltaX !== 0) return;
+            const mode = getComputedStyle(view).writingMode;
+            if (!mode.startsWith('vertical')) return;
+            event.preventDefault();
+            view.scrollLeft += mode === 'vertical-rl' ? -event.deltaY : event.deltaY;
+        }}, {{ passive: false, signal }});
+        await new Promise(() => {{}});
+    "#, guard = replace_listeners("__fastEpubWheel"), view = CONTENT_VIEW_ID)
+}
+
+/// 滚动到正文中的锚点，没有锚点时回到顶部
+pub fn scroll_to_anchor(anchor: Option<&str>) {
+    let anchor = serde_json::to_string(&anchor).unwrap_or_else(|_| "null".to_string());
+    // 等 DOM 更新完成后再定位
+    document::eval(&format!(r#"
+        requestAnimationFrame(() => {{
+            const view = document.getElementById('{view}');
+            if (!view) return;
+            const anchor = {anchor};
+            const target = anchor && (document.getElementById(anchor) || document.getElementsByName(anchor)[0]);
+            if (target && view.contains(target)) {{
+                target.scrollIntoView();
+            }} else {{
+                // 竖排时 scrollLeft 为 0 即是行首
+                view.scrollTop = 0;
+                view.scrollLeft = 0;
+            }}
+        }});
+    "#, view = CONTENT_VIEW_ID));
+}
+
+/// 确认后在系统浏览器中打开外部链接
+pub fn open_external_link(url: &str) {
+    let confirmed = MessageDialog::new()
+        .set_title("打开外部链接")
+        .set_description(format!("是否在浏览器中打开以下链接？\n\n{}", url))
+        .set_buttons(MessageButtons::YesNo)
+        .show();
+    if confirmed == MessageDialogResult::Yes {
+        if let Err(e) = webbrowser::open(url) {
+            MessageDialog::new()
+                .set_title("打开外部链接")
+                .set_description(format!("无法打开链接：{}", e))
+                .show();
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use dioxus::prelude::*;
+use fast_epub::book::BookError;
+
+// 每种错误对应的图标、标题和处理建议
+fn describe(error: &BookError) -> (&'static str, &'static str, &'static str) {
+    match error {
+        BookError::NotFound(_) => (
+            "🔍", "找不到文件",
+            "文件可能已被移动、重命名或删除，请重新打开。",
+        ),
+        BookError::NotZip(_) => (
+            "📦", "不是有效的 EPUB 文件",
+            "EPUB 本质上是 zip 压缩包，这个文件可能已损坏或下载不完整。",
+        ),
+        BookError::MissingContainer => (
+            "🗂️", "缺少 container.xml",
+            "压缩包中没有 META-INF/container.xml，无法找到书籍入口。",
+        ),
+        BookError::MissingPackage(_) => (
+            "📄", "找不到 OPF 文件",
+            "container.xml 指向的书籍描述文件不存在，文件可能已损坏。",
+        ),
+        BookError::MalformedPackage(_) => (
+            "⚠️", "书籍描述文件格式错误",
+            "OPF 文件无法解析，可以尝试用 Calibre 等工具修复后再打开。",
+        ),
+        BookError::Encrypted => (
+            "🔒", "书籍受 DRM 保护",
+            "请使用购买平台的官方阅读器打开这本书。",
+        ),
+        BookError::UnsupportedMedia(_) => (
+            "🚫", "不支持的内容格式",
+            "这本书的正文不是 XHTML，暂时无法显示。",
+        ),
+        BookError::EmptyText => (
+            "📝", "文本文件是空的",
+            "这个 TXT 文件中没有可以显示的文字。",
+        ),
+        BookError::MalformedMobi(_) => (
+            "📕", "MOBI 文件格式错误",
+            "文件可能已损坏，或者是不支持的 Kindle 格式（如 KFX）。",
+        ),
+        BookError::MalformedFb2(_) => (
+            "📗", "FB2 文件格式错误",
+            "文件不是完整的 FictionBook 文档，可能已损坏或下载不完整。",
+        ),
+        BookError::NoImages => (
+            "🖼️", "漫画中没有图片",
+            "CBZ 压缩包中没有找到 JPEG、PNG 等格式的图片。",
+        ),
+        BookError::Io(_) => (
+            "💾", "读取文件失败",
+            "请检查文件权限或磁盘状态后重试。",
+        ),
+    }
+}
+
+#[component]
+pub fn LoadErrorView(error: BookError) -> Element {
+    let (icon, title, hint) = describe(&error);
+
+    rsx! {
+        div {
+            class: "flex flex-col items-center justify-center h-full text-center text-gray-600 select-text",
+            span { class: "text-6xl mb-4", "{icon}" }
+            h2 { class: "text-xl font-bold text-red-500 mb-2", "{title}" }
+            p { class: "text-sm mb-4", "{hint}" }
+            p { class: "text-xs text-gray-400 break-all max-w-lg", "{error}" }
+        }
+    }
+}
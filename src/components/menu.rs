@@ -1,11 +1,11 @@
 use dioxus::prelude::*;
 use rfd::FileDialog;
-use crate::components::AppState;
+use crate::components::{AppState, ReadingDirection};
 
 #[component]
 pub fn MenuButton(
     show_library: Signal<bool>,
-    app_state: Signal<AppState>,
+    mut app_state: Signal<AppState>,
     current_file: Signal<String>,
 ) -> Element {
     let mut dropdown_open = use_signal(|| false);
@@ -18,7 +18,12 @@ pub fn MenuButton(
 
     let open_file = move |_| {
         if let Some(file) = FileDialog::new()
+            .add_filter("电子书", &["epub", "txt", "mobi", "azw3", "azw", "fb2", "fb2.zip", "cbz"])
             .add_filter("EPUB", &["epub"])
+            .add_filter("TXT 小说", &["txt"])
+            .add_filter("Kindle", &["mobi", "azw3", "azw"])
+            .add_filter("FictionBook", &["fb2", "fb2.zip"])
+            .add_filter("CBZ 漫画", &["cbz"])
             .set_directory("/")
             .pick_file() 
         {
@@ -37,6 +42,35 @@ pub fn MenuButton(
         dropdown_open.set(false);
     };
 
+    let toggle_publisher_styles = move |_| {
+        let mut state = app_state.write();
+        state.settings.publisher_styles = !state.settings.publisher_styles;
+        let _ = state.save();
+    };
+
+    // 漫画的显示方式，依次切换
+    let toggle_comic_fit = move |_| {
+        let mut state = app_state.write();
+        state.settings.comic_fit = state.settings.comic_fit.next();
+        let _ = state.save();
+    };
+    let comic_fit_label = app_state.read().settings.comic_fit.label();
+
+    // 当前书的翻页方向，依次切换
+    let toggle_direction = move |_| {
+        let path = current_file.read().to_string();
+        if path.is_empty() {
+            return;
+        }
+        let mut state = app_state.write();
+        let direction = state.direction(&path).next();
+        state.set_direction(path, direction);
+    };
+    let direction_label = {
+        let path = current_file.read();
+        ReadingDirection::label(app_state.read().direction(&path))
+    };
+
     rsx! {
         div {
             class: "relative",
@@ -95,6 +129,29 @@ pub fn MenuButton(
                                 span { class: "mr-2", "🎨" }
                                 "切换主题"
                             }
+                            button {
+                                class: "w-full text-left px-4 py-2 text-gray-800 hover:bg-gray-100 rounded-lg flex items-center",
+                                onclick: toggle_publisher_styles,
+                                span { class: "mr-2", "🖋️" }
+                                {if app_state.read().settings.publisher_styles {
+                                    "使用出版社样式 ✓"
+                                } else {
+                                    "使用出版社样式"
+                                }}
+                            }
+                            button {
+                                class: "w-full text-left px-4 py-2 text-gray-800 hover:bg-gray-100 rounded-lg flex items-center disabled:opacity-50",
+                                disabled: current_file.read().is_empty(),
+                                onclick: toggle_direction,
+                                span { class: "mr-2", "↔️" }
+                                "翻页方向：{direction_label}"
+                            }
+                            button {
+                                class: "w-full text-left px-4 py-2 text-gray-800 hover:bg-gray-100 rounded-lg flex items-center",
+                                onclick: toggle_comic_fit,
+                                span { class: "mr-2", "🖼️" }
+                                "漫画显示：{comic_fit_label}"
+                            }
                             button {
                                 class: "w-full text-left px-4 py-2 text-gray-800 hover:bg-gray-100 rounded-lg flex items-center",
                                 onclick: |evt| evt.stop_propagation(),
@@ -2,16 +2,33 @@ mod header;
 mod menu;
 mod epub_reader;
 mod toc;
+mod landmarks;
 mod epub_loader;
-mod html_processor;
 mod storage;
 mod library;
+mod link_handler;
+mod load_error;
+mod page_dialog;
+mod book_details;
+mod diagnostics;
+mod resource_protocol;
+mod covers;
+mod read_along;
 
 pub use header::Header;
 pub use epub_reader::{EpubReader}; // 更新导出
 pub(crate) use menu::MenuButton;
 pub(crate) use toc::TableOfContents;
-pub(crate) use epub_loader::{BookState, Chapter, BookMetadata, load_epub};
-pub(crate) use html_processor::process_html_content;
-pub(crate) use storage::{AppState, BookInfo};
+pub(crate) use landmarks::Landmarks;
+pub(crate) use epub_loader::{BookState, load_epub};
+pub(crate) use storage::{config_dir, AppState, BookInfo, ComicFit, ReaderSettings, ReadingDirection};
 pub(crate) use library::Library;
+pub(crate) use load_error::LoadErrorView;
+pub(crate) use page_dialog::GoToPageDialog;
+pub(crate) use book_details::BookDetails;
+pub(crate) use diagnostics::Diagnostics;
+pub(crate) use link_handler::{link_script, open_external_link, scroll_to_anchor, scroll_tracker_script, show_footnote, vertical_wheel_script, LinkClick, ScrollPosition, CONTENT_VIEW_ID};
+pub use resource_protocol::handle_resource_request;
+pub(crate) use resource_protocol::set_current_book;
+pub(crate) use covers::{cache_cover, cached_cover, cover_url, read_cover};
+pub(crate) use read_along::{load_overlay, overlay_script, set_overlay_playing, set_overlay_rate, stop_overlay, ReadAlongControls};
@@ -0,0 +1,59 @@
+use dioxus::prelude::*;
+
+#[derive(Props, PartialEq, Clone)]
+pub struct GoToPageDialogProps {
+    current_page: Option<String>,
+    on_submit: EventHandler<String>,  // 输入的页码
+    on_close: EventHandler<()>,
+}
+
+/// “跳到第 N 页”对话框，页码按书中登记的印刷页码匹配
+#[component]
+pub fn GoToPageDialog(props: GoToPageDialogProps) -> Element {
+    let mut input = use_signal(|| props.current_page.clone().unwrap_or_default());
+
+    let submit = move || {
+        let page = input.read().trim().to_string();
+        if !page.is_empty() {
+            props.on_submit.call(page);
+        }
+    };
+
+    rsx! {
+        // 遮罩层
+        div {
+            class: "fixed inset-0 bg-black bg-opacity-30 flex items-center justify-center z-50",
+            onclick: move |_| props.on_close.call(()),
+            div {
+                class: "bg-white rounded-lg shadow-lg p-4 w-64",
+                onclick: move |evt| evt.stop_propagation(),
+                h2 { class: "text-lg font-bold mb-3", "跳到页码" }
+                input {
+                    class: "w-full border border-gray-300 rounded px-2 py-1 mb-3",
+                    r#type: "text",
+                    autofocus: true,
+                    placeholder: "印刷版页码",
+                    value: "{input}",
+                    oninput: move |evt| input.set(evt.value()),
+                    onkeydown: move |evt| match evt.key() {
+                        Key::Enter => submit(),
+                        Key::Escape => props.on_close.call(()),
+                        _ => {}
+                    },
+                }
+                div { class: "flex justify-end space-x-2",
+                    button {
+                        class: "px-3 py-1 bg-gray-200 rounded hover:bg-gray-300",
+                        onclick: move |_| props.on_close.call(()),
+                        "取消"
+                    }
+                    button {
+                        class: "px-3 py-1 bg-blue-600 text-white rounded hover:bg-blue-700",
+                        onclick: move |_| submit(),
+                        "跳转"
+                    }
+                }
+            }
+        }
+    }
+}
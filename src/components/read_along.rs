@@ -0,0 +1,183 @@
+use dioxus::prelude::*;
+use fast_epub::book::{resource_url, MediaOverlay};
+use crate::components::CONTENT_VIEW_ID;
+
+/// 朗读速度选项
+const RATES: &[f64] = &[0.75, 1.0, 1.25, 1.5, 2.0];
+
+/// 朗读播放器脚本。播放器挂在 `window.__epubOverlay` 上，由 `load_overlay` 等函数控制；
+/// 当前章节的片段读完时发送 "ended"，由阅读器翻到下一个书脊项。
+/// 阅读器重新挂载时先停掉上一个播放器，避免旧的音频继续播放。
+pub fn overlay_script() -> String {
+    format!(r#"
+        window.__epubOverlay?.stop();
+        const audio = new Audio();
+        let frame = null;
+        const player = {{
+            clips: [],
+            index: 0,
+            activeClass: '',
+            playing: false,
+            active: null,
+            load(clips, activeClass, playing, rate) {{
+                audio.pause();
+                this.highlight(null);
+                this.clips = clips;
+                this.index = 0;
+                this.activeClass = activeClass;
+                this.setRate(rate);
+                this.playing = playing;
+                // 等新章节的正文渲染完再开始
+                if (playing) requestAnimationFrame(() => this.start());
+            }},
+            start() {{
+                const clip = this.clips[this.index];
+                if (!clip) {{
+                    this.highlight(null);
+                    dioxus.send('ended');
+                    return;
+                }}
+                this.highlight(clip.fragment);
+                if (audio.src !== clip.audio) audio.src = clip.audio;
+                const seek = () => {{
+                    // 同一音频中连续的片段不重新定位，避免断音
+                    if (Math.abs(audio.currentTime - clip.begin) > 0.25) audio.currentTime = clip.begin;
+                    if (this.playing) audio.play();
+                }};
+                if (audio.readyState >= 1) seek(); else audio.addEventListener('loadedmetadata', seek, {{ once: true }});
+                this.watch();
+            }},
+            next() {{
+                this.index += 1;
+                this.start();
+            }},
+            // 按帧检查播放位置，比 timeupdate 事件更及时地切换高亮
+            watch() {{
+                cancelAnimationFrame(frame);
+                const tick = () => {{
+                    const clip = this.clips[this.index];
+                    if (!this.playing || !clip) return;
+                    if (clip.end !== null && audio.currentTime >= clip.end) {{
+                        this.next();
+                        return;
+                    }}
+                    frame = requestAnimationFrame(tick);
+                }};
+                frame = requestAnimationFrame(tick);
+            }},
+            play() {{
+                if (this.playing) return;
+                this.playing = true;
+                if (this.clips.length === 0) {{
+                    dioxus.send('ended');
+                }} else if (audio.src && this.active) {{
+                    audio.play();
+                    this.watch();
+                }} else {{
+                    this.start();
+                }}
+            }},
+            pause() {{
+                this.playing = false;
+                audio.pause();
+            }},
+            // 停止播放并释放音频，之后这个播放器不再使用
+            stop() {{
+                this.pause();
+                cancelAnimationFrame(frame);
+                this.highlight(null);
+                this.clips = [];
+                audio.removeAttribute('src');
+                audio.load();
+            }},
+            setRate(rate) {{
+                audio.defaultPlaybackRate = rate;
+                audio.playbackRate = rate;
+            }},
+            highlight(fragment) {{
+                if (this.active) this.active.classList.remove(this.activeClass, 'epub-overlay-active');
+                this.active = null;
+                const view = document.getElementById('{view}');
+                const target = fragment && document.getElementById(fragment);
+                if (!view || !target || !view.contains(target)) return;
+                target.classList.add(this.activeClass, 'epub-overlay-active');
+                this.active = target;
+                const rect = target.getBoundingClientRect();
+                const viewRect = view.getBoundingClientRect();
+                if (rect.top < viewRect.top || rect.bottom > viewRect.bottom
+                    || rect.left < viewRect.left || rect.right > viewRect.right) {{
+                    target.scrollIntoView({{ block: 'center', inline: 'center', behavior: 'smooth' }});
+                }}
+            }},
+        }};
+        audio.addEventListener('ended', () => {{
+            if (player.playing) player.next();
+        }});
+        window.__epubOverlay = player;
+        await new Promise(() => {{}});
+    "#, view = CONTENT_VIEW_ID)
+}
+
+/// 载入当前章节的朗读片段，`playing` 为真时从第一个片段开始朗读
+pub fn load_overlay(overlay: Option<&MediaOverlay>, playing: bool, rate: f64) {
+    let clips: Vec<serde_json::Value> = overlay.map(|overlay| {
+        overlay.clips.iter()
+            .map(|clip| serde_json::json!({
+                "fragment": clip.fragment(),
+                "audio": resource_url(&clip.audio),
+                "begin": clip.begin,
+                "end": clip.end,
+            }))
+            .collect()
+    }).unwrap_or_default();
+    let active_class = overlay.map(|overlay| overlay.active_class.as_str()).unwrap_or_default();
+    document::eval(&format!(
+        "window.__epubOverlay && window.__epubOverlay.load({}, {}, {}, {});",
+        serde_json::Value::from(clips),
+        serde_json::Value::from(active_class),
+        playing,
+        rate,
+    ));
+}
+
+pub fn set_overlay_playing(playing: bool) {
+    let action = if playing { "play" } else { "pause" };
+    document::eval(&format!("window.__epubOverlay && window.__epubOverlay.{}();", action));
+}
+
+/// 阅读器卸载时停止朗读
+pub fn stop_overlay() {
+    document::eval("window.__epubOverlay?.stop(); window.__epubOverlay = null;");
+}
+
+pub fn set_overlay_rate(rate: f64) {
+    document::eval(&format!("window.__epubOverlay && window.__epubOverlay.setRate({});", rate));
+}
+
+/// 朗读控制：播放/暂停和朗读速度
+#[component]
+pub fn ReadAlongControls(playing: Signal<bool>, rate: Signal<f64>) -> Element {
+    rsx! {
+        div { class: "flex items-center gap-2",
+            button {
+                class: "px-4 py-2 bg-gray-100 rounded hover:bg-gray-200",
+                title: "朗读",
+                onclick: move |_| playing.toggle(),
+                if playing() { "⏸ 暂停" } else { "▶ 朗读" }
+            }
+            select {
+                class: "px-2 py-2 bg-gray-100 rounded",
+                title: "朗读速度",
+                value: "{rate}",
+                onchange: move |evt| {
+                    if let Ok(value) = evt.value().parse::<f64>() {
+                        rate.set(value);
+                    }
+                },
+                for value in RATES.iter() {
+                    option { key: "{value}", value: "{value}", "{value}×" }
+                }
+            }
+        }
+    }
+}
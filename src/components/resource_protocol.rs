@@ -0,0 +1,166 @@
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+use dioxus::desktop::wry::http::{header, Request, Response, StatusCode};
+use fast_epub::book::BookArchive;
+use crate::components::read_cover;
+
+// 当前打开的书，协议处理函数在 webview 请求资源时从这里读取
+static CURRENT_BOOK: Mutex<Option<BookArchive>> = Mutex::new(None);
+
+// 最近一次 Range 请求读取的资源。音视频拖动进度时每次只取其中一段，不必每次都重新解压整个文件
+static RANGE_CACHE: Mutex<Option<(String, Resource)>> = Mutex::new(None);
+
+// 资源内容和 MIME 类型
+type Resource = (Arc<Vec<u8>>, String);
+
+pub fn set_current_book(archive: Option<BookArchive>) {
+    *CURRENT_BOOK.lock().unwrap_or_else(|e| e.into_inner()) = archive;
+    *RANGE_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// 处理 `epub://book/<path>` 请求，从打开的书中按需读取资源；
+/// `epub://cover/<key>.jpg` 请求读取书库的封面缩略图
+pub fn handle_resource_request(request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let path = percent_encoding::percent_decode_str(request.uri().path().trim_start_matches('/'))
+        .decode_utf8_lossy()
+        .into_owned();
+
+    // 音视频拖动进度时 webview 会发送 Range 请求
+    let range_header = request.headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok());
+
+    // Windows 和 Android 上主机名为 `epub.cover`
+    let is_cover = request.uri().host()
+        .is_some_and(|host| host == "cover" || host.ends_with(".cover"));
+    let resource = if is_cover {
+        read_cover(&path).map(|data| (Arc::new(data), "image/jpeg".to_string()))
+    } else if range_header.is_some() {
+        read_cached(&path)
+    } else {
+        read_book(&path).map(|(data, mime)| (Arc::new(data), mime))
+    };
+
+    let Some((data, mime)) = resource else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Cow::Borrowed(&[][..]))
+            .unwrap();
+    };
+
+    let total = data.len();
+    let range = range_header.and_then(|v| parse_range(v, total));
+
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::ACCEPT_RANGES, "bytes")
+        // 不同的书可能有同名路径，不能让 webview 缓存
+        .header(header::CACHE_CONTROL, "no-store");
+
+    match range {
+        Some(ByteRange::Satisfiable(start, end)) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total))
+            .body(Cow::Owned(data[start..=end].to_vec()))
+            .unwrap(),
+        Some(ByteRange::Unsatisfiable) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", total))
+            .body(Cow::Borrowed(&[][..]))
+            .unwrap(),
+        None => builder
+            .status(StatusCode::OK)
+            .body(Cow::Owned(Arc::unwrap_or_clone(data)))
+            .unwrap(),
+    }
+}
+
+fn read_book(path: &str) -> Option<(Vec<u8>, String)> {
+    CURRENT_BOOK.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .and_then(|archive| archive.read(path))
+}
+
+// 同一资源的后续 Range 请求直接从缓存中截取
+fn read_cached(path: &str) -> Option<Resource> {
+    let mut cache = RANGE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_path, resource)) = cache.as_ref() {
+        if cached_path == path {
+            return Some(resource.clone());
+        }
+    }
+    let (data, mime) = read_book(path)?;
+    let resource = (Arc::new(data), mime);
+    *cache = Some((path.to_string(), resource.clone()));
+    Some(resource)
+}
+
+// Range 请求头解析的结果，区间的两端都包含在内
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Satisfiable(usize, usize),
+    Unsatisfiable,  // 区间在资源末尾之后，应返回 416
+}
+
+// 只支持单个区间：`bytes=start-end`、`bytes=start-`、`bytes=-suffix`。格式无效时忽略，返回完整内容
+fn parse_range(value: &str, total: usize) -> Option<ByteRange> {
+    let spec = value.strip_prefix("bytes=")?.split(',').next()?.trim();
+    let (start, end) = spec.split_once('-')?;
+    if start.is_empty() {
+        let suffix: usize = end.parse().ok()?;
+        if suffix == 0 || total == 0 {
+            return Some(ByteRange::Unsatisfiable);
+        }
+        return Some(ByteRange::Satisfiable(total.saturating_sub(suffix), total - 1));
+    }
+    let start: usize = start.parse().ok()?;
+    let end = if end.is_empty() { None } else { Some(end.parse::<usize>().ok()?) };
+    if end.is_some_and(|end| end < start) {
+        return None;
+    }
+    if start >= total {
+        return Some(ByteRange::Unsatisfiable);
+    }
+    Some(ByteRange::Satisfiable(start, end.map_or(total - 1, |end| end.min(total - 1))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_and_open_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(ByteRange::Satisfiable(0, 99)));
+        assert_eq!(parse_range("bytes=500-", 1000), Some(ByteRange::Satisfiable(500, 999)));
+        // 结尾超出资源长度时截到末尾
+        assert_eq!(parse_range("bytes=900-2000", 1000), Some(ByteRange::Satisfiable(900, 999)));
+    }
+
+    #[test]
+    fn suffix_ranges() {
+        assert_eq!(parse_range("bytes=-100", 1000), Some(ByteRange::Satisfiable(900, 999)));
+        assert_eq!(parse_range("bytes=-5000", 1000), Some(ByteRange::Satisfiable(0, 999)));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(ByteRange::Unsatisfiable));
+    }
+
+    #[test]
+    fn multiple_ranges_use_the_first() {
+        assert_eq!(parse_range("bytes=0-1, 5-6", 1000), Some(ByteRange::Satisfiable(0, 1)));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(ByteRange::Unsatisfiable));
+        assert_eq!(parse_range("bytes=1500-1600", 1000), Some(ByteRange::Unsatisfiable));
+        assert_eq!(parse_range("bytes=0-", 0), Some(ByteRange::Unsatisfiable));
+    }
+
+    #[test]
+    fn invalid_headers_are_ignored() {
+        assert_eq!(parse_range("bytes=5-1", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_range("bytes=", 1000), None);
+    }
+}
@@ -2,6 +2,7 @@ use std::fs;
 use std::path::PathBuf;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
+use fast_epub::book::{BookMetadata, ChapterPatterns, DEFAULT_CHAPTER_CACHE_BYTES};
 
 #[derive(Serialize, Deserialize, Clone, Default, PartialEq)]  // 添加 PartialEq
 pub struct BookInfo {
@@ -10,6 +11,16 @@ pub struct BookInfo {
     pub author: String,
     pub last_read: chrono::DateTime<chrono::Utc>,
     pub chapter_index: usize,
+    #[serde(default)]
+    pub series: Option<String>,
+    #[serde(default)]
+    pub series_index: Option<f64>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub publisher: Option<String>,
+    #[serde(default)]
+    pub cover: Option<String>,  // 封面缩略图在缓存目录中的键
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -17,21 +28,124 @@ pub struct AppState {
     pub last_book: Option<String>,
     pub reading_progress: HashMap<String, ReadingProgress>,
     pub library: Vec<BookInfo>,  // 添加书库
+    #[serde(default)]
+    pub settings: ReaderSettings,
+    #[serde(default)]
+    pub directions: HashMap<String, ReadingDirection>,  // 用户为单本书指定的翻页方向
+}
+
+/// 翻页方向，Auto 表示按书籍声明
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum ReadingDirection {
+    #[default]
+    Auto,
+    Ltr,
+    Rtl,
+}
+
+impl ReadingDirection {
+    /// 菜单中依次切换：按书籍声明 → 从右到左 → 从左到右
+    pub fn next(self) -> Self {
+        match self {
+            ReadingDirection::Auto => ReadingDirection::Rtl,
+            ReadingDirection::Rtl => ReadingDirection::Ltr,
+            ReadingDirection::Ltr => ReadingDirection::Auto,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReadingDirection::Auto => "按书籍设置",
+            ReadingDirection::Ltr => "从左到右",
+            ReadingDirection::Rtl => "从右到左",
+        }
+    }
+}
+
+/// 漫画页面的显示方式
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum ComicFit {
+    #[default]
+    FitPage,
+    FitWidth,
+    TwoPage,
+}
+
+impl ComicFit {
+    /// 菜单中依次切换：适合页面 → 适合宽度 → 双页
+    pub fn next(self) -> Self {
+        match self {
+            ComicFit::FitPage => ComicFit::FitWidth,
+            ComicFit::FitWidth => ComicFit::TwoPage,
+            ComicFit::TwoPage => ComicFit::FitPage,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ComicFit::FitPage => "适合页面",
+            ComicFit::FitWidth => "适合宽度",
+            ComicFit::TwoPage => "双页",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ReaderSettings {
+    pub chapter_cache_mb: usize,  // 章节缓存的内存上限
+    pub publisher_styles: bool,  // 使用书籍自带的样式
+    pub txt_volume_patterns: Vec<String>,   // TXT 小说卷标题的正则表达式
+    pub txt_chapter_patterns: Vec<String>,  // TXT 小说章节标题的正则表达式
+    pub comic_fit: ComicFit,
+}
+
+impl Default for ReaderSettings {
+    fn default() -> Self {
+        let patterns = ChapterPatterns::default();
+        Self {
+            chapter_cache_mb: DEFAULT_CHAPTER_CACHE_BYTES / (1024 * 1024),
+            publisher_styles: true,
+            txt_volume_patterns: patterns.volumes,
+            txt_chapter_patterns: patterns.chapters,
+            comic_fit: ComicFit::default(),
+        }
+    }
+}
+
+impl ReaderSettings {
+    pub fn chapter_cache_bytes(&self) -> usize {
+        self.chapter_cache_mb * 1024 * 1024
+    }
+
+    pub fn chapter_patterns(&self) -> ChapterPatterns {
+        ChapterPatterns {
+            volumes: self.txt_volume_patterns.clone(),
+            chapters: self.txt_chapter_patterns.clone(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct ReadingProgress {
     pub chapter_index: usize,
     pub last_read: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub spine_index: Option<usize>,  // 所在的书脊项，漫画等目录只指向章节开头的书按它恢复到具体页面
 }
 
 const STATE_FILE: &str = "app_state.json";
 
+/// 应用的配置目录，阅读状态和封面缓存等都放在这里
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .map(|d| d.join("fast_epub"))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
 impl AppState {
     pub fn load() -> Self {
-        let config_dir = dirs::config_dir()
-            .map(|d| d.join("fast_epub"))
-            .unwrap_or_else(|| PathBuf::from("."));
+        let config_dir = config_dir();
 
         if !config_dir.exists() {
             let _ = fs::create_dir_all(&config_dir);
@@ -45,9 +159,7 @@ impl AppState {
     }
 
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let config_dir = dirs::config_dir()
-            .map(|d| d.join("fast_epub"))
-            .unwrap_or_else(|| PathBuf::from("."));
+        let config_dir = config_dir();
 
         if !config_dir.exists() {
             fs::create_dir_all(&config_dir)?;
@@ -59,11 +171,12 @@ impl AppState {
         Ok(())
     }
 
-    pub fn update_progress(&mut self, book_path: String, chapter: usize) {
+    pub fn update_progress(&mut self, book_path: String, chapter: usize, spine_index: usize) {
         // 更新阅读进度
         self.reading_progress.insert(book_path.clone(), ReadingProgress {
             chapter_index: chapter,
             last_read: chrono::Utc::now(),
+            spine_index: Some(spine_index),
         });
         
         // 更新最后打开的书
@@ -85,6 +198,10 @@ impl AppState {
         self.reading_progress.get(book_path).map(|p| p.chapter_index)
     }
 
+    pub fn get_spine_progress(&self, book_path: &str) -> Option<usize> {
+        self.reading_progress.get(book_path).and_then(|p| p.spine_index)
+    }
+
     pub fn get_last_book(&self) -> Option<(String, usize)> {
         self.last_book.as_ref().and_then(|path| {
             self.reading_progress
@@ -93,15 +210,48 @@ impl AppState {
         })
     }
 
-    pub fn add_to_library(&mut self, path: String, title: String, author: String, chapter: usize) {
-        if !self.library.iter().any(|book| book.path == path) {
-            self.library.push(BookInfo {
-                path,
-                title,
-                author,
-                last_read: chrono::Utc::now(),
-                chapter_index: chapter,
-            });
+    /// 把书加入书库，已在书库中的书更新书名、作者、丛书和封面等信息
+    pub fn add_to_library(
+        &mut self,
+        path: String,
+        metadata: &BookMetadata,
+        cover: Option<String>,
+        chapter: usize,
+    ) {
+        let info = BookInfo {
+            path,
+            title: metadata.title.clone().unwrap_or_else(|| "未知标题".to_string()),
+            author: metadata.author.clone().unwrap_or_else(|| "未知作者".to_string()),
+            last_read: chrono::Utc::now(),
+            chapter_index: chapter,
+            series: metadata.series.as_ref().map(|s| s.name.clone()),
+            series_index: metadata.series.as_ref().and_then(|s| s.index),
+            language: metadata.language.clone(),
+            publisher: metadata.publisher.clone(),
+            cover,
+        };
+        match self.library.iter_mut().find(|book| book.path == info.path) {
+            // 保留原有的阅读进度
+            Some(book) => *book = BookInfo {
+                last_read: book.last_read,
+                chapter_index: book.chapter_index,
+                ..info
+            },
+            None => self.library.push(info),
+        }
+        let _ = self.save();
+    }
+
+    pub fn direction(&self, book_path: &str) -> ReadingDirection {
+        self.directions.get(book_path).copied().unwrap_or_default()
+    }
+
+    /// 记录单本书的翻页方向，Auto 时删除记录
+    pub fn set_direction(&mut self, book_path: String, direction: ReadingDirection) {
+        if direction == ReadingDirection::Auto {
+            self.directions.remove(&book_path);
+        } else {
+            self.directions.insert(book_path, direction);
         }
         let _ = self.save();
     }
@@ -0,0 +1,2 @@
+//! FastEpub 的核心库，不依赖界面，可供桌面阅读器、命令行工具和测试共用。
+pub mod book;
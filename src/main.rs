@@ -6,15 +6,20 @@ use dioxus::desktop::{Config, WindowBuilder};
 use dioxus::document::Stylesheet;
 
 mod components;
-use components::{Header, EpubReader, AppState, Library};
+use components::{Header, EpubReader, AppState, Library, handle_resource_request};
+use fast_epub::book::RESOURCE_SCHEME;
 fn main() {
     dioxus::LaunchBuilder::desktop()
-        .with_cfg(Config::new().with_window(
-            WindowBuilder::new()
-                .with_resizable(true)
-                .with_decorations(false) // Disable native window decorations
-                .with_title("FastEpub")
-        ))
+        .with_cfg(Config::new()
+            .with_window(
+                WindowBuilder::new()
+                    .with_resizable(true)
+                    .with_decorations(false) // Disable native window decorations
+                    .with_title("FastEpub")
+            )
+            // 书中的图片、样式、字体和音视频通过 epub:// 协议按需读取
+            .with_custom_protocol(RESOURCE_SCHEME, handle_resource_request)
+        )
         .launch(App)
 }
 